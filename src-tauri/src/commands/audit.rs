@@ -0,0 +1,19 @@
+use crate::error::Result;
+use crate::services::request_guard::{AuditEntry, AuditLogQuery};
+use crate::state::AppState;
+use tauri::State;
+
+/// Get requests made to the manifest, trigger and streaming servers (newest first)
+#[tauri::command]
+pub async fn get_request_audit_log(
+    state: State<'_, AppState>,
+    query: Option<AuditLogQuery>,
+) -> Result<Vec<AuditEntry>> {
+    Ok(state.request_audit.query(&query.unwrap_or_default()))
+}
+
+/// Clear the request audit log
+#[tauri::command]
+pub async fn clear_request_audit_log(state: State<'_, AppState>) -> Result<()> {
+    state.request_audit.clear()
+}
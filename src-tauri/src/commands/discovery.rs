@@ -0,0 +1,121 @@
+use crate::error::{ArchivistError, Result};
+use crate::node_api::NodeApiClient;
+use crate::services::config::{BackupTarget, SourcePeerConfig};
+use crate::services::discovery::{DiscoveredDevice, DiscoveryService, CAP_BACKUP_DAEMON};
+use crate::state::AppState;
+use tauri::State;
+use tokio::time::Duration;
+
+/// Browse the local network for other Archivist devices
+#[tauri::command]
+pub async fn discover_devices(
+    state: State<'_, AppState>,
+    timeout_ms: Option<u64>,
+) -> Result<Vec<DiscoveredDevice>> {
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(2000).clamp(250, 10_000));
+    let devices = DiscoveryService::browse(timeout).await?;
+
+    // Hide this device from the results
+    let api_port = state.node.read().await.get_config().api_port;
+    let own_peer_id = NodeApiClient::new(api_port)
+        .get_info()
+        .await
+        .ok()
+        .map(|info| info.id);
+
+    Ok(devices
+        .into_iter()
+        .filter(|d| own_peer_id.is_none() || d.peer_id != own_peer_id)
+        .collect())
+}
+
+/// Add a discovered device as a source peer for the backup daemon
+#[tauri::command]
+pub async fn add_discovered_source_peer(
+    state: State<'_, AppState>,
+    device: DiscoveredDevice,
+) -> Result<SourcePeerConfig> {
+    let source_peer = device.to_source_peer_config()?;
+
+    let mut config_service = state.config.write().await;
+    let mut config = config_service.get();
+
+    // Replace an existing entry for the same device instead of duplicating it
+    config.backup_server.source_peers.retain(|p| {
+        let same_peer = source_peer.peer_id.is_some() && p.peer_id == source_peer.peer_id;
+        let same_host = p.host == source_peer.host && p.manifest_port == source_peer.manifest_port;
+        !(same_peer || same_host)
+    });
+    config.backup_server.source_peers.push(source_peer.clone());
+    let backup_settings = config.backup_server.clone();
+    config_service.update(config)?;
+    drop(config_service);
+
+    state.backup_daemon.apply_settings(&backup_settings).await;
+
+    log::info!(
+        "Added discovered source peer {} ({}:{})",
+        source_peer.nickname,
+        source_peer.host,
+        source_peer.manifest_port
+    );
+
+    Ok(source_peer)
+}
+
+/// Use a discovered device as this machine's backup peer
+#[tauri::command]
+pub async fn set_discovered_backup_peer(
+    state: State<'_, AppState>,
+    device: DiscoveredDevice,
+) -> Result<()> {
+    let trigger_port = device
+        .trigger_port
+        .filter(|_| device.has_capability(CAP_BACKUP_DAEMON))
+        .ok_or_else(|| {
+            ArchivistError::ConfigError(format!(
+                "Device {} does not advertise a backup daemon",
+                device.instance_name
+            ))
+        })?;
+    let multiaddr = device.multiaddr.clone().ok_or_else(|| {
+        ArchivistError::ConfigError(format!(
+            "Device {} did not advertise a peer ID",
+            device.instance_name
+        ))
+    })?;
+
+    let mut config_service = state.config.write().await;
+    let mut config = config_service.get();
+    // Keep any existing targets (including the legacy single peer) and add
+    // this device alongside them
+    let mut targets = config.sync.backup_targets();
+    match targets.iter_mut().find(|t| t.peer_address == multiaddr) {
+        Some(target) => {
+            target.nickname = device.instance_name.clone();
+            target.trigger_port = trigger_port;
+            target.enabled = true;
+        }
+        None => targets.push(BackupTarget {
+            id: uuid::Uuid::new_v4().to_string(),
+            nickname: device.instance_name.clone(),
+            peer_address: multiaddr.clone(),
+            trigger_port,
+            enabled: true,
+        }),
+    }
+    config.sync.backup_targets = targets;
+    config.sync.backup_peer_address = Some(multiaddr.clone());
+    config.sync.backup_peer_nickname = Some(device.instance_name.clone());
+    config.sync.backup_trigger_port = trigger_port;
+    config_service.update(config)?;
+
+    log::info!(
+        "Backup peer set to discovered device {} ({}, trigger port {})",
+        device.instance_name,
+        multiaddr,
+        trigger_port
+    );
+
+    Ok(())
+}
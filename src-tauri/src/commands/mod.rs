@@ -1,18 +1,24 @@
 // Tauri command handlers
 
+pub mod audit;
+pub mod discovery;
 pub mod files;
 pub mod media;
 pub mod node;
 pub mod peers;
+pub mod restore;
 pub mod streaming;
 pub mod sync;
 pub mod system;
 
 // Re-export all commands for registration
+pub use audit::*;
+pub use discovery::*;
 pub use files::*;
 pub use media::*;
 pub use node::*;
 pub use peers::*;
+pub use restore::*;
 pub use streaming::*;
 pub use sync::*;
 pub use system::*;
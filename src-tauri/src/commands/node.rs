@@ -1,8 +1,12 @@
-use crate::error::Result;
+use crate::error::{ArchivistError, Result};
 use crate::services::node::{NodeConfig, NodeStatus};
+use crate::services::node_logs::{
+    node_log_path, query_logs, tail_lines, NodeLogLevel, NodeLogPage, NodeLogQuery,
+};
+use crate::services::DiscoveryService;
 use crate::state::AppState;
 use serde::{Deserialize, Serialize};
-use std::io::BufRead;
+use std::collections::BTreeMap;
 use tauri::{AppHandle, State};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,8 +36,14 @@ pub async fn start_node(app_handle: AppHandle, state: State<'_, AppState>) -> Re
             break;
         }
     }
+    let status = node.get_status();
+    drop(node);
 
-    Ok(node.get_status())
+    // Announce the (possibly new) peer ID right away
+    let app_config = state.config.read().await.get();
+    DiscoveryService::refresh(&state.discovery, &app_config).await;
+
+    Ok(status)
 }
 
 #[tauri::command]
@@ -124,7 +134,7 @@ pub async fn get_node_logs(
     let config = node.get_config();
 
     // Construct log file path (inside data_dir)
-    let log_file = std::path::Path::new(&config.data_dir).join("node.log");
+    let log_file = node_log_path(&config.data_dir);
 
     if !log_file.exists() {
         return Ok(vec![
@@ -132,29 +142,63 @@ pub async fn get_node_logs(
         ]);
     }
 
-    // Read the log file
-    // On Windows, we need to explicitly allow sharing to read files being written by the node
-    #[cfg(target_os = "windows")]
-    let file = {
-        use std::fs::OpenOptions;
-        use std::os::windows::fs::OpenOptionsExt;
-        OpenOptions::new()
-            .read(true)
-            .share_mode(0x00000001 | 0x00000002) // FILE_SHARE_READ | FILE_SHARE_WRITE
-            .open(&log_file)?
-    };
-
-    #[cfg(not(target_os = "windows"))]
-    let file = std::fs::File::open(&log_file)?;
-
-    let reader = std::io::BufReader::new(file);
-    let all_lines: Vec<String> = reader.lines().map_while(|line| line.ok()).collect();
-
-    // Return last N lines (default: 500)
-    let num_lines = lines.unwrap_or(500);
-    let start_index = all_lines.len().saturating_sub(num_lines);
-
-    Ok(all_lines[start_index..].to_vec())
+    // Return last N lines (default: 500), reading backwards from the end
+    tail_lines(&log_file, lines.unwrap_or(500))
+}
+
+/// Query the node's logs (including rotated files) by level, text and time
+/// range, one page at a time from the newest entries
+#[tauri::command]
+pub async fn query_node_logs(
+    state: State<'_, AppState>,
+    query: NodeLogQuery,
+) -> Result<NodeLogPage> {
+    let node = state.node.read().await;
+    let log_file = node_log_path(&node.get_config().data_dir);
+    drop(node);
+
+    tokio::task::spawn_blocking(move || query_logs(&log_file, &query))
+        .await
+        .map_err(|e| ArchivistError::FileOperationFailed(format!("Log query failed: {}", e)))?
+}
+
+/// Start emitting new node log lines as `node-log-lines` events
+#[tauri::command]
+pub async fn subscribe_node_logs(state: State<'_, AppState>) -> Result<()> {
+    state.node.read().await.set_log_streaming(true);
+    Ok(())
+}
+
+/// Stop emitting node log lines, e.g. when the logs view is closed
+#[tauri::command]
+pub async fn unsubscribe_node_logs(state: State<'_, AppState>) -> Result<()> {
+    state.node.read().await.set_log_streaming(false);
+    Ok(())
+}
+
+/// Change the node's log level, with optional per-topic levels, and persist
+/// it. Returns the chronicles level setting that was applied.
+#[tauri::command]
+pub async fn set_node_log_level(
+    state: State<'_, AppState>,
+    level: NodeLogLevel,
+    topics: Option<BTreeMap<String, NodeLogLevel>>,
+) -> Result<String> {
+    let topics = topics.unwrap_or_default();
+    let spec = state
+        .node
+        .write()
+        .await
+        .set_log_level(level, topics.clone())
+        .await?;
+
+    let mut config_service = state.config.write().await;
+    let mut config = config_service.get();
+    config.node.log_level = level.as_chronicles().to_string();
+    config.node.log_topics = topics;
+    config_service.update(config)?;
+
+    Ok(spec)
 }
 
 #[tauri::command]
@@ -162,7 +206,7 @@ pub async fn get_node_log_path(state: State<'_, AppState>) -> Result<String> {
     let node = state.node.read().await;
     let config = node.get_config();
 
-    let log_file = std::path::Path::new(&config.data_dir).join("node.log");
+    let log_file = node_log_path(&config.data_dir);
 
     Ok(log_file.to_string_lossy().to_string())
 }
@@ -0,0 +1,93 @@
+use crate::error::{ArchivistError, Result};
+use crate::services::backup_daemon::ProcessedManifest;
+use crate::services::restore::{RestoreJob, RestoreRequest, SnapshotFile};
+use crate::state::AppState;
+use tauri::{AppHandle, State};
+
+/// Restore a backed-up folder (or selected paths in it) to disk from one of
+/// its snapshots. Uses the latest processed manifest when none is given.
+#[tauri::command]
+pub async fn start_folder_restore(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    request: RestoreRequest,
+) -> Result<RestoreJob> {
+    let manifest_cid = match &request.manifest_cid {
+        Some(cid) => cid.clone(),
+        None => state
+            .backup_daemon
+            .latest_processed_manifest(&request.source_peer_id, &request.folder_id)
+            .await
+            .map(|m| m.manifest_cid)
+            .ok_or_else(|| {
+                ArchivistError::RestoreError(format!(
+                    "No backed-up manifest found for folder {}",
+                    request.folder_id
+                ))
+            })?,
+    };
+
+    // Files of pruned snapshots are gone from storage once their deletion
+    // grace period has passed
+    if let Some(snapshot) = state
+        .backup_daemon
+        .get_processed_manifest(&manifest_cid)
+        .await
+    {
+        if !state.backup_daemon.snapshot_restorable(&snapshot) {
+            return Err(ArchivistError::RestoreError(format!(
+                "Snapshot {} (sequence {}) is no longer retained",
+                manifest_cid, snapshot.sequence_number
+            )));
+        }
+    }
+
+    state
+        .restore
+        .start_restore(app_handle, request, &manifest_cid)
+        .await
+}
+
+/// Get the snapshot history of a backed-up folder (newest first)
+#[tauri::command]
+pub async fn get_folder_snapshots(
+    state: State<'_, AppState>,
+    source_peer_id: String,
+    folder_id: String,
+) -> Result<Vec<ProcessedManifest>> {
+    Ok(state
+        .backup_daemon
+        .folder_snapshots(&source_peer_id, &folder_id)
+        .await)
+}
+
+/// List the files in a folder as of a snapshot
+#[tauri::command]
+pub async fn get_snapshot_files(
+    state: State<'_, AppState>,
+    manifest_cid: String,
+) -> Result<Vec<SnapshotFile>> {
+    state.restore.snapshot_files(&manifest_cid).await
+}
+
+/// Resume an interrupted, cancelled or partially failed restore
+#[tauri::command]
+pub async fn resume_folder_restore(
+    app_handle: AppHandle,
+    state: State<'_, AppState>,
+    job_id: String,
+) -> Result<RestoreJob> {
+    state.restore.resume_restore(app_handle, &job_id).await
+}
+
+/// Stop a running restore after the current file
+#[tauri::command]
+pub async fn cancel_folder_restore(state: State<'_, AppState>, job_id: String) -> Result<()> {
+    state.restore.cancel_restore(&job_id).await
+}
+
+/// Get all restore jobs with their progress (newest first)
+#[tauri::command]
+pub async fn get_restore_jobs(state: State<'_, AppState>) -> Result<Vec<RestoreJob>> {
+    Ok(state.restore.get_jobs().await)
+}
@@ -1,6 +1,11 @@
 use crate::error::{ArchivistError, Result};
-use crate::services::backup_daemon::DaemonState;
-use crate::services::manifest_server::ManifestInfo;
+use crate::services::backup::{BackupTargetStatus, TargetNotifyResult};
+use crate::services::backup_daemon::{DaemonState, PendingDeletion};
+use crate::services::backup_history::{self, FolderBackupSummary, HistoryFormat};
+use crate::services::backup_lag::{self, FolderLag};
+use crate::services::config::{FolderMirrorRule, SourcePeerConfig};
+use crate::services::manifest_server::{BackupAckRecord, ManifestInfo};
+use crate::services::scrubber::IntegrityReport;
 use crate::services::sync::{SyncState, WatchedFolder};
 use crate::state::AppState;
 use chrono::Utc;
@@ -84,8 +89,14 @@ pub async fn generate_folder_manifest(
     Ok(manifest_cid)
 }
 
+/// Notify backup targets about a folder's current manifest: every enabled
+/// target, or only `target_id` when given
 #[tauri::command]
-pub async fn notify_backup_peer(state: State<'_, AppState>, folder_id: String) -> Result<()> {
+pub async fn notify_backup_peer(
+    state: State<'_, AppState>,
+    folder_id: String,
+    target_id: Option<String>,
+) -> Result<Vec<TargetNotifyResult>> {
     // Get manifest CID for folder
     let sync = state.sync.read().await;
     let folder = sync
@@ -96,6 +107,7 @@ pub async fn notify_backup_peer(state: State<'_, AppState>, folder_id: String) -
         .manifest_cid
         .clone()
         .ok_or_else(|| ArchivistError::SyncError("No manifest generated yet".into()))?;
+    let sequence_number = folder.manifest_sequence;
 
     drop(sync);
 
@@ -135,29 +147,72 @@ pub async fn notify_backup_peer(state: State<'_, AppState>, folder_id: String) -
         manifest_cid
     );
 
-    // 2. Get backup peer address and trigger port from config
+    // 2. Get backup targets (and the secret triggers are signed with) from config
     let config = state.config.read().await;
     let app_config = config.get();
-    let backup_addr = app_config
-        .sync
-        .backup_peer_address
-        .clone()
-        .ok_or_else(|| ArchivistError::ConfigError("No backup peer configured".into()))?;
-    let trigger_port = app_config.sync.backup_trigger_port;
+    let mut targets = app_config.sync.backup_targets();
+    let secret = app_config.manifest_server.ack_secret;
     drop(config);
 
-    // 3. Notify backup peer via HTTP trigger
-    let backup = state.backup.read().await;
-    backup
-        .notify_backup_peer(&manifest_cid, &backup_addr, trigger_port)
-        .await?;
+    if let Some(target_id) = &target_id {
+        targets.retain(|t| &t.id == target_id);
+        if targets.is_empty() {
+            return Err(ArchivistError::ConfigError(format!(
+                "Backup target not found: {}",
+                target_id
+            )));
+        }
+    }
+    if !targets.iter().any(|t| t.enabled) {
+        return Err(ArchivistError::ConfigError(
+            "No backup peer configured".into(),
+        ));
+    }
+
+    // 3. Notify each target via HTTP trigger
+    let mut backup = state.backup.write().await;
+    let results = backup
+        .notify_targets(
+            &targets,
+            &folder_id,
+            &manifest_cid,
+            sequence_number,
+            secret.as_deref(),
+        )
+        .await;
+    drop(backup);
+
+    if results.iter().all(|r| !r.success) {
+        let errors: Vec<String> = results
+            .iter()
+            .map(|r| format!("{}: {}", r.nickname, r.error.clone().unwrap_or_default()))
+            .collect();
+        return Err(ArchivistError::SyncError(format!(
+            "Failed to notify backup targets: {}",
+            errors.join("; ")
+        )));
+    }
 
     log::info!(
-        "Successfully notified backup peer to poll for manifest: {}",
+        "Notified {}/{} backup targets to poll for manifest: {}",
+        results.iter().filter(|r| r.success).count(),
+        results.len(),
         manifest_cid
     );
 
-    Ok(())
+    Ok(results)
+}
+
+/// Configured backup targets with the sequence each one was triggered for
+/// and has acknowledged, per folder
+#[tauri::command]
+pub async fn get_backup_targets_status(
+    state: State<'_, AppState>,
+) -> Result<Vec<BackupTargetStatus>> {
+    let targets = state.config.read().await.get().sync.backup_targets();
+    let registry = state.manifest_registry.read().await;
+    let backup = state.backup.read().await;
+    Ok(backup.target_statuses(&targets, &registry))
 }
 
 #[tauri::command]
@@ -172,6 +227,16 @@ pub async fn test_backup_peer_connection(
     }
 }
 
+/// Acknowledgements backup peers sent for a watched folder, one per backup peer
+#[tauri::command]
+pub async fn get_backup_acks(
+    state: State<'_, AppState>,
+    folder_id: String,
+) -> Result<Vec<BackupAckRecord>> {
+    let registry = state.manifest_registry.read().await;
+    Ok(registry.get_acks(&folder_id))
+}
+
 // ========== Backup Daemon Commands ==========
 
 #[tauri::command]
@@ -180,6 +245,40 @@ pub async fn get_backup_daemon_state(state: State<'_, AppState>) -> Result<Daemo
     Ok(daemon_state)
 }
 
+/// Export the backup history (processed and failed manifests, optionally
+/// limited to a date range) as JSON or CSV
+#[tauri::command]
+pub async fn export_backup_history(
+    state: State<'_, AppState>,
+    format: HistoryFormat,
+    from: Option<chrono::DateTime<Utc>>,
+    to: Option<chrono::DateTime<Utc>>,
+) -> Result<String> {
+    let daemon_state = state.backup_daemon.get_state().await;
+    let entries = backup_history::history_entries(&daemon_state, from, to);
+    backup_history::export_history(&entries, format)
+}
+
+/// When each backed-up folder was last fully backed up, and its lag
+#[tauri::command]
+pub async fn get_backup_summary(state: State<'_, AppState>) -> Result<Vec<FolderBackupSummary>> {
+    let daemon_state = state.backup_daemon.get_state().await;
+    Ok(backup_history::folder_summaries(&daemon_state, Utc::now()))
+}
+
+/// How far each mirrored folder is behind its source, against the configured
+/// lag alert thresholds
+#[tauri::command]
+pub async fn get_backup_lag(state: State<'_, AppState>) -> Result<Vec<FolderLag>> {
+    let settings = state.config.read().await.get().backup_server.lag_alerts;
+    let daemon_state = state.backup_daemon.get_state().await;
+    Ok(backup_lag::folder_lags(
+        &daemon_state,
+        &settings,
+        Utc::now(),
+    ))
+}
+
 #[tauri::command]
 pub async fn enable_backup_daemon(state: State<'_, AppState>) -> Result<()> {
     // Enable in-memory flag
@@ -231,6 +330,218 @@ pub async fn retry_failed_manifest(state: State<'_, AppState>, manifest_cid: Str
     Ok(())
 }
 
+/// Apply a change to the configured source peers, persist it and push the new
+/// list to the running daemon
+async fn update_source_peers<F>(state: &State<'_, AppState>, change: F) -> Result<()>
+where
+    F: FnOnce(&mut Vec<SourcePeerConfig>) -> Result<()>,
+{
+    let mut config_service = state.config.write().await;
+    let mut config = config_service.get();
+    change(&mut config.backup_server.source_peers)?;
+    let backup_settings = config.backup_server.clone();
+    config_service.update(config)?;
+    drop(config_service);
+
+    state.backup_daemon.apply_settings(&backup_settings).await;
+    Ok(())
+}
+
+fn validate_source_peer(peer: &SourcePeerConfig) -> Result<()> {
+    if peer.host.trim().is_empty() {
+        return Err(ArchivistError::ConfigError(
+            "Source peer host is required".into(),
+        ));
+    }
+    if peer.manifest_port == 0 {
+        return Err(ArchivistError::ConfigError(
+            "Source peer manifest port is required".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Add a source peer for the backup daemon to poll
+#[tauri::command]
+pub async fn add_source_peer(
+    state: State<'_, AppState>,
+    peer: SourcePeerConfig,
+) -> Result<SourcePeerConfig> {
+    validate_source_peer(&peer)?;
+
+    let added = peer.clone();
+    update_source_peers(&state, move |peers| {
+        if peers
+            .iter()
+            .any(|p| p.host == peer.host && p.manifest_port == peer.manifest_port)
+        {
+            return Err(ArchivistError::ConfigError(format!(
+                "Source peer {}:{} is already configured",
+                peer.host, peer.manifest_port
+            )));
+        }
+        peers.push(peer);
+        Ok(())
+    })
+    .await?;
+
+    log::info!(
+        "Added source peer {} ({}:{})",
+        added.nickname,
+        added.host,
+        added.manifest_port
+    );
+    Ok(added)
+}
+
+/// Replace the source peer at `host:manifest_port` with `peer`
+#[tauri::command]
+pub async fn update_source_peer(
+    state: State<'_, AppState>,
+    host: String,
+    manifest_port: u16,
+    peer: SourcePeerConfig,
+) -> Result<SourcePeerConfig> {
+    validate_source_peer(&peer)?;
+
+    let updated = peer.clone();
+    update_source_peers(&state, move |peers| {
+        let index = peers
+            .iter()
+            .position(|p| p.host == host && p.manifest_port == manifest_port)
+            .ok_or_else(|| {
+                ArchivistError::ConfigError(format!(
+                    "Source peer not found: {}:{}",
+                    host, manifest_port
+                ))
+            })?;
+        let collides = peers.iter().enumerate().any(|(i, p)| {
+            i != index && p.host == peer.host && p.manifest_port == peer.manifest_port
+        });
+        if collides {
+            return Err(ArchivistError::ConfigError(format!(
+                "Source peer {}:{} is already configured",
+                peer.host, peer.manifest_port
+            )));
+        }
+        peers[index] = peer;
+        Ok(())
+    })
+    .await?;
+
+    log::info!(
+        "Updated source peer {} ({}:{})",
+        updated.nickname,
+        updated.host,
+        updated.manifest_port
+    );
+    Ok(updated)
+}
+
+/// Stop polling the source peer at `host:manifest_port`. Content already
+/// backed up from it is kept.
+#[tauri::command]
+pub async fn remove_source_peer(
+    state: State<'_, AppState>,
+    host: String,
+    manifest_port: u16,
+) -> Result<()> {
+    update_source_peers(&state, |peers| {
+        let before = peers.len();
+        peers.retain(|p| !(p.host == host && p.manifest_port == manifest_port));
+        if peers.len() == before {
+            return Err(ArchivistError::ConfigError(format!(
+                "Source peer not found: {}:{}",
+                host, manifest_port
+            )));
+        }
+        Ok(())
+    })
+    .await?;
+
+    log::info!("Removed source peer {}:{}", host, manifest_port);
+    Ok(())
+}
+
+/// Set how one of a source's folders is mirrored, replacing any earlier rule
+/// for the folder. The new rule applies from the folder's next manifest.
+#[tauri::command]
+pub async fn set_folder_mirror_rule(
+    state: State<'_, AppState>,
+    host: String,
+    manifest_port: u16,
+    rule: FolderMirrorRule,
+) -> Result<()> {
+    if rule.folder_id.trim().is_empty() {
+        return Err(ArchivistError::ConfigError("Folder ID is required".into()));
+    }
+
+    let folder_id = rule.folder_id.clone();
+    update_source_peers(&state, |peers| {
+        let peer = peers
+            .iter_mut()
+            .find(|p| p.host == host && p.manifest_port == manifest_port)
+            .ok_or_else(|| {
+                ArchivistError::ConfigError(format!(
+                    "Source peer not found: {}:{}",
+                    host, manifest_port
+                ))
+            })?;
+        peer.folder_rules.retain(|r| r.folder_id != rule.folder_id);
+        peer.folder_rules.push(rule);
+        Ok(())
+    })
+    .await?;
+
+    log::info!(
+        "Updated mirroring of folder {} from {}:{}",
+        folder_id,
+        host,
+        manifest_port
+    );
+    Ok(())
+}
+
+/// List tombstoned CIDs waiting out the deletion grace period
+#[tauri::command]
+pub async fn get_pending_deletions(state: State<'_, AppState>) -> Result<Vec<PendingDeletion>> {
+    Ok(state.backup_daemon.pending_deletions().await)
+}
+
+/// Delete pending CIDs now instead of waiting for the grace period to end.
+/// Deletes every pending CID when `cids` is omitted.
+#[tauri::command]
+pub async fn confirm_pending_deletions(
+    state: State<'_, AppState>,
+    cids: Option<Vec<String>>,
+) -> Result<u32> {
+    state.backup_daemon.confirm_pending_deletions(cids).await
+}
+
+/// Get the per-folder health report from the latest integrity scrub
+#[tauri::command]
+pub async fn get_integrity_report(state: State<'_, AppState>) -> Result<IntegrityReport> {
+    Ok(state.scrubber.get_report().await)
+}
+
+/// Start an integrity scrub now (runs in background)
+#[tauri::command]
+pub async fn start_integrity_scrub(state: State<'_, AppState>) -> Result<()> {
+    if state.scrubber.is_running() {
+        return Err(ArchivistError::SyncError(
+            "An integrity scrub is already running".to_string(),
+        ));
+    }
+
+    let scrubber = state.scrubber.clone();
+    tokio::spawn(async move {
+        if let Err(e) = scrubber.run().await {
+            log::warn!("Integrity scrub failed: {}", e);
+        }
+    });
+    Ok(())
+}
+
 // ========== Onboarding Commands ==========
 
 /// Create a quickstart folder for first-run onboarding
@@ -1,6 +1,7 @@
 use crate::error::Result;
 use crate::services::config::AppConfig;
 use crate::services::node::NodeConfig;
+use crate::services::DiscoveryService;
 use crate::state::AppState;
 use tauri::State;
 
@@ -12,15 +13,28 @@ pub async fn get_config(state: State<'_, AppState>) -> Result<AppConfig> {
 
 #[tauri::command]
 pub async fn save_config(state: State<'_, AppState>, config: AppConfig) -> Result<()> {
+    // Reject node launch options the node would fail to start with
+    config.node.launch.validate()?;
+
     // Save to disk via ConfigService
     let mut config_service = state.config.write().await;
     config_service.update(config.clone())?;
     drop(config_service); // Release lock
 
+    // Push backup settings into the running daemon
+    state
+        .backup_daemon
+        .apply_settings(&config.backup_server)
+        .await;
+
     // Sync to NodeService in-memory config
     let node_config = NodeConfig::from_node_settings(&config.node);
     let mut node_service = state.node.write().await;
     node_service.set_config(node_config.clone());
+    drop(node_service);
+
+    // Advertise the new settings (or stop advertising) on the LAN
+    DiscoveryService::refresh(&state.discovery, &config).await;
 
     log::info!(
         "Configuration synced: api_port={}, discovery_port={}, listen_port={}, auto_start={}",
@@ -41,10 +55,18 @@ pub async fn reset_config(state: State<'_, AppState>) -> Result<()> {
     let app_config = config_service.get();
     drop(config_service); // Release lock
 
+    state
+        .backup_daemon
+        .apply_settings(&app_config.backup_server)
+        .await;
+
     // Sync to NodeService in-memory config
     let node_config = NodeConfig::from_node_settings(&app_config.node);
     let mut node_service = state.node.write().await;
     node_service.set_config(node_config);
+    drop(node_service);
+
+    DiscoveryService::refresh(&state.discovery, &app_config).await;
 
     log::info!("Configuration reset to defaults and synced to NodeService");
 
@@ -42,6 +42,12 @@ pub enum ArchivistError {
     #[error("Streaming server error: {0}")]
     StreamingError(String),
 
+    #[error("Restore error: {0}")]
+    RestoreError(String),
+
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 
@@ -5,7 +5,9 @@ pub mod node_api;
 mod services;
 mod state;
 
+use services::backup_lag::BackupLagMonitor;
 use services::node::NodeManager;
+use services::request_guard::{RequestGuard, ServerKind};
 use services::sync::SyncManager;
 use state::AppState;
 
@@ -16,6 +18,10 @@ use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent}
 #[cfg(not(any(target_os = "android", target_os = "ios")))]
 use tauri::Manager;
 
+/// ID of the system tray icon, for updating its status later
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub(crate) const TRAY_ID: &str = "main";
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Create shared app state
@@ -28,6 +34,9 @@ pub fn run() {
     let manifest_server = app_state.manifest_server.clone();
     let media_service = app_state.media.clone();
     let media_streaming = app_state.media_streaming.clone();
+    let discovery_service = app_state.discovery.clone();
+    let request_audit = app_state.request_audit.clone();
+    let scrubber = app_state.scrubber.clone();
 
     let mut builder = tauri::Builder::default()
         .plugin(
@@ -76,6 +85,10 @@ pub fn run() {
             commands::run_node_diagnostics,
             commands::get_node_logs,
             commands::get_node_log_path,
+            commands::query_node_logs,
+            commands::subscribe_node_logs,
+            commands::unsubscribe_node_logs,
+            commands::set_node_log_level,
             // File commands
             commands::list_files,
             commands::upload_file,
@@ -95,15 +108,42 @@ pub fn run() {
             commands::pause_sync,
             commands::generate_folder_manifest,
             commands::notify_backup_peer,
+            commands::get_backup_targets_status,
             commands::test_backup_peer_connection,
+            commands::get_backup_acks,
             commands::create_quickstart_folder,
             // Backup daemon commands
             commands::get_backup_daemon_state,
+            commands::export_backup_history,
+            commands::get_backup_summary,
+            commands::get_backup_lag,
             commands::enable_backup_daemon,
             commands::disable_backup_daemon,
             commands::pause_backup_daemon,
             commands::resume_backup_daemon,
             commands::retry_failed_manifest,
+            commands::add_source_peer,
+            commands::update_source_peer,
+            commands::remove_source_peer,
+            commands::set_folder_mirror_rule,
+            commands::get_pending_deletions,
+            commands::confirm_pending_deletions,
+            commands::get_integrity_report,
+            commands::start_integrity_scrub,
+            // Restore commands
+            commands::start_folder_restore,
+            commands::resume_folder_restore,
+            commands::cancel_folder_restore,
+            commands::get_restore_jobs,
+            commands::get_folder_snapshots,
+            commands::get_snapshot_files,
+            // LAN discovery commands
+            commands::discover_devices,
+            commands::add_discovered_source_peer,
+            commands::set_discovered_backup_peer,
+            // Request audit commands
+            commands::get_request_audit_log,
+            commands::clear_request_audit_log,
             // Peer commands
             commands::get_peers,
             commands::connect_peer,
@@ -217,30 +257,60 @@ pub fn run() {
                 }
             });
 
+            // Watch backup lag: notifications past the thresholds, status in the tray
+            let lag_monitor = BackupLagMonitor::new(
+                backup_daemon.clone(),
+                config_service.clone(),
+                app.handle().clone(),
+            );
+            tauri::async_runtime::spawn(async move {
+                lag_monitor.start_monitoring().await;
+            });
+
             // Configure and start the backup daemon for automatic manifest processing
             let config_for_backup = config_service.clone();
-            let backup_daemon_for_server = backup_daemon.clone();
+            let audit_for_trigger = request_audit.clone();
 
             // Spawn the main daemon loop
             tauri::async_runtime::spawn(async move {
-                // Configure source peers from settings
+                // Configure the daemon (source peers, retention, ...) from settings
                 let config = config_for_backup.read().await;
-                let source_peers = config.get().backup_server.source_peers.clone();
+                let backup_settings = config.get().backup_server;
                 drop(config);
 
-                backup_daemon.set_source_peers(source_peers).await;
+                backup_daemon.apply_settings(&backup_settings).await;
+
+                // Start the HTTP trigger server; later settings changes re-bind it
+                let guard = RequestGuard::new(
+                    ServerKind::TriggerServer,
+                    &backup_settings.trigger_rate_limit,
+                    audit_for_trigger,
+                );
+                if let Err(e) = backup_daemon.start_trigger_server(guard).await {
+                    log::error!("Failed to start backup daemon trigger server: {}", e);
+                }
+
                 backup_daemon.start().await;
             });
+            log::info!("Backup daemon initialized");
+
+            // Periodically check backed-up content for missing or unreadable
+            // CIDs; the interval follows the backup daemon's settings
+            tauri::async_runtime::spawn(scrubber.run_scheduled());
 
-            // Spawn the HTTP trigger server
-            let trigger_port = backup_daemon_for_server.get_trigger_port();
+            // Advertise this device on the LAN (mDNS) so peers can be set up without
+            // typing addresses. The announcement is rebuilt periodically so it follows
+            // settings changes and node restarts (new peer ID).
+            let config_for_discovery = config_service.clone();
             tauri::async_runtime::spawn(async move {
-                backup_daemon_for_server.start_trigger_server().await;
+                // Wait for the node so the first announcement can include its peer ID
+                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+                loop {
+                    let app_config = config_for_discovery.read().await.get();
+                    services::DiscoveryService::refresh(&discovery_service, &app_config).await;
+                    tokio::time::sleep(services::discovery::ANNOUNCEMENT_REFRESH_INTERVAL).await;
+                }
             });
-            log::info!(
-                "Backup daemon initialized (trigger server on port {})",
-                trigger_port
-            );
 
             // Start media download queue processor
             let media_service_clone = media_service.clone();
@@ -310,7 +380,7 @@ fn setup_system_tray(app: &tauri::App) -> Result<(), Box<dyn std::error::Error>>
     let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
     let menu = Menu::with_items(app, &[&show, &quit])?;
 
-    let _tray = TrayIconBuilder::new()
+    let _tray = TrayIconBuilder::with_id(TRAY_ID)
         .icon(app.default_window_icon().unwrap().clone())
         .menu(&menu)
         .tooltip("Archivist - Decentralized Storage")
@@ -328,6 +328,58 @@ impl NodeApiClient {
         })
     }
 
+    /// Upload a body streamed from elsewhere (e.g. another device's HTTP
+    /// fallback endpoint) without buffering it on disk or in RAM.
+    pub async fn upload_stream(
+        &self,
+        body: reqwest::Body,
+        content_type: &str,
+        content_disposition: Option<&str>,
+        content_length: Option<u64>,
+    ) -> Result<UploadResponse> {
+        let url = format!("{}/api/archivist/v1/data", self.base_url);
+
+        // Same dynamic timeout as file uploads; unknown lengths get an hour
+        let timeout_secs = content_length
+            .map(|len| std::cmp::max(300, len / (10 * 1024 * 1024)))
+            .unwrap_or(3600);
+
+        let mut request = self
+            .client
+            .post(&url)
+            .header(header::CONTENT_TYPE, content_type)
+            .timeout(Duration::from_secs(timeout_secs));
+        if let Some(disposition) = content_disposition {
+            request = request.header(header::CONTENT_DISPOSITION, disposition);
+        }
+        if let Some(len) = content_length {
+            request = request.header(header::CONTENT_LENGTH, len);
+        }
+
+        let response = request
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| ArchivistError::ApiError(format!("Upload failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ArchivistError::ApiError(format!(
+                "Upload failed: HTTP {} - {}",
+                status, body
+            )));
+        }
+
+        let cid = response.text().await.map_err(|e| {
+            ArchivistError::ApiError(format!("Failed to read upload response: {}", e))
+        })?;
+
+        Ok(UploadResponse {
+            cid: cid.trim().to_string(),
+        })
+    }
+
     /// Download a file by CID into memory (from local storage).
     /// Use `download_file_to_path` for large files to avoid memory issues.
     pub async fn download_file(&self, cid: &str) -> Result<Vec<u8>> {
@@ -394,6 +446,60 @@ impl NodeApiClient {
         Ok(())
     }
 
+    /// Open a locally stored file by CID for streaming; the caller reads the
+    /// body from the returned response.
+    pub async fn open_local_stream(&self, cid: &str) -> Result<reqwest::Response> {
+        let url = format!("{}/api/archivist/v1/data/{}", self.base_url, cid);
+
+        let response = self
+            .client
+            .get(&url)
+            .timeout(Duration::from_secs(3600))
+            .send()
+            .await
+            .map_err(|e| ArchivistError::ApiError(format!("Read failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(ArchivistError::ApiError(format!(
+                "Read failed: HTTP {}",
+                response.status()
+            )));
+        }
+
+        Ok(response)
+    }
+
+    /// Read a file by CID from local storage without keeping its content,
+    /// returning its length. Used to check that stored blocks are readable.
+    pub async fn read_file_len(&self, cid: &str) -> Result<u64> {
+        let url = format!("{}/api/archivist/v1/data/{}", self.base_url, cid);
+
+        let response = self
+            .client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| ArchivistError::ApiError(format!("Read failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(ArchivistError::ApiError(format!(
+                "Read failed: HTTP {}",
+                response.status()
+            )));
+        }
+
+        let mut len = 0u64;
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let data = chunk.map_err(|e| {
+                ArchivistError::ApiError(format!("Failed to read data stream: {}", e))
+            })?;
+            len += data.len() as u64;
+        }
+
+        Ok(len)
+    }
+
     /// Trigger the sidecar to fetch a CID from the P2P network.
     /// Does NOT download the file content — just tells the sidecar to store it locally.
     pub async fn request_network_download(&self, cid: &str) -> Result<()> {
@@ -540,6 +646,34 @@ impl NodeApiClient {
         Ok(())
     }
 
+    /// Change the node's log level at runtime. `level` is a chronicles level
+    /// setting, optionally with per-topic overrides (`DEBUG;TRACE:libp2p`)
+    pub async fn set_log_level(&self, level: &str) -> Result<()> {
+        let url = format!(
+            "{}/api/archivist/v1/debug/chronicles/loglevel?level={}",
+            self.base_url,
+            urlencoding::encode(level)
+        );
+
+        let response = self
+            .client
+            .post(&url)
+            .send()
+            .await
+            .map_err(|e| ArchivistError::ApiError(format!("Failed to set log level: {}", e)))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(ArchivistError::ApiError(format!(
+                "Failed to set log level: HTTP {} - {}",
+                status, body
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Delete a file by CID from the node's storage
     pub async fn delete_file(&self, cid: &str) -> Result<()> {
         let url = format!("{}/api/archivist/v1/data/{}", self.base_url, cid);
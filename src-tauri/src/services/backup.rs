@@ -1,31 +1,290 @@
 //! Backup peer notification service
 //!
-//! This service handles notifying a designated backup peer about new manifest files.
-//! It uses HTTP triggers to notify the backup server's daemon to poll immediately,
-//! rather than relying on on-chain persistence features.
+//! This service handles notifying backup targets about new manifest files.
+//! It uses HTTP triggers to notify each backup server's daemon to poll
+//! immediately, rather than relying on on-chain persistence features.
+//!
+//! A folder can be replicated to several targets. The last trigger sent to
+//! each target (per folder) is persisted to `backup-targets.json`; combined
+//! with the acknowledgements the manifest server records, it shows which
+//! sequence each target holds.
 
 use crate::error::{ArchivistError, Result};
 use crate::node_api::NodeApiClient;
+use crate::services::backup_daemon::TriggerRequest;
+use crate::services::config::BackupTarget;
+use crate::services::manifest_server::ManifestRegistry;
 use crate::services::peers::PeerService;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+/// A manifest a target was last triggered for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggeredManifest {
+    pub manifest_cid: String,
+    pub sequence_number: u64,
+    pub triggered_at: DateTime<Utc>,
+}
+
+/// Persisted trigger state of one backup target
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupTargetState {
+    pub last_trigger_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Last successful trigger per folder_id
+    #[serde(default)]
+    pub folders: HashMap<String, TriggeredManifest>,
+}
+
+/// What one target holds of one folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderReplicaStatus {
+    pub folder_id: String,
+    /// Latest sequence this device advertises for the folder
+    pub current_sequence: u64,
+    pub triggered_sequence: Option<u64>,
+    /// Sequence the target acknowledged storing
+    pub acked_sequence: Option<u64>,
+    pub acked_files_failed: Option<u32>,
+    pub acked_at: Option<String>,
+    pub ack_verified: bool,
+    /// The target acknowledged the current sequence with no failed files
+    pub up_to_date: bool,
+}
+
+/// A backup target with its trigger and acknowledgement state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupTargetStatus {
+    pub target: BackupTarget,
+    /// Peer ID from the target's multiaddr (used to match its acks)
+    pub peer_id: Option<String>,
+    pub state: BackupTargetState,
+    pub folders: Vec<FolderReplicaStatus>,
+}
+
+/// Result of notifying one target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetNotifyResult {
+    pub target_id: String,
+    pub nickname: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
 /// Service for managing backup peer notifications
 pub struct BackupService {
-    #[allow(dead_code)]
     api_client: NodeApiClient,
     peer_service: Arc<RwLock<PeerService>>,
+    /// Trigger state per target id
+    target_state: HashMap<String, BackupTargetState>,
+    state_file_path: Option<PathBuf>,
 }
 
 impl BackupService {
-    /// Create a new BackupService
+    /// Create a new BackupService, loading persisted target state
     pub fn new(api_client: NodeApiClient, peer_service: Arc<RwLock<PeerService>>) -> Self {
+        let path = dirs::data_dir()
+            .map(|p| p.join("archivist").join("backup-targets.json"))
+            .unwrap_or_else(|| PathBuf::from("backup-targets.json"));
+
+        let target_state = Self::load_state(&path).unwrap_or_else(|e| {
+            log::warn!("Failed to load backup target state, starting empty: {}", e);
+            HashMap::new()
+        });
+
         Self {
             api_client,
             peer_service,
+            target_state,
+            state_file_path: Some(path),
         }
     }
 
+    /// Load target state from disk
+    fn load_state(path: &Path) -> Result<HashMap<String, BackupTargetState>> {
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!(
+                "Failed to read backup target state: {}",
+                e
+            ))
+        })?;
+
+        serde_json::from_str(&contents).map_err(ArchivistError::SerializationError)
+    }
+
+    /// Save target state to disk (no-op without a state file)
+    fn save_state(&self) -> Result<()> {
+        let Some(path) = &self.state_file_path else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ArchivistError::FileOperationFailed(format!(
+                    "Failed to create state directory: {}",
+                    e
+                ))
+            })?;
+        }
+
+        let json = serde_json::to_string_pretty(&self.target_state)
+            .map_err(ArchivistError::SerializationError)?;
+
+        std::fs::write(path, json).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!(
+                "Failed to write backup target state: {}",
+                e
+            ))
+        })?;
+
+        Ok(())
+    }
+
+    /// Notify every enabled target about a folder's new manifest. Targets are
+    /// independent: one failing doesn't stop the others. Triggers are signed
+    /// with `secret` (this device's ack secret) when one is set.
+    pub async fn notify_targets(
+        &mut self,
+        targets: &[BackupTarget],
+        folder_id: &str,
+        manifest_cid: &str,
+        sequence_number: u64,
+        secret: Option<&str>,
+    ) -> Vec<TargetNotifyResult> {
+        let mut results = Vec::new();
+
+        let source_peer_id = match self.api_client.get_info().await {
+            Ok(info) => Some(info.id),
+            Err(e) => {
+                log::warn!("Could not read own peer ID for trigger: {}", e);
+                None
+            }
+        };
+
+        for target in targets.iter().filter(|t| t.enabled) {
+            let mut trigger = TriggerRequest {
+                folder_id: Some(folder_id.to_string()),
+                manifest_cid: Some(manifest_cid.to_string()),
+                source_peer_id: source_peer_id.clone(),
+                ..Default::default()
+            };
+            if let Some(secret) = secret {
+                // Signed per target so each carries a fresh timestamp
+                trigger.sign(secret);
+            }
+
+            let outcome = self
+                .notify_backup_peer(&trigger, &target.peer_address, target.trigger_port)
+                .await;
+
+            let now = Utc::now();
+            let state = self.target_state.entry(target.id.clone()).or_default();
+            state.last_trigger_at = Some(now);
+            match &outcome {
+                Ok(()) => {
+                    state.last_success_at = Some(now);
+                    state.last_error = None;
+                    state.folders.insert(
+                        folder_id.to_string(),
+                        TriggeredManifest {
+                            manifest_cid: manifest_cid.to_string(),
+                            sequence_number,
+                            triggered_at: now,
+                        },
+                    );
+                }
+                Err(e) => {
+                    log::warn!("Failed to notify backup target {}: {}", target.nickname, e);
+                    state.last_error = Some(e.to_string());
+                }
+            }
+
+            results.push(TargetNotifyResult {
+                target_id: target.id.clone(),
+                nickname: target.nickname.clone(),
+                success: outcome.is_ok(),
+                error: outcome.err().map(|e| e.to_string()),
+            });
+        }
+
+        if let Err(e) = self.save_state() {
+            log::warn!("Failed to persist backup target state: {}", e);
+        }
+
+        results
+    }
+
+    /// Trigger and acknowledgement state of each target, per advertised folder
+    pub fn target_statuses(
+        &self,
+        targets: &[BackupTarget],
+        registry: &ManifestRegistry,
+    ) -> Vec<BackupTargetStatus> {
+        let mut manifests = registry.get_all_manifests();
+        manifests.sort_by(|a, b| a.folder_path.cmp(&b.folder_path));
+
+        targets
+            .iter()
+            .map(|target| {
+                let peer_id = Self::extract_peer_id_from_multiaddr(&target.peer_address);
+                let state = self
+                    .target_state
+                    .get(&target.id)
+                    .cloned()
+                    .unwrap_or_default();
+
+                let folders = manifests
+                    .iter()
+                    .map(|m| {
+                        let ack = peer_id.as_deref().and_then(|pid| {
+                            registry
+                                .get_acks(&m.folder_id)
+                                .into_iter()
+                                .find(|r| r.ack.backup_peer_id == pid)
+                        });
+                        FolderReplicaStatus {
+                            folder_id: m.folder_id.clone(),
+                            current_sequence: m.sequence_number,
+                            triggered_sequence: state
+                                .folders
+                                .get(&m.folder_id)
+                                .map(|t| t.sequence_number),
+                            acked_sequence: ack.as_ref().map(|r| r.ack.sequence_number),
+                            acked_files_failed: ack.as_ref().map(|r| r.ack.files_failed),
+                            acked_at: ack.as_ref().map(|r| r.received_at.clone()),
+                            ack_verified: ack.as_ref().is_some_and(|r| r.verified),
+                            up_to_date: ack.as_ref().is_some_and(|r| {
+                                r.ack.sequence_number >= m.sequence_number
+                                    && r.ack.files_failed == 0
+                            }),
+                        }
+                    })
+                    .collect();
+
+                BackupTargetStatus {
+                    target: target.clone(),
+                    peer_id,
+                    state,
+                    folders,
+                }
+            })
+            .collect()
+    }
+
     /// Notify backup peer to poll for new manifests via HTTP trigger
     ///
     /// This ensures the backup peer is connected via P2P (for file transfer)
@@ -33,15 +292,16 @@ impl BackupService {
     /// immediately for new manifests.
     ///
     /// # Arguments
-    /// * `manifest_cid` - CID of the manifest (for logging)
+    /// * `trigger` - Trigger body naming the folder and manifest that changed
     /// * `backup_peer_addr` - Multiaddr of backup peer (e.g., /ip4/1.2.3.4/tcp/8070/p2p/...)
     /// * `trigger_port` - Port of backup server's trigger HTTP endpoint (default: 8086)
     pub async fn notify_backup_peer(
         &self,
-        manifest_cid: &str,
+        trigger: &TriggerRequest,
         backup_peer_addr: &str,
         trigger_port: u16,
     ) -> Result<()> {
+        let manifest_cid = trigger.manifest_cid.as_deref().unwrap_or_default();
         log::info!(
             "Notifying backup peer about manifest CID: {} via HTTP trigger",
             manifest_cid
@@ -61,6 +321,7 @@ impl BackupService {
         let client = reqwest::Client::new();
         let response = client
             .post(&trigger_url)
+            .json(trigger)
             .timeout(std::time::Duration::from_secs(10))
             .send()
             .await
@@ -115,6 +376,17 @@ impl BackupService {
         )))
     }
 
+    /// Extract the peer ID (`/p2p/<id>`) from a multiaddr, if present
+    fn extract_peer_id_from_multiaddr(multiaddr: &str) -> Option<String> {
+        let parts: Vec<&str> = multiaddr.split('/').collect();
+        parts
+            .iter()
+            .position(|part| *part == "p2p")
+            .and_then(|i| parts.get(i + 1))
+            .filter(|id| !id.is_empty())
+            .map(|id| id.to_string())
+    }
+
     /// Connect to backup peer if not already connected
     async fn ensure_backup_peer_connected(&self, peer_addr: &str) -> Result<()> {
         log::info!("Ensuring backup peer is connected: {}", peer_addr);
@@ -174,4 +446,77 @@ mod tests {
         let result = BackupService::extract_ip_from_multiaddr("invalid-multiaddr");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_extract_peer_id_from_multiaddr() {
+        assert_eq!(
+            BackupService::extract_peer_id_from_multiaddr(
+                "/ip4/192.168.1.100/tcp/8070/p2p/16Uiu2HAmXYZ"
+            )
+            .as_deref(),
+            Some("16Uiu2HAmXYZ")
+        );
+        assert!(
+            BackupService::extract_peer_id_from_multiaddr("/ip4/192.168.1.100/tcp/8070").is_none()
+        );
+    }
+
+    #[test]
+    fn test_target_statuses_match_acks_by_peer_id() {
+        use crate::services::manifest_server::{BackupAck, ManifestInfo};
+
+        let mut registry = ManifestRegistry::default();
+        registry.register_manifest(ManifestInfo {
+            folder_id: "photos".to_string(),
+            folder_path: "/data/photos".to_string(),
+            manifest_cid: "zCid4".to_string(),
+            sequence_number: 4,
+            updated_at: Utc::now().to_rfc3339(),
+            file_count: 2,
+            total_size_bytes: 20,
+        });
+        registry.record_ack(
+            BackupAck {
+                manifest_cid: "zCid3".to_string(),
+                folder_id: "photos".to_string(),
+                sequence_number: 3,
+                backup_peer_id: "16Uiu2HAmOffice".to_string(),
+                files_stored: 2,
+                files_failed: 0,
+                bytes_stored: 20,
+                processed_at: Utc::now().to_rfc3339(),
+                timestamp: None,
+                signature: None,
+            },
+            false,
+        );
+
+        let target = |id: &str, peer: &str| BackupTarget {
+            id: id.to_string(),
+            nickname: id.to_string(),
+            peer_address: format!("/ip4/10.0.0.2/tcp/8070/p2p/{}", peer),
+            trigger_port: 8086,
+            enabled: true,
+        };
+        let service = BackupService {
+            api_client: NodeApiClient::new(8080),
+            peer_service: Arc::new(RwLock::new(PeerService::new())),
+            target_state: HashMap::new(),
+            state_file_path: None,
+        };
+
+        let statuses = service.target_statuses(
+            &[
+                target("office", "16Uiu2HAmOffice"),
+                target("nas", "16Uiu2HAmNas"),
+            ],
+            &registry,
+        );
+
+        let office = &statuses[0].folders[0];
+        assert_eq!(office.current_sequence, 4);
+        assert_eq!(office.acked_sequence, Some(3));
+        assert!(!office.up_to_date);
+        assert_eq!(statuses[1].folders[0].acked_sequence, None);
+    }
 }
@@ -4,25 +4,98 @@
 //! - Polls source peers for new manifest CIDs via HTTP
 //! - Downloads manifests from the P2P network
 //! - Parses manifests to extract file lists and deletions
-//! - Downloads missing files from the network
-//! - Enforces deletions based on tombstones
-//! - Tracks processing state with sequence numbers
-//! - Accepts trigger notifications from source peers via HTTP
+//! - Downloads missing files from the network, after checking that they fit
+//!   the node's free quota and the source's storage limit (manifests that
+//!   don't fit are held as "blocked: quota" and re-checked every cycle)
+//! - Enforces deletions based on tombstones, after a grace period during
+//!   which tombstoned CIDs are quarantined as pending deletions
+//! - Mirrors only the folders (and path prefixes inside them) selected per
+//!   source; excluded folders are still listed with the source's folders but
+//!   their manifests are ignored, and files outside the prefixes are neither
+//!   downloaded nor deleted by tombstones
+//! - Tracks processing state with sequence numbers; when a folder skips
+//!   sequences, the missing manifests are fetched from the source's manifest
+//!   history and applied in order before the newest one
+//! - Keeps the last N snapshots (processed manifests) of each folder
+//!   restorable; their CIDs are protected from deletion until pruned
+//! - Accepts trigger notifications from source peers via HTTP (rate limited
+//!   and audited through the shared request guard). Triggers are signed with
+//!   the same per-source secret as acks; a trigger naming its folder or
+//!   manifest polls only that source. The trigger server is re-bound (or
+//!   stopped) when its settings change.
+//! - Sends a signed acknowledgement (files stored / failed) back to the
+//!   source's manifest server once a manifest has been processed
+//! - Falls back to the source's authenticated HTTP content endpoint when a
+//!   manifest or file can't be fetched over P2P (sources with a shared secret
+//!   only); the content is uploaded into the local node and its CID checked
 
 use crate::error::{ArchivistError, Result};
 use crate::node_api::NodeApiClient;
-use crate::services::config::SourcePeerConfig;
-use crate::services::manifest_server::ManifestClient;
+use crate::services::config::{BackupServerSettings, RateLimitSettings, SourcePeerConfig};
+use crate::services::manifest_server::{BackupAck, ManifestClient, ManifestInfo};
+use crate::services::request_guard::{rate_limited_reply, RateLimitedError, RequestGuard};
 use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, oneshot, RwLock, Semaphore};
 use tokio::time::Duration;
 use warp::Filter;
 
+/// Hours tombstoned CIDs stay quarantined unless configured otherwise
+const DEFAULT_DELETION_GRACE_HOURS: u32 = 72;
+
+/// Snapshots kept per source folder unless configured otherwise
+const DEFAULT_SNAPSHOT_RETENTION: u32 = 5;
+
+/// Attempts per file within one processing run before the file counts as failed
+const FILE_DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// Backoff between attempts for one file: base * 2^(attempt-1), capped
+const FILE_RETRY_BASE_SECS: u64 = 2;
+const FILE_RETRY_MAX_SECS: u64 = 60;
+
+/// Manifests interrupted longer ago than this are not resumed at startup but
+/// handed to the retry pass as failed
+const STALE_IN_PROGRESS_SECS: i64 = 24 * 60 * 60;
+
+/// Consecutive poll failures before a source's circuit opens
+const SOURCE_CIRCUIT_THRESHOLD: u32 = 5;
+
+/// Base delay between retries of a failed manifest
+const MANIFEST_RETRY_BASE_SECS: u64 = 30;
+
+/// How long a manifest that used up its retries rests before the retry pass
+/// admits it again with a fresh retry count
+const EXHAUSTED_RETRY_COOLDOWN_SECS: i64 = 24 * 60 * 60;
+
+/// Upper bound for source poll and manifest retry backoff
+const BACKOFF_MAX_SECS: u64 = 60 * 60;
+
+/// Past manifest failures kept for the backup history (oldest dropped first)
+const MAX_FAILURE_HISTORY: usize = 1000;
+
+/// Signed triggers older (or further in the future) than this are rejected
+const TRIGGER_MAX_AGE_SECS: i64 = 5 * 60;
+
+/// How long a stopping trigger server may take to finish in-flight requests
+const TRIGGER_SHUTDOWN_TIMEOUT_SECS: u64 = 5;
+
+/// Status shown for manifests held back by admission control
+const BLOCKED_QUOTA_STATUS: &str = "blocked: quota";
+
+/// Save per-file progress to disk after this many completed files
+const PROGRESS_SAVE_INTERVAL: u32 = 25;
+
+/// Rebuild the local CID index after this long even within a cycle, to pick
+/// up changes made outside the daemon
+const LOCAL_INDEX_TTL: Duration = Duration::from_secs(300);
+
 /// Persistent state for backup daemon (stored in daemon-state.json)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DaemonState {
@@ -40,6 +113,159 @@ pub struct DaemonState {
 
     /// Statistics
     pub stats: DaemonStats,
+
+    /// Poll health per source peer, keyed by `host:port`
+    #[serde(default)]
+    pub source_health: HashMap<String, SourceHealth>,
+
+    /// CIDs quarantined during the deletion grace period, keyed by CID
+    #[serde(default)]
+    pub pending_deletions: HashMap<String, PendingDeletion>,
+
+    /// Manifests held back by admission control, keyed by manifest CID
+    #[serde(default)]
+    pub blocked_manifests: HashMap<String, BlockedManifest>,
+
+    /// Folders each source advertised on its last successful poll, keyed by
+    /// `host:port`, including the ones excluded from mirroring
+    #[serde(default)]
+    pub source_folders: HashMap<String, Vec<SourceFolder>>,
+
+    /// Every failed attempt at a manifest, oldest first, kept after the
+    /// manifest is retried or completes
+    #[serde(default)]
+    pub failure_history: Vec<ManifestFailureRecord>,
+}
+
+/// A folder advertised by a source peer and how this server mirrors it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceFolder {
+    pub folder_id: String,
+    pub folder_path: String,
+    pub latest_sequence: u64,
+    pub file_count: u32,
+    pub total_size_bytes: u64,
+    /// Whether the folder's manifests are processed
+    pub mirrored: bool,
+    /// Paths mirrored inside the folder (empty = the whole folder)
+    pub path_prefixes: Vec<String>,
+    /// When the source first advertised the folder
+    #[serde(default)]
+    pub first_seen_at: Option<DateTime<Utc>>,
+}
+
+/// A manifest that was not started because its files would not fit. It is
+/// re-checked every cycle and processed once space is available.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockedManifest {
+    pub manifest_cid: String,
+    pub source_peer_id: String,
+    pub folder_id: String,
+    pub sequence_number: u64,
+    /// Always "blocked: quota"
+    pub status: String,
+    pub reason: String,
+    /// Bytes of manifest files not yet stored locally
+    pub required_bytes: u64,
+    /// Free node quota at the last check, if it could be read
+    pub available_bytes: Option<u64>,
+    pub source_limit_bytes: Option<u64>,
+    /// Bytes the source already occupies (its other folders)
+    pub source_used_bytes: u64,
+    pub blocked_at: DateTime<Utc>,
+    pub last_checked_at: DateTime<Utc>,
+    #[serde(default)]
+    pub multiaddr: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeletionReason {
+    /// Listed as deleted by a source manifest
+    Tombstone,
+    /// Only referenced by snapshots that fell outside retention
+    SnapshotPruned,
+}
+
+/// A CID that will be deleted from local storage once its grace period ends
+/// (or the user confirms). Until then it can still be restored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingDeletion {
+    pub cid: String,
+    pub path: String,
+    pub source_peer_id: String,
+    pub folder_id: String,
+    /// Manifest that tombstoned the file, or the snapshot that was pruned
+    pub manifest_cid: String,
+    pub reason: DeletionReason,
+    pub queued_at: DateTime<Utc>,
+    pub delete_after: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceHealthStatus {
+    Healthy,
+    /// Recent polls failed; polling is backing off
+    Degraded,
+    /// Too many consecutive failures; polls and manifest retries for this
+    /// source wait for the (maximum) backoff to expire
+    CircuitOpen,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceHealth {
+    pub nickname: String,
+    /// Peer ID reported by the source's manifest server
+    pub peer_id: Option<String>,
+    pub status: SourceHealthStatus,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_failure_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub consecutive_failures: u32,
+    /// Polls are skipped until this time
+    pub next_attempt_at: Option<DateTime<Utc>>,
+}
+
+impl SourceHealth {
+    pub(crate) fn new(nickname: &str) -> Self {
+        Self {
+            nickname: nickname.to_string(),
+            peer_id: None,
+            status: SourceHealthStatus::Healthy,
+            last_success_at: None,
+            last_failure_at: None,
+            last_error: None,
+            consecutive_failures: 0,
+            next_attempt_at: None,
+        }
+    }
+
+    fn record_success(&mut self, peer_id: &str, now: DateTime<Utc>) {
+        self.peer_id = Some(peer_id.to_string());
+        self.status = SourceHealthStatus::Healthy;
+        self.last_success_at = Some(now);
+        self.last_error = None;
+        self.consecutive_failures = 0;
+        self.next_attempt_at = None;
+    }
+
+    fn record_failure(&mut self, error: &str, base_secs: u64, now: DateTime<Utc>) {
+        self.consecutive_failures += 1;
+        self.last_failure_at = Some(now);
+        self.last_error = Some(error.to_string());
+        self.status = if self.consecutive_failures >= SOURCE_CIRCUIT_THRESHOLD {
+            SourceHealthStatus::CircuitOpen
+        } else {
+            SourceHealthStatus::Degraded
+        };
+        let delay = backoff_with_jitter(base_secs, BACKOFF_MAX_SECS, self.consecutive_failures);
+        self.next_attempt_at = Some(now + chrono::Duration::seconds(delay.as_secs() as i64));
+    }
+
+    fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.next_attempt_at.map_or(true, |t| t <= now)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,6 +278,22 @@ pub struct ProcessedManifest {
     pub file_count: u32,
     pub total_size_bytes: u64,
     pub deleted_count: u32,
+    /// When the source last changed the folder (the snapshot's point in time)
+    #[serde(default)]
+    pub manifest_updated_at: Option<DateTime<Utc>>,
+    /// Set once the snapshot falls outside retention and is no longer restorable
+    #[serde(default)]
+    pub pruned_at: Option<DateTime<Utc>>,
+    /// When the source recorded our acknowledgement (None = not delivered yet)
+    #[serde(default)]
+    pub ack_sent_at: Option<DateTime<Utc>>,
+    /// Path prefixes the snapshot was mirrored with (empty = the whole folder)
+    #[serde(default)]
+    pub path_prefixes: Vec<String>,
+    /// CIDs of the snapshot's files, kept while it is retained so deletions
+    /// can be checked without fetching the manifest again (None = not cached)
+    #[serde(default)]
+    pub file_cids: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +306,33 @@ pub struct InProgressManifest {
     pub files_downloaded: u32,
     pub files_failed: u32,
     pub current_status: String,
+    /// Per-file download state, keyed by CID
+    #[serde(default)]
+    pub files: HashMap<String, FileDownloadState>,
+    /// Source multiaddr, kept so an interrupted manifest can be resumed
+    #[serde(default)]
+    pub multiaddr: Option<String>,
+    #[serde(default)]
+    pub folder_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileDownloadStatus {
+    Pending,
+    Downloading,
+    /// Stored locally (downloaded now or already present)
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDownloadState {
+    pub path: String,
+    pub status: FileDownloadStatus,
+    /// Download attempts across all processing runs of the manifest
+    pub attempts: u32,
+    pub last_error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -75,6 +344,36 @@ pub struct FailedManifest {
     pub retry_count: u32,
     #[serde(default)]
     pub multiaddr: Option<String>,
+    /// Per-file state from the failed run, so a retry only fetches missing files
+    #[serde(default)]
+    pub files: HashMap<String, FileDownloadState>,
+    /// The retry pass skips this manifest until then
+    #[serde(default)]
+    pub next_retry_at: Option<DateTime<Utc>>,
+    /// Folder and sequence, when the manifest got far enough to be parsed
+    #[serde(default)]
+    pub folder_id: Option<String>,
+    #[serde(default)]
+    pub sequence_number: Option<u64>,
+    /// When the manifest used up its retries; it is admitted again once
+    /// the cooldown has passed
+    #[serde(default)]
+    pub exhausted_at: Option<DateTime<Utc>>,
+}
+
+/// One failed attempt at processing a manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestFailureRecord {
+    pub manifest_cid: String,
+    pub source_peer_id: String,
+    pub folder_id: Option<String>,
+    pub sequence_number: Option<u64>,
+    pub failed_at: DateTime<Utc>,
+    pub error_message: String,
+    /// Retries the manifest had used when this attempt failed
+    pub retry_count: u32,
+    pub files_done: u32,
+    pub files_failed: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -94,13 +393,18 @@ impl Default for DaemonState {
             failed_manifests: Vec::new(),
             last_poll_time: Utc::now(),
             stats: DaemonStats::default(),
+            source_health: HashMap::new(),
+            pending_deletions: HashMap::new(),
+            blocked_manifests: HashMap::new(),
+            source_folders: HashMap::new(),
+            failure_history: Vec::new(),
         }
     }
 }
 
 /// Manifest file structure (JSON) - must match primary peer's ManifestFile
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct ManifestFile {
+pub(crate) struct ManifestFile {
     pub version: String,
     pub folder_id: String,
     pub folder_path: String,
@@ -114,7 +418,7 @@ struct ManifestFile {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct ManifestFileEntry {
+pub(crate) struct ManifestFileEntry {
     pub path: String,
     pub cid: String,
     pub size_bytes: u64,
@@ -123,18 +427,141 @@ struct ManifestFileEntry {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct ManifestDeletedEntry {
+pub(crate) struct ManifestDeletedEntry {
     pub path: String,
     pub cid: String,
     pub deleted_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct ManifestStats {
+pub(crate) struct ManifestStats {
     pub total_files: u32,
     pub total_size_bytes: u64,
 }
 
+/// Body of a `POST /trigger` request. Every field is optional: an empty body
+/// (as sent by older sources) polls all sources.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerRequest {
+    /// Folder whose manifest changed
+    #[serde(default)]
+    pub folder_id: Option<String>,
+    /// The new manifest CID
+    #[serde(default)]
+    pub manifest_cid: Option<String>,
+    /// Node peer ID of the source sending the trigger
+    #[serde(default)]
+    pub source_peer_id: Option<String>,
+    /// RFC 3339 time the trigger was signed at
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    /// Hex HMAC-SHA256 of the other fields, keyed with the secret shared
+    /// with this source (the one acks are signed with)
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl TriggerRequest {
+    /// Canonical string covered by the signature
+    fn signing_payload(&self) -> String {
+        format!(
+            "{}|{}|{}|{}",
+            self.folder_id.as_deref().unwrap_or_default(),
+            self.manifest_cid.as_deref().unwrap_or_default(),
+            self.source_peer_id.as_deref().unwrap_or_default(),
+            self.timestamp.as_deref().unwrap_or_default()
+        )
+    }
+
+    fn mac(secret: &str) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length")
+    }
+
+    /// Timestamp and sign the trigger with the shared secret
+    pub fn sign(&mut self, secret: &str) {
+        self.timestamp = Some(Utc::now().to_rfc3339());
+        let mut mac = Self::mac(secret);
+        mac.update(self.signing_payload().as_bytes());
+        self.signature = Some(hex::encode(mac.finalize().into_bytes()));
+    }
+
+    /// Check the signature (constant time) and that it was made recently
+    pub fn verify(&self, secret: &str, now: DateTime<Utc>) -> bool {
+        let fresh = self
+            .timestamp
+            .as_deref()
+            .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+            .is_some_and(|t| {
+                (now - t.with_timezone(&Utc)).num_seconds().abs() <= TRIGGER_MAX_AGE_SECS
+            });
+        let Some(signature) = self.signature.as_deref().and_then(|s| hex::decode(s).ok()) else {
+            return false;
+        };
+        let mut mac = Self::mac(secret);
+        mac.update(self.signing_payload().as_bytes());
+        fresh && mac.verify_slice(&signature).is_ok()
+    }
+}
+
+/// Outcome of checking a trigger against the configured source secrets
+#[derive(Debug, PartialEq)]
+enum TriggerAuth {
+    /// Signed by the source with this `host:port` key
+    Source(String),
+    /// Unsigned, accepted because unsigned triggers were explicitly allowed
+    /// and no enabled source has a secret configured
+    Open,
+    Rejected,
+}
+
+/// What an accepted trigger led to
+#[derive(Debug)]
+enum TriggerOutcome {
+    /// A poll of this source (`None` = every source) was queued
+    Polling(Option<String>),
+    /// The named manifest is already processed, so nothing was polled
+    AlreadyProcessed,
+}
+
+/// Where (and whether) the trigger server listens, and its per-IP rate limit
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerListenSettings {
+    pub enabled: bool,
+    pub bind_address: String,
+    pub port: u16,
+    pub rate_limit: RateLimitSettings,
+}
+
+impl TriggerListenSettings {
+    pub fn from_settings(settings: &BackupServerSettings) -> Self {
+        Self {
+            // No point listening for triggers while the backup server is off
+            enabled: settings.enabled && settings.trigger_enabled,
+            bind_address: settings.trigger_bind_address.clone(),
+            port: settings.trigger_port,
+            rate_limit: settings.trigger_rate_limit.clone(),
+        }
+    }
+
+    fn socket_addr(&self) -> Result<SocketAddr> {
+        let ip: std::net::IpAddr = self.bind_address.parse().map_err(|_| {
+            ArchivistError::ConfigError(format!(
+                "Invalid trigger bind address: {}",
+                self.bind_address
+            ))
+        })?;
+        Ok(SocketAddr::new(ip, self.port))
+    }
+}
+
+/// A running trigger server
+struct TriggerServerHandle {
+    addr: SocketAddr,
+    shutdown_tx: oneshot::Sender<()>,
+    task: tokio::task::JoinHandle<()>,
+}
+
 /// Manifest CID discovered from source peer
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -144,6 +571,7 @@ struct DiscoveredManifest {
     pub sequence_number: u64,
     pub source_peer_id: String,
     pub source_host: String,
+    pub source_port: u16,
     pub multiaddr: Option<String>,
 }
 
@@ -163,6 +591,138 @@ struct DeletionResult {
     pub deleted: u32,
     pub failed: u32,
     pub not_found: u32,
+    /// Queued as pending deletions instead of deleted
+    pub quarantined: u32,
+}
+
+/// Set of CIDs held by the local node, built from one `list_data` call and
+/// kept up to date as the daemon downloads and deletes content
+#[derive(Debug, Default)]
+struct LocalCidIndex {
+    cids: HashSet<String>,
+    /// When the index was last rebuilt (None = must rebuild before use)
+    built_at: Option<std::time::Instant>,
+}
+
+impl LocalCidIndex {
+    fn is_fresh(&self, now: std::time::Instant) -> bool {
+        self.built_at
+            .map(|built| now.saturating_duration_since(built) < LOCAL_INDEX_TTL)
+            .unwrap_or(false)
+    }
+
+    fn invalidate(&mut self) {
+        self.built_at = None;
+    }
+
+    /// Replace the index with a fresh content listing. A failed listing
+    /// leaves the index stale so it is retried, rather than treating every
+    /// CID as absent.
+    fn rebuild(&mut self, listing: Result<HashSet<String>>, now: std::time::Instant) -> Result<()> {
+        match listing {
+            Ok(cids) => {
+                self.cids = cids;
+                self.built_at = Some(now);
+                Ok(())
+            }
+            Err(e) => {
+                self.built_at = None;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Where to fetch content over HTTP when the P2P network fails, i.e. a source
+/// peer's manifest server and the secret its content requests are signed with
+#[derive(Clone)]
+struct HttpFallback {
+    client: ManifestClient,
+    host: String,
+    port: u16,
+    secret: String,
+    /// CIDs stored or referenced locally before the fallback was set up; a
+    /// mismatched upload is only deleted when known not to be one of them
+    keep: Option<Arc<HashSet<String>>>,
+}
+
+impl HttpFallback {
+    /// Fallback for a configured source; needs the source's shared secret
+    fn for_source(client: &ManifestClient, peer: &SourcePeerConfig) -> Option<Self> {
+        let secret = peer.ack_secret.clone().filter(|s| !s.is_empty())?;
+        Some(Self {
+            client: client.clone(),
+            host: peer.host.clone(),
+            port: peer.manifest_port,
+            secret,
+            keep: None,
+        })
+    }
+
+    /// Allow deleting mismatched uploads whose CID isn't in `keep`
+    fn keeping(mut self, keep: HashSet<String>) -> Self {
+        self.keep = Some(Arc::new(keep));
+        self
+    }
+
+    /// Whether a mismatched upload can be deleted without touching content
+    /// that was already stored or is still referenced
+    fn may_delete(&self, cid: &str) -> bool {
+        self.keep.as_ref().is_some_and(|keep| !keep.contains(cid))
+    }
+
+    /// Stream `cid` from the source into the local node, checking that the
+    /// upload produced the same CID
+    async fn fetch_into_node(&self, api_client: &NodeApiClient, cid: &str) -> Result<()> {
+        let response = self
+            .client
+            .fetch_content(&self.host, self.port, cid, &self.secret)
+            .await?;
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let content_type = header(reqwest::header::CONTENT_TYPE)
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+        let content_disposition = header(reqwest::header::CONTENT_DISPOSITION);
+        let content_length = response.content_length();
+
+        let uploaded = api_client
+            .upload_stream(
+                reqwest::Body::wrap_stream(response.bytes_stream()),
+                &content_type,
+                content_disposition.as_deref(),
+                content_length,
+            )
+            .await?;
+
+        if uploaded.cid != cid {
+            // Don't leave content nobody asked for in the node, but never
+            // delete a CID that may have been there before the upload
+            if !self.may_delete(&uploaded.cid) {
+                log::warn!(
+                    "Leaving mismatched HTTP fallback upload {} in place: it may be stored or referenced locally",
+                    uploaded.cid
+                );
+            } else if let Err(e) = api_client.delete_file(&uploaded.cid).await {
+                log::warn!(
+                    "Failed to delete mismatched HTTP fallback upload {}: {}",
+                    uploaded.cid,
+                    e
+                );
+            }
+            return Err(ArchivistError::SyncError(format!(
+                "HTTP fallback for {} produced CID {} instead",
+                cid, uploaded.cid
+            )));
+        }
+        log::info!("Fetched {} over HTTP fallback from {}", cid, self.host);
+        Ok(())
+    }
 }
 
 /// Backup daemon for automatic manifest processing
@@ -172,18 +732,37 @@ pub struct BackupDaemon {
     state: Arc<RwLock<DaemonState>>,
     state_file_path: PathBuf,
     enabled: Arc<AtomicBool>,
-    poll_interval_secs: u64,
-    max_concurrent_downloads: u32,
-    max_retries: u32,
-    auto_delete_tombstones: bool,
+    /// Settings below can change while the daemon runs (see `apply_settings`);
+    /// the main loop reads them fresh each cycle
+    poll_interval_secs: AtomicU64,
+    max_concurrent_downloads: AtomicU32,
+    max_retries: AtomicU32,
+    auto_delete_tombstones: AtomicBool,
+    /// Number of snapshots kept restorable per source folder (minimum 1)
+    snapshot_retention: AtomicU32,
+    /// How long CIDs stay quarantined before deletion (0 deletes immediately)
+    deletion_grace_secs: AtomicU64,
+    /// Hours between scheduled integrity scrubs (0 disables them); read by
+    /// the scrubber before each scheduled run
+    scrub_interval_hours: AtomicU32,
+    /// CIDs in local storage, rebuilt once per cycle
+    local_index: RwLock<LocalCidIndex>,
     /// Source peers to poll for manifests
     source_peers: Arc<RwLock<Vec<SourcePeerConfig>>>,
-    /// Port for HTTP trigger server
-    trigger_port: u16,
-    /// Channel to send trigger signals to the main loop
-    trigger_tx: mpsc::Sender<()>,
+    /// Bind address, port and enable flag of the HTTP trigger server
+    trigger_listen: RwLock<TriggerListenSettings>,
+    /// Whether unsigned triggers are accepted while no source has a secret
+    allow_unsigned_triggers: AtomicBool,
+    /// Rate limiting and auditing for the trigger server, set when it is
+    /// first started
+    trigger_guard: RwLock<Option<RequestGuard>>,
+    /// The trigger server, while it is running
+    trigger_server: RwLock<Option<TriggerServerHandle>>,
+    /// Channel to send trigger signals to the main loop: the `host:port` key of
+    /// the source to poll, or `None` to poll every source
+    trigger_tx: mpsc::Sender<Option<String>>,
     /// Channel to receive trigger signals (held by main loop)
-    trigger_rx: Arc<RwLock<mpsc::Receiver<()>>>,
+    trigger_rx: Arc<RwLock<mpsc::Receiver<Option<String>>>>,
 }
 
 impl BackupDaemon {
@@ -201,6 +780,21 @@ impl BackupDaemon {
             .map(|p| p.join("archivist").join("backup-daemon-state.json"))
             .unwrap_or_else(|| PathBuf::from("backup-daemon-state.json"));
 
+        let mut daemon = Self::with_state_file(api_client, state_path);
+        daemon.enabled.store(enabled, Ordering::Relaxed);
+        *daemon.poll_interval_secs.get_mut() = poll_interval_secs;
+        *daemon.max_concurrent_downloads.get_mut() = max_concurrent_downloads;
+        *daemon.max_retries.get_mut() = max_retries;
+        *daemon.auto_delete_tombstones.get_mut() = auto_delete_tombstones;
+        let listen = daemon.trigger_listen.get_mut();
+        listen.enabled = enabled;
+        listen.port = trigger_port;
+        daemon
+    }
+
+    /// Create a disabled backup daemon with default settings, backed by the
+    /// given state file
+    pub fn with_state_file(api_client: NodeApiClient, state_path: PathBuf) -> Self {
         let state = Self::load_state(&state_path).unwrap_or_default();
 
         // Create trigger channel (buffer of 10 to avoid blocking)
@@ -211,13 +805,27 @@ impl BackupDaemon {
             manifest_client: ManifestClient::new(),
             state: Arc::new(RwLock::new(state)),
             state_file_path: state_path,
-            enabled: Arc::new(AtomicBool::new(enabled)),
-            poll_interval_secs,
-            max_concurrent_downloads,
-            max_retries,
-            auto_delete_tombstones,
+            enabled: Arc::new(AtomicBool::new(false)),
+            poll_interval_secs: AtomicU64::new(30),
+            max_concurrent_downloads: AtomicU32::new(3),
+            max_retries: AtomicU32::new(3),
+            auto_delete_tombstones: AtomicBool::new(true),
+            snapshot_retention: AtomicU32::new(DEFAULT_SNAPSHOT_RETENTION),
+            deletion_grace_secs: AtomicU64::new(DEFAULT_DELETION_GRACE_HOURS as u64 * 3600),
+            local_index: RwLock::new(LocalCidIndex::default()),
             source_peers: Arc::new(RwLock::new(Vec::new())),
-            trigger_port,
+            trigger_listen: RwLock::new(TriggerListenSettings {
+                enabled: false,
+                bind_address: "0.0.0.0".to_string(),
+                port: 8086,
+                rate_limit: crate::services::config::default_trigger_rate_limit(),
+            }),
+            scrub_interval_hours: AtomicU32::new(
+                crate::services::config::default_scrub_interval_hours(),
+            ),
+            allow_unsigned_triggers: AtomicBool::new(false),
+            trigger_guard: RwLock::new(None),
+            trigger_server: RwLock::new(None),
             trigger_tx,
             trigger_rx: Arc::new(RwLock::new(trigger_rx)),
         }
@@ -230,6 +838,95 @@ impl BackupDaemon {
         log::info!("Updated source peers: {} configured", source_peers.len());
     }
 
+    /// Apply updated backup server settings to the running daemon. Changes
+    /// take effect on the next cycle; a cycle is started right away when the
+    /// poll interval or source peers changed. The trigger server is restarted
+    /// when its bind address, port or enable flag changed.
+    pub async fn apply_settings(self: &Arc<Self>, settings: &BackupServerSettings) {
+        let poll_changed = self
+            .poll_interval_secs
+            .swap(settings.poll_interval_secs, Ordering::Relaxed)
+            != settings.poll_interval_secs;
+        self.max_concurrent_downloads
+            .store(settings.max_concurrent_downloads, Ordering::Relaxed);
+        self.max_retries
+            .store(settings.max_retries, Ordering::Relaxed);
+        self.auto_delete_tombstones
+            .store(settings.auto_delete_tombstones, Ordering::Relaxed);
+        self.set_snapshot_retention(settings.snapshot_retention);
+        self.set_deletion_grace_period(settings.deletion_grace_period_hours);
+        self.allow_unsigned_triggers
+            .store(settings.allow_unsigned_triggers, Ordering::Relaxed);
+        self.scrub_interval_hours
+            .store(settings.scrub_interval_hours, Ordering::Relaxed);
+
+        let peers_changed = *self.source_peers.read().await != settings.source_peers;
+        if peers_changed {
+            self.set_source_peers(settings.source_peers.clone()).await;
+        }
+
+        if settings.enabled != self.is_enabled() {
+            if settings.enabled {
+                self.enable();
+            } else {
+                self.disable();
+            }
+        }
+
+        let listen = TriggerListenSettings::from_settings(settings);
+        let listen_changed = {
+            let mut current = self.trigger_listen.write().await;
+            let changed = *current != listen;
+            *current = listen;
+            changed
+        };
+        // Only restart a server that was started; startup starts it once
+        // settings are applied
+        if listen_changed && self.trigger_guard.read().await.is_some() {
+            if let Err(e) = self.restart_trigger_server().await {
+                log::error!("Failed to restart trigger server: {}", e);
+            }
+        }
+
+        if poll_changed || peers_changed {
+            // Wake the main loop so a long poll interval doesn't delay the change
+            let _ = self.trigger_tx.try_send(None);
+        }
+
+        log::info!(
+            "Applied backup daemon settings (poll interval: {}s, max concurrent downloads: {}, max retries: {}, auto delete: {})",
+            settings.poll_interval_secs,
+            settings.max_concurrent_downloads,
+            settings.max_retries,
+            settings.auto_delete_tombstones
+        );
+    }
+
+    /// Hours between scheduled integrity scrubs (0 = disabled)
+    pub fn scrub_interval_hours(&self) -> u32 {
+        self.scrub_interval_hours.load(Ordering::Relaxed)
+    }
+
+    /// Set how many snapshots of each source folder stay restorable
+    pub fn set_snapshot_retention(&self, retention: u32) {
+        self.snapshot_retention
+            .store(retention.max(1), Ordering::Relaxed);
+    }
+
+    /// Set how long tombstoned CIDs stay quarantined before they are deleted
+    pub fn set_deletion_grace_period(&self, hours: u32) {
+        self.deletion_grace_secs
+            .store(hours as u64 * 3600, Ordering::Relaxed);
+    }
+
+    /// Grace period for new pending deletions, or `None` to delete immediately
+    fn deletion_grace(&self) -> Option<chrono::Duration> {
+        match self.deletion_grace_secs.load(Ordering::Relaxed) {
+            0 => None,
+            secs => Some(chrono::Duration::seconds(secs as i64)),
+        }
+    }
+
     /// Add a source peer
     #[allow(dead_code)]
     pub async fn add_source_peer(&self, peer: SourcePeerConfig) {
@@ -291,6 +988,115 @@ impl BackupDaemon {
         self.state.read().await.clone()
     }
 
+    /// All processed snapshots of a source folder, newest first
+    /// (pruned ones are included with `pruned_at` set)
+    pub async fn folder_snapshots(
+        &self,
+        source_peer_id: &str,
+        folder_id: &str,
+    ) -> Vec<ProcessedManifest> {
+        let state = self.state.read().await;
+        let mut snapshots: Vec<ProcessedManifest> = state
+            .processed_manifests
+            .values()
+            .filter(|m| m.source_peer_id == source_peer_id && m.folder_id == folder_id)
+            .cloned()
+            .collect();
+        snapshots.sort_by_key(|m| std::cmp::Reverse(m.sequence_number));
+        snapshots
+    }
+
+    /// Latest retained snapshot of every backed-up folder
+    pub async fn latest_snapshots(&self) -> Vec<ProcessedManifest> {
+        let state = self.state.read().await;
+        let mut latest: HashMap<(&str, &str), &ProcessedManifest> = HashMap::new();
+        for m in state
+            .processed_manifests
+            .values()
+            .filter(|m| m.pruned_at.is_none())
+        {
+            let entry = latest
+                .entry((m.source_peer_id.as_str(), m.folder_id.as_str()))
+                .or_insert(m);
+            if m.sequence_number > entry.sequence_number {
+                *entry = m;
+            }
+        }
+        latest.into_values().cloned().collect()
+    }
+
+    /// Configured source peer for a peer ID, matched by its configured peer ID
+    /// or by the peer ID its manifest server last reported
+    pub async fn source_peer_config(&self, source_peer_id: &str) -> Option<SourcePeerConfig> {
+        let source_peers = self.source_peers.read().await;
+        let state = self.state.read().await;
+        source_peers
+            .iter()
+            .find(|p| {
+                p.peer_id.as_deref() == Some(source_peer_id)
+                    || state
+                        .source_health
+                        .get(&source_key(&p.host, p.manifest_port))
+                        .is_some_and(|h| h.peer_id.as_deref() == Some(source_peer_id))
+            })
+            .cloned()
+    }
+
+    /// HTTP fallback for content published by a source peer, if the source
+    /// is configured with a shared secret. `incoming` is the manifest being
+    /// downloaded, whose files must survive a mismatched upload too.
+    async fn http_fallback(
+        &self,
+        source_peer_id: &str,
+        incoming: Option<&ManifestFile>,
+    ) -> Option<HttpFallback> {
+        let peer = self.source_peer_config(source_peer_id).await?;
+        let fallback = HttpFallback::for_source(&self.manifest_client, &peer)?;
+        match self.fallback_keep_set(incoming).await {
+            Ok(keep) => Some(fallback.keeping(keep)),
+            Err(e) => {
+                log::warn!(
+                    "Mismatched HTTP fallback uploads will be kept, local content is unknown: {}",
+                    e
+                );
+                Some(fallback)
+            }
+        }
+    }
+
+    /// CIDs stored locally or protected by retained snapshots
+    async fn fallback_keep_set(&self, incoming: Option<&ManifestFile>) -> Result<HashSet<String>> {
+        self.ensure_local_index().await?;
+        let mut keep = self.local_index.read().await.cids.clone();
+        keep.extend(self.protected_cids(incoming).await?);
+        Ok(keep)
+    }
+
+    /// Look up a processed manifest by CID
+    pub async fn get_processed_manifest(&self, manifest_cid: &str) -> Option<ProcessedManifest> {
+        self.state
+            .read()
+            .await
+            .processed_manifests
+            .get(manifest_cid)
+            .cloned()
+    }
+
+    /// Latest fully processed manifest for a source folder
+    pub async fn latest_processed_manifest(
+        &self,
+        source_peer_id: &str,
+        folder_id: &str,
+    ) -> Option<ProcessedManifest> {
+        let state = self.state.read().await;
+        state
+            .processed_manifests
+            .values()
+            .filter(|m| m.source_peer_id == source_peer_id && m.folder_id == folder_id)
+            .max_by_key(|m| m.sequence_number)
+            .cloned()
+    }
+
     /// Enable the daemon
     pub fn enable(&self) {
         self.enabled.store(true, Ordering::Relaxed);
@@ -308,74 +1114,231 @@ impl BackupDaemon {
         self.enabled.load(Ordering::Relaxed)
     }
 
-    /// Get the trigger port
-    pub fn get_trigger_port(&self) -> u16 {
-        self.trigger_port
-    }
-
-    /// Trigger an immediate poll cycle
-    pub async fn trigger_poll(&self) -> Result<()> {
-        log::info!("Received trigger to poll immediately");
+    /// Trigger an immediate poll of one source (by `host:port` key), or of
+    /// every source when `source` is `None`
+    pub async fn trigger_poll(&self, source: Option<String>) -> Result<()> {
+        log::info!(
+            "Received trigger to poll {} immediately",
+            source.as_deref().unwrap_or("all sources")
+        );
         self.trigger_tx
-            .send(())
+            .send(source)
             .await
             .map_err(|e| ArchivistError::SyncError(format!("Failed to send trigger: {}", e)))?;
         Ok(())
     }
 
-    /// Start the HTTP trigger server (runs in background)
-    pub async fn start_trigger_server(self: Arc<Self>) {
-        let port = self.trigger_port;
-        let daemon = self.clone();
+    /// Authenticate a trigger and work out which source it is about, then
+    /// wake the main loop
+    async fn accept_trigger(
+        &self,
+        request: &TriggerRequest,
+    ) -> std::result::Result<TriggerOutcome, warp::Rejection> {
+        let source_peers = self.source_peers.read().await.clone();
+        let allow_unsigned = self.allow_unsigned_triggers.load(Ordering::Relaxed);
+        let source = match authorize_trigger(&source_peers, request, allow_unsigned, Utc::now()) {
+            TriggerAuth::Rejected => {
+                log::warn!("Rejected trigger with a missing or invalid signature");
+                return Err(warp::reject::custom(UnauthorizedTriggerError));
+            }
+            TriggerAuth::Source(key) => Some(key),
+            TriggerAuth::Open => self.resolve_trigger_source(&source_peers, request).await,
+        };
 
-        // POST /trigger - triggers immediate poll
-        let trigger_route = warp::path("trigger")
-            .and(warp::post())
-            .and(warp::any().map(move || daemon.clone()))
-            .and_then(|daemon: Arc<BackupDaemon>| async move {
-                match daemon.trigger_poll().await {
-                    Ok(_) => {
-                        log::info!("Trigger request received and processed");
-                        Ok::<_, warp::Rejection>(warp::reply::json(&serde_json::json!({
-                            "status": "ok",
-                            "message": "Poll triggered"
-                        })))
-                    }
-                    Err(e) => {
-                        log::error!("Trigger request failed: {}", e);
-                        Ok(warp::reply::json(&serde_json::json!({
-                            "status": "error",
-                            "message": format!("{}", e)
-                        })))
-                    }
-                }
-            });
+        if let Some(cid) = &request.manifest_cid {
+            if self.get_processed_manifest(cid).await.is_some() {
+                log::debug!("Trigger for already processed manifest {}, ignoring", cid);
+                return Ok(TriggerOutcome::AlreadyProcessed);
+            }
+        }
+
+        self.trigger_poll(source.clone())
+            .await
+            .map_err(|_| warp::reject::custom(TriggerUnavailableError))?;
+        Ok(TriggerOutcome::Polling(source))
+    }
+
+    /// Find the source an unsigned trigger is about from its peer ID or, for
+    /// folders backed up before, the source their manifests came from
+    async fn resolve_trigger_source(
+        &self,
+        source_peers: &[SourcePeerConfig],
+        request: &TriggerRequest,
+    ) -> Option<String> {
+        let peer_id = match &request.source_peer_id {
+            Some(peer_id) => Some(peer_id.clone()),
+            None => {
+                let folder_id = request.folder_id.as_deref()?;
+                self.state
+                    .read()
+                    .await
+                    .processed_manifests
+                    .values()
+                    .find(|m| m.folder_id == folder_id)
+                    .map(|m| m.source_peer_id.clone())
+            }
+        }?;
+
+        // Matches configured peer IDs as well as ones learned while polling
+        let peer = self.source_peer_config(&peer_id).await?;
+        source_peers
+            .iter()
+            .any(|p| p.host == peer.host && p.manifest_port == peer.manifest_port)
+            .then(|| source_key(&peer.host, peer.manifest_port))
+    }
+
+    /// Start the HTTP trigger server with the current listen settings. The
+    /// guard is kept so the server can be re-bound when settings change.
+    pub async fn start_trigger_server(self: &Arc<Self>, guard: RequestGuard) -> Result<()> {
+        *self.trigger_guard.write().await = Some(guard);
+        self.restart_trigger_server().await
+    }
+
+    /// Stop the trigger server (if running), then start it again with the
+    /// current listen settings unless it is disabled
+    pub async fn restart_trigger_server(self: &Arc<Self>) -> Result<()> {
+        self.stop_trigger_server().await;
+
+        let listen = self.trigger_listen.read().await.clone();
+        if !listen.enabled {
+            log::info!("Backup daemon trigger server is disabled");
+            return Ok(());
+        }
+        let addr = listen.socket_addr()?;
+
+        let guard = {
+            let mut current = self.trigger_guard.write().await;
+            let guard = current.as_mut().ok_or_else(|| {
+                ArchivistError::SyncError("Trigger server not initialized".into())
+            })?;
+            // A new limit needs a new limiter; the audit log is kept
+            if guard.rate_limit_settings() != &listen.rate_limit {
+                *guard = guard.with_rate_limit(&listen.rate_limit);
+            }
+            guard.clone()
+        };
+
+        let daemon = self.clone();
+
+        // POST /trigger - triggers immediate poll
+        let trigger_route = warp::path("trigger")
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(
+                warp::body::content_length_limit(16 * 1024)
+                    .and(warp::body::bytes())
+                    // Older sources POST without a body (or Content-Length)
+                    .or(warp::any().map(warp::hyper::body::Bytes::new))
+                    .unify(),
+            )
+            .and(warp::any().map(move || daemon.clone()))
+            .and_then(handle_trigger);
 
         // GET /health - health check
         let health_route = warp::path("health")
             .and(warp::get())
             .map(|| warp::reply::json(&serde_json::json!({"status": "ok"})));
 
-        let routes = trigger_route.or(health_route);
+        let routes = guard
+            .rate_limit()
+            .and(trigger_route.or(health_route))
+            .recover(handle_trigger_rejection)
+            .with(guard.audit());
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let (addr, server) = warp::serve(routes)
+            .try_bind_with_graceful_shutdown(addr, async {
+                shutdown_rx.await.ok();
+            })
+            .map_err(|e| {
+                ArchivistError::SyncError(format!(
+                    "Failed to bind trigger server to {}: {}",
+                    addr, e
+                ))
+            })?;
+
+        log::info!("Backup daemon trigger server listening on {}", addr);
+
+        *self.trigger_server.write().await = Some(TriggerServerHandle {
+            addr,
+            shutdown_tx,
+            task: tokio::spawn(server),
+        });
+        Ok(())
+    }
 
-        log::info!("Starting backup daemon trigger server on port {}", port);
+    /// Stop the trigger server, letting in-flight requests finish
+    pub async fn stop_trigger_server(&self) {
+        let Some(handle) = self.trigger_server.write().await.take() else {
+            return;
+        };
 
-        // Run server (this blocks, so it should be spawned)
-        warp::serve(routes).run(([0, 0, 0, 0], port)).await;
+        let _ = handle.shutdown_tx.send(());
+        // Wait for the listener to be released so the port can be re-bound
+        match tokio::time::timeout(
+            Duration::from_secs(TRIGGER_SHUTDOWN_TIMEOUT_SECS),
+            handle.task,
+        )
+        .await
+        {
+            Ok(_) => log::info!("Backup daemon trigger server on {} stopped", handle.addr),
+            Err(_) => log::warn!(
+                "Backup daemon trigger server on {} did not stop within {}s",
+                handle.addr,
+                TRIGGER_SHUTDOWN_TIMEOUT_SECS
+            ),
+        }
     }
 
-    /// Discover new manifests by polling source peers
-    async fn discover_manifests(&self) -> Result<Vec<DiscoveredManifest>> {
+    /// Discover new manifests by polling source peers (only the source with
+    /// the `host:port` key `only_source`, when given)
+    async fn discover_manifests(
+        &self,
+        only_source: Option<&str>,
+    ) -> Result<Vec<DiscoveredManifest>> {
         log::debug!("Polling source peers for manifests");
 
-        let source_peers = self.source_peers.read().await;
+        // Clone so settings updates aren't blocked while sources are polled
+        let source_peers = self.source_peers.read().await.clone();
         let mut discovered = Vec::new();
 
+        // Forget health and folders of sources that are no longer configured
+        {
+            let mut state = self.state.write().await;
+            let configured = |key: &String| {
+                source_peers
+                    .iter()
+                    .any(|p| &source_key(&p.host, p.manifest_port) == key)
+            };
+            state.source_health.retain(|key, _| configured(key));
+            state.source_folders.retain(|key, _| configured(key));
+        }
+
         for peer in source_peers.iter() {
             if !peer.enabled {
                 continue;
             }
 
+            let key = source_key(&peer.host, peer.manifest_port);
+            if only_source.is_some_and(|only| only != key) {
+                continue;
+            }
+            let now = Utc::now();
+            // A trigger from the source shows it is reachable, so a targeted
+            // poll ignores its backoff
+            if let Some(health) = self.state.read().await.source_health.get(&key) {
+                if only_source.is_none() && !health.is_due(now) {
+                    log::debug!(
+                        "Skipping source peer {} ({:?}, {} consecutive failures) until {:?}",
+                        peer.nickname,
+                        health.status,
+                        health.consecutive_failures,
+                        health.next_attempt_at
+                    );
+                    continue;
+                }
+            }
+
             log::debug!(
                 "Polling source peer: {} ({}:{})",
                 peer.nickname,
@@ -396,23 +1359,62 @@ impl BackupDaemon {
                         response.peer_id
                     );
 
+                    {
+                        let mut state = self.state.write().await;
+                        state
+                            .source_health
+                            .entry(key.clone())
+                            .or_insert_with(|| SourceHealth::new(&peer.nickname))
+                            .record_success(&response.peer_id, Utc::now());
+                        let folders = source_folders(
+                            peer,
+                            &response.manifests,
+                            state.source_folders.get(&key).map_or(&[][..], |f| f),
+                            Utc::now(),
+                        );
+                        state.source_folders.insert(key, folders);
+                    }
+
                     for manifest in response.manifests {
+                        if !peer.mirrors_folder(&manifest.folder_id) {
+                            log::debug!(
+                                "Ignoring manifest {} of folder {} from {}: excluded from mirroring",
+                                manifest.manifest_cid,
+                                manifest.folder_id,
+                                peer.nickname
+                            );
+                            continue;
+                        }
                         discovered.push(DiscoveredManifest {
                             cid: manifest.manifest_cid,
                             folder_id: manifest.folder_id,
                             sequence_number: manifest.sequence_number,
                             source_peer_id: response.peer_id.clone(),
                             source_host: peer.host.clone(),
+                            source_port: peer.manifest_port,
                             multiaddr: peer.multiaddr.clone(),
                         });
                     }
                 }
                 Err(e) => {
+                    let mut state = self.state.write().await;
+                    let health = state
+                        .source_health
+                        .entry(key)
+                        .or_insert_with(|| SourceHealth::new(&peer.nickname));
+                    health.nickname = peer.nickname.clone();
+                    health.record_failure(
+                        &e.to_string(),
+                        self.poll_interval_secs.load(Ordering::Relaxed),
+                        Utc::now(),
+                    );
                     log::warn!(
-                        "Failed to poll source peer {} ({}:{}): {}",
+                        "Failed to poll source peer {} ({}:{}), {} consecutive failures, next attempt at {:?}: {}",
                         peer.nickname,
                         peer.host,
                         peer.manifest_port,
+                        health.consecutive_failures,
+                        health.next_attempt_at,
                         e
                     );
                 }
@@ -436,9 +1438,130 @@ impl BackupDaemon {
             .into_iter()
             .filter(|m| !state.processed_manifests.contains_key(&m.cid))
             .filter(|m| !state.in_progress_manifests.contains_key(&m.cid))
+            // Blocked manifests are re-checked by their own pass
+            .filter(|m| !state.blocked_manifests.contains_key(&m.cid))
+            // Failed manifests are picked up by the retry pass instead
+            .filter(|m| {
+                !state
+                    .failed_manifests
+                    .iter()
+                    .any(|f| f.manifest_cid == m.cid)
+            })
             .collect()
     }
 
+    /// Put the manifests a folder skipped (fetched from the source's manifest
+    /// history) in front of its newest manifest, oldest first. A folder whose
+    /// skipped manifests are still waiting on the retry or quota passes is left
+    /// out of this cycle so sequences are never applied out of order.
+    async fn fill_sequence_gaps(
+        &self,
+        manifests: Vec<DiscoveredManifest>,
+    ) -> Vec<DiscoveredManifest> {
+        let max_retries = self.max_retries.load(Ordering::Relaxed);
+        let mut ordered = Vec::with_capacity(manifests.len());
+
+        for newest in manifests {
+            let last = {
+                let state = self.state.read().await;
+                last_processed_sequence(&state, &newest.source_peer_id, &newest.folder_id)
+            };
+            let Some(last) = last.filter(|last| newest.sequence_number > last + 1) else {
+                ordered.push(newest);
+                continue;
+            };
+
+            let history = match self
+                .manifest_client
+                .fetch_manifest_history(
+                    &newest.source_host,
+                    newest.source_port,
+                    &newest.folder_id,
+                    last,
+                )
+                .await
+            {
+                Ok(response) => response.manifests,
+                Err(e) => {
+                    log::warn!(
+                        "Could not fetch manifest history of folder {} to fill sequences {}..{}: {}",
+                        newest.folder_id,
+                        last + 1,
+                        newest.sequence_number - 1,
+                        e
+                    );
+                    ordered.push(newest);
+                    continue;
+                }
+            };
+
+            let (gap, unavailable) = gap_fill_plan(last, newest.sequence_number, &history);
+            if !unavailable.is_empty() {
+                log::warn!(
+                    "Sequences {:?} of folder {} are no longer in the source's history; their tombstones can't be applied",
+                    unavailable,
+                    newest.folder_id
+                );
+            }
+
+            let mut to_apply = Vec::new();
+            let mut waiting = false;
+            {
+                let state = self.state.read().await;
+                for info in gap {
+                    if state.processed_manifests.contains_key(&info.manifest_cid) {
+                        continue;
+                    }
+                    let exhausted = state
+                        .failed_manifests
+                        .iter()
+                        .find(|f| f.manifest_cid == info.manifest_cid)
+                        .map(|f| f.retry_count >= max_retries);
+                    match exhausted {
+                        Some(true) => continue,
+                        Some(false) => waiting = true,
+                        None if state.blocked_manifests.contains_key(&info.manifest_cid)
+                            || state.in_progress_manifests.contains_key(&info.manifest_cid) =>
+                        {
+                            waiting = true
+                        }
+                        None => to_apply.push(info),
+                    }
+                }
+            }
+
+            if waiting {
+                log::info!(
+                    "Folder {} has skipped sequences waiting to be retried, deferring seq {}",
+                    newest.folder_id,
+                    newest.sequence_number
+                );
+                continue;
+            }
+
+            if !to_apply.is_empty() {
+                log::info!(
+                    "Filling sequence gap of folder {}: applying {} manifests before seq {}",
+                    newest.folder_id,
+                    to_apply.len(),
+                    newest.sequence_number
+                );
+            }
+            ordered.extend(to_apply.into_iter().map(|info| DiscoveredManifest {
+                cid: info.manifest_cid,
+                folder_id: info.folder_id,
+                sequence_number: info.sequence_number,
+                source_peer_id: newest.source_peer_id.clone(),
+                source_host: newest.source_host.clone(),
+                source_port: newest.source_port,
+                multiaddr: newest.multiaddr.clone(),
+            }));
+            ordered.push(newest);
+        }
+
+        ordered
+    }
+
     /// Process a single manifest (download from network if needed)
     async fn process_manifest(
         &self,
@@ -466,28 +1589,30 @@ impl BackupDaemon {
             }
         }
 
-        // 1. Try to download manifest from local storage first, then from network
-        let manifest_bytes = match self.api_client.download_file(manifest_cid).await {
-            Ok(bytes) => {
-                log::debug!("Manifest {} found in local storage", manifest_cid);
-                bytes
-            }
-            Err(_) => {
-                log::info!(
-                    "Manifest {} not in local storage, fetching from network",
-                    manifest_cid
+        // 1. Try to download manifest from local storage first, then from network,
+        // then over the source's HTTP fallback
+        let mut manifest = match fetch_manifest(&self.api_client, manifest_cid).await {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                let fallback = match peer_id {
+                    Some(pid) => self.http_fallback(pid, None).await,
+                    None => None,
+                };
+                let Some(fallback) = fallback else {
+                    return Err(e);
+                };
+                log::warn!(
+                    "Fetching manifest {} failed ({}), trying HTTP fallback",
+                    manifest_cid,
+                    e
                 );
-                self.api_client
-                    .request_network_download(manifest_cid)
+                fallback
+                    .fetch_into_node(&self.api_client, manifest_cid)
                     .await?;
-                self.api_client.download_file(manifest_cid).await?
+                fetch_manifest(&self.api_client, manifest_cid).await?
             }
         };
 
-        let manifest_json = String::from_utf8(manifest_bytes)
-            .map_err(|e| ArchivistError::SyncError(format!("Invalid UTF-8 in manifest: {}", e)))?;
-        let manifest: ManifestFile = serde_json::from_str(&manifest_json)?;
-
         log::info!(
             "Manifest from peer {} folder {} sequence {} with {} files",
             manifest.source_peer_id,
@@ -496,12 +1621,45 @@ impl BackupDaemon {
             manifest.files.len()
         );
 
+        // 1b. Keep only the paths mirrored for the folder, so downloads, quota
+        // checks and tombstones all ignore the rest
+        let path_prefixes = self
+            .source_peer_config(&manifest.source_peer_id)
+            .await
+            .map(|p| p.path_prefixes(&manifest.folder_id).to_vec())
+            .unwrap_or_default();
+        if !path_prefixes.is_empty() {
+            filter_manifest_paths(&mut manifest, &path_prefixes);
+            log::info!(
+                "Mirroring {} files of folder {} under {:?}",
+                manifest.files.len(),
+                manifest.folder_id,
+                path_prefixes
+            );
+        }
+
         // 2. Validate sequence number (check for gaps)
         self.validate_sequence_number(&manifest).await?;
 
-        // 3. Mark as in-progress
+        // 2b. Admission control: don't start a manifest that won't fit
+        self.admit_manifest(manifest_cid, &manifest, multiaddr)
+            .await?;
+
+        // 3. Mark as in-progress, carrying over per-file state from an earlier run
         {
             let mut state = self.state.write().await;
+            let previous_files = state
+                .in_progress_manifests
+                .get(manifest_cid)
+                .map(|p| p.files.clone())
+                .or_else(|| {
+                    state
+                        .failed_manifests
+                        .iter()
+                        .find(|f| f.manifest_cid == manifest_cid)
+                        .map(|f| f.files.clone())
+                })
+                .unwrap_or_default();
             state.in_progress_manifests.insert(
                 manifest_cid.to_string(),
                 InProgressManifest {
@@ -513,36 +1671,309 @@ impl BackupDaemon {
                     files_downloaded: 0,
                     files_failed: 0,
                     current_status: "Downloading files".to_string(),
+                    files: previous_files,
+                    multiaddr: multiaddr.map(|s| s.to_string()),
+                    folder_id: Some(manifest.folder_id.clone()),
                 },
             );
         }
         self.save_state().await?;
 
         // 4. Download all files
-        let download_result = self.download_manifest_files(&manifest).await;
+        let download_result = self.download_manifest_files(manifest_cid, &manifest).await;
 
         // 5. Enforce deletions (if enabled)
-        let deletion_result = if self.auto_delete_tombstones {
+        let deletion_result = if self.auto_delete_tombstones.load(Ordering::Relaxed) {
             self.enforce_deletions(&manifest).await
         } else {
             Ok(DeletionResult {
                 deleted: 0,
                 failed: 0,
                 not_found: 0,
+                quarantined: 0,
             })
         };
 
         // 6. Mark as processed (or failed)
-        self.finalize_manifest_processing(
+        let (files_stored, files_failed, bytes_stored) = {
+            let state = self.state.read().await;
+            state
+                .in_progress_manifests
+                .get(manifest_cid)
+                .map(|p| file_outcome(&manifest, &p.files))
+                .unwrap_or_default()
+        };
+        let result = self
+            .finalize_manifest_processing(
+                manifest_cid,
+                &manifest,
+                &path_prefixes,
+                download_result,
+                deletion_result,
+                multiaddr,
+            )
+            .await;
+
+        // 7. Tell the source how it went (best effort; delivery of acks for
+        // processed manifests is retried every cycle)
+        let ack = BackupAck {
+            manifest_cid: manifest_cid.to_string(),
+            folder_id: manifest.folder_id.clone(),
+            sequence_number: manifest.sequence_number,
+            backup_peer_id: String::new(),
+            files_stored,
+            files_failed,
+            bytes_stored,
+            processed_at: Utc::now().to_rfc3339(),
+            timestamp: None,
+            signature: None,
+        };
+        if self.send_backup_ack(ack, &manifest.source_peer_id).await && result.is_ok() {
+            self.mark_ack_sent(manifest_cid).await;
+        }
+
+        result
+    }
+
+    /// Send a signed acknowledgement to the source's manifest server.
+    /// Returns whether the source recorded it.
+    async fn send_backup_ack(&self, mut ack: BackupAck, source_peer_id: &str) -> bool {
+        let Some(source) = self.source_peer_config(source_peer_id).await else {
+            log::debug!(
+                "No configured source for peer {}, not sending ack for {}",
+                source_peer_id,
+                ack.manifest_cid
+            );
+            return false;
+        };
+
+        ack.backup_peer_id = match self.api_client.get_info().await {
+            Ok(info) => info.id,
+            Err(e) => {
+                log::warn!(
+                    "Cannot send ack for {}: node info unavailable: {}",
+                    ack.manifest_cid,
+                    e
+                );
+                return false;
+            }
+        };
+        if let Some(secret) = &source.ack_secret {
+            ack.sign(secret);
+        }
+
+        match self
+            .manifest_client
+            .send_ack(&source.host, source.manifest_port, &ack)
+            .await
+        {
+            Ok(()) => {
+                log::info!(
+                    "Acknowledged manifest {} to {} ({} stored, {} failed)",
+                    ack.manifest_cid,
+                    source.nickname,
+                    ack.files_stored,
+                    ack.files_failed
+                );
+                true
+            }
+            Err(e) => {
+                log::warn!(
+                    "Failed to send ack for {} to {}: {}",
+                    ack.manifest_cid,
+                    source.nickname,
+                    e
+                );
+                false
+            }
+        }
+    }
+
+    async fn mark_ack_sent(&self, manifest_cid: &str) {
+        {
+            let mut state = self.state.write().await;
+            if let Some(m) = state.processed_manifests.get_mut(manifest_cid) {
+                m.ack_sent_at = Some(Utc::now());
+            }
+        }
+        if let Err(e) = self.save_state().await {
+            log::error!("Failed to save daemon state: {}", e);
+        }
+    }
+
+    /// Re-send acks for the latest snapshot of each folder that the source
+    /// has not recorded yet
+    async fn send_pending_acks(&self) {
+        for snapshot in self.latest_snapshots().await {
+            if snapshot.ack_sent_at.is_some() {
+                continue;
+            }
+            let ack = BackupAck {
+                manifest_cid: snapshot.manifest_cid.clone(),
+                folder_id: snapshot.folder_id.clone(),
+                sequence_number: snapshot.sequence_number,
+                backup_peer_id: String::new(),
+                files_stored: snapshot.file_count,
+                files_failed: 0,
+                bytes_stored: snapshot.total_size_bytes,
+                processed_at: snapshot.processed_at.to_rfc3339(),
+                timestamp: None,
+                signature: None,
+            };
+            if self.send_backup_ack(ack, &snapshot.source_peer_id).await {
+                self.mark_ack_sent(&snapshot.manifest_cid).await;
+            }
+        }
+    }
+
+    /// Check that the manifest's missing files fit both the node's free quota
+    /// and the source's storage limit. A manifest that doesn't fit is recorded
+    /// as blocked (and taken off the failed and in-progress lists, so it
+    /// doesn't burn retries) and `QuotaExceeded` is returned.
+    async fn admit_manifest(
+        &self,
+        manifest_cid: &str,
+        manifest: &ManifestFile,
+        multiaddr: Option<&str>,
+    ) -> Result<()> {
+        self.ensure_local_index().await?;
+        let required_bytes = {
+            let index = self.local_index.read().await;
+            let mut seen = HashSet::new();
+            manifest
+                .files
+                .iter()
+                .filter(|f| !index.cids.contains(&f.cid) && seen.insert(f.cid.as_str()))
+                .map(|f| f.size_bytes)
+                .sum::<u64>()
+        };
+
+        let available_bytes = match self.api_client.get_space().await {
+            Ok(space) => Some(
+                space
+                    .quota_max_bytes
+                    .saturating_sub(space.quota_used_bytes)
+                    .saturating_sub(space.quota_reserved_bytes),
+            ),
+            Err(e) => {
+                log::warn!("Could not read node quota, skipping quota check: {}", e);
+                None
+            }
+        };
+
+        let source_limit_bytes = self.source_storage_limit(&manifest.source_peer_id).await;
+        let source_used_bytes = {
+            let state = self.state.read().await;
+            source_stored_bytes(
+                &state.processed_manifests,
+                &manifest.source_peer_id,
+                &manifest.folder_id,
+            )
+        };
+
+        let reason = admission_block_reason(
+            required_bytes,
+            manifest.stats.total_size_bytes,
+            available_bytes,
+            source_limit_bytes,
+            source_used_bytes,
+        );
+
+        let mut state = self.state.write().await;
+        let Some(reason) = reason else {
+            if state.blocked_manifests.remove(manifest_cid).is_some() {
+                log::info!("Manifest {} fits again, no longer blocked", manifest_cid);
+            }
+            return Ok(());
+        };
+
+        let now = Utc::now();
+        let blocked_at = state
+            .blocked_manifests
+            .get(manifest_cid)
+            .map_or(now, |b| b.blocked_at);
+        state
+            .failed_manifests
+            .retain(|m| m.manifest_cid != manifest_cid);
+        state.in_progress_manifests.remove(manifest_cid);
+        state.blocked_manifests.insert(
+            manifest_cid.to_string(),
+            BlockedManifest {
+                manifest_cid: manifest_cid.to_string(),
+                source_peer_id: manifest.source_peer_id.clone(),
+                folder_id: manifest.folder_id.clone(),
+                sequence_number: manifest.sequence_number,
+                status: BLOCKED_QUOTA_STATUS.to_string(),
+                reason: reason.clone(),
+                required_bytes,
+                available_bytes,
+                source_limit_bytes,
+                source_used_bytes,
+                blocked_at,
+                last_checked_at: now,
+                multiaddr: multiaddr.map(|s| s.to_string()),
+            },
+        );
+        drop(state);
+        self.save_state().await?;
+
+        log::warn!(
+            "Manifest {} {}: {}",
             manifest_cid,
-            &manifest,
-            download_result,
-            deletion_result,
-            multiaddr,
-        )
-        .await?;
+            BLOCKED_QUOTA_STATUS,
+            reason
+        );
+        Err(ArchivistError::QuotaExceeded(reason))
+    }
 
-        Ok(())
+    /// Storage limit configured for the source peer
+    async fn source_storage_limit(&self, source_peer_id: &str) -> Option<u64> {
+        self.source_peer_config(source_peer_id)
+            .await
+            .and_then(|p| p.max_storage_bytes)
+    }
+
+    /// Re-run blocked manifests; admission control lets them through once
+    /// space is available
+    async fn recheck_blocked_manifests(&self) {
+        let blocked: Vec<BlockedManifest> = self
+            .state
+            .read()
+            .await
+            .blocked_manifests
+            .values()
+            .cloned()
+            .collect();
+
+        for b in blocked {
+            let peer_id = (!b.source_peer_id.is_empty()).then_some(b.source_peer_id.as_str());
+            match self
+                .process_manifest(&b.manifest_cid, peer_id, b.multiaddr.as_deref())
+                .await
+            {
+                Ok(_) => log::info!("Blocked manifest {} processed", b.manifest_cid),
+                Err(ArchivistError::QuotaExceeded(_)) => {
+                    log::debug!(
+                        "Manifest {} is still {}",
+                        b.manifest_cid,
+                        BLOCKED_QUOTA_STATUS
+                    )
+                }
+                Err(e) => {
+                    log::error!("Failed to process manifest {}: {}", b.manifest_cid, e);
+                    let mut state = self.state.write().await;
+                    state.blocked_manifests.remove(&b.manifest_cid);
+                    record_failed_manifest(
+                        &mut state,
+                        &b.manifest_cid,
+                        &b.source_peer_id,
+                        b.multiaddr.as_deref(),
+                        &e.to_string(),
+                        HashMap::new(),
+                    );
+                }
+            }
+        }
     }
 
     /// Validate sequence number to detect gaps
@@ -550,13 +1981,8 @@ impl BackupDaemon {
         let state = self.state.read().await;
 
         // Find last processed manifest from this source peer + folder
-        let last_seq = state
-            .processed_manifests
-            .values()
-            .filter(|m| m.source_peer_id == manifest.source_peer_id)
-            .filter(|m| m.folder_id == manifest.folder_id)
-            .map(|m| m.sequence_number)
-            .max();
+        let last_seq =
+            last_processed_sequence(&state, &manifest.source_peer_id, &manifest.folder_id);
 
         if let Some(last) = last_seq {
             let expected = last + 1;
@@ -569,106 +1995,220 @@ impl BackupDaemon {
                     manifest.sequence_number,
                     manifest.sequence_number - expected
                 );
-                // Log warning but continue (eventually consistent)
+                // Gaps are filled from the source's history before discovery
+                // hands the newest manifest over, so one that remains could
+                // not be recovered. Log and continue (eventually consistent).
             }
         }
 
         Ok(())
     }
 
-    /// Download all files referenced in manifest
-    async fn download_manifest_files(&self, manifest: &ManifestFile) -> Result<DownloadResult> {
+    /// Download all files referenced in manifest that are missing locally.
+    /// Up to `max_concurrent_downloads` files are fetched at once; each file is
+    /// retried with backoff, and per-file state is kept in the in-progress entry.
+    async fn download_manifest_files(
+        &self,
+        manifest_cid: &str,
+        manifest: &ManifestFile,
+    ) -> Result<DownloadResult> {
         let mut downloaded = 0;
         let mut failed = 0;
         let mut skipped_existing = 0;
 
-        log::info!("Downloading {} files from manifest", manifest.files.len());
-
-        // Process files in batches (respect max_concurrent_downloads)
-        for (batch_num, chunk) in manifest
-            .files
-            .chunks(self.max_concurrent_downloads as usize)
-            .enumerate()
+        // A CID can appear under several paths; fetch it once
+        let mut missing: Vec<(String, String)> = Vec::new();
+        let mut file_states: HashMap<String, FileDownloadState> = HashMap::new();
         {
-            log::debug!("Processing batch {} ({} files)", batch_num + 1, chunk.len());
-
-            let mut tasks = Vec::new();
+            let state = self.state.read().await;
+            let previous = state
+                .in_progress_manifests
+                .get(manifest_cid)
+                .map(|p| &p.files);
 
-            for file in chunk {
-                // Check if file already exists locally
-                let exists = self.check_file_exists(&file.cid).await;
-
-                if exists {
-                    skipped_existing += 1;
-                    log::debug!("File already exists: {} ({})", file.path, file.cid);
+            for file in &manifest.files {
+                if file_states.contains_key(&file.cid) {
                     continue;
                 }
-
-                // Download from network
-                let api_client = self.api_client.clone();
-                let cid = file.cid.clone();
-                let path = file.path.clone();
-
-                let task = tokio::spawn(async move {
-                    match api_client.request_network_download(&cid).await {
-                        Ok(()) => {
-                            log::info!("Downloaded: {} ({})", path, cid);
-                            Ok(())
-                        }
-                        Err(e) => {
-                            log::error!("Failed to download {} ({}): {}", path, cid, e);
-                            Err(e)
-                        }
-                    }
-                });
-
-                tasks.push(task);
+                let attempts = previous
+                    .and_then(|files| files.get(&file.cid))
+                    .map(|f| f.attempts)
+                    .unwrap_or(0);
+                file_states.insert(
+                    file.cid.clone(),
+                    FileDownloadState {
+                        path: file.path.clone(),
+                        status: FileDownloadStatus::Pending,
+                        attempts,
+                        last_error: None,
+                    },
+                );
             }
+        }
 
-            // Wait for batch to complete
-            for task in tasks {
-                match task.await {
-                    Ok(Ok(())) => downloaded += 1,
-                    Ok(Err(_)) | Err(_) => failed += 1,
-                }
+        for (cid, file_state) in file_states.iter_mut() {
+            if self.check_file_exists(cid).await? {
+                file_state.status = FileDownloadStatus::Done;
+                skipped_existing += 1;
+            } else {
+                missing.push((cid.clone(), file_state.path.clone()));
             }
+        }
 
-            // Update progress
-            {
-                let mut state = self.state.write().await;
-                if let Some(manifest_cid_str) = &manifest.manifest_cid {
-                    if let Some(progress) = state.in_progress_manifests.get_mut(manifest_cid_str) {
-                        progress.files_downloaded = downloaded + skipped_existing;
-                        progress.files_failed = failed;
-                    }
-                }
+        let total = file_states.len() as u32;
+        {
+            let mut state = self.state.write().await;
+            if let Some(progress) = state.in_progress_manifests.get_mut(manifest_cid) {
+                progress.total_files = total;
+                progress.files_downloaded = skipped_existing;
+                progress.files_failed = 0;
+                progress.files = file_states;
             }
-            self.save_state().await?;
         }
+        self.save_state().await?;
 
         log::info!(
-            "Download complete: {} downloaded, {} skipped (existing), {} failed",
-            downloaded,
-            skipped_existing,
-            failed
+            "Downloading {} of {} files from manifest ({} already stored)",
+            missing.len(),
+            total,
+            skipped_existing
         );
 
-        Ok(DownloadResult {
-            downloaded,
-            failed,
-            skipped_existing,
-        })
-    }
-
-    /// Check if a file CID exists in local storage
-    async fn check_file_exists(&self, cid: &str) -> bool {
-        match self.api_client.list_data().await {
-            Ok(data_list) => data_list.content.iter().any(|item| item.cid == cid),
-            Err(e) => {
-                log::warn!("Failed to check file existence for {}: {}", cid, e);
-                false
+        let semaphore = Arc::new(Semaphore::new(
+            self.max_concurrent_downloads.load(Ordering::Relaxed).max(1) as usize,
+        ));
+        let fallback = if missing.is_empty() {
+            None
+        } else {
+            self.http_fallback(&manifest.source_peer_id, Some(manifest))
+                .await
+        };
+        let mut tasks = tokio::task::JoinSet::new();
+
+        for (cid, path) in missing {
+            let api_client = self.api_client.clone();
+            let semaphore = semaphore.clone();
+            let state = self.state.clone();
+            let manifest_cid = manifest_cid.to_string();
+            let fallback = fallback.clone();
+
+            tasks.spawn(async move {
+                let result = download_file_with_retry(
+                    &api_client,
+                    &semaphore,
+                    &state,
+                    &manifest_cid,
+                    &cid,
+                    &path,
+                    fallback.as_ref(),
+                )
+                .await;
+                (cid, path, result)
+            });
+        }
+
+        let mut completed = 0;
+        while let Some(joined) = tasks.join_next().await {
+            let (cid, path, result) = match joined {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    log::error!("Download task failed: {}", e);
+                    failed += 1;
+                    continue;
+                }
+            };
+
+            match &result {
+                Ok(()) => {
+                    downloaded += 1;
+                    log::info!("Downloaded: {} ({})", path, cid);
+                    self.local_index.write().await.cids.insert(cid.clone());
+                }
+                Err(e) => {
+                    failed += 1;
+                    log::error!("Failed to download {} ({}): {}", path, cid, e);
+                }
+            }
+
+            {
+                let mut state = self.state.write().await;
+                if let Some(progress) = state.in_progress_manifests.get_mut(manifest_cid) {
+                    if let Some(file) = progress.files.get_mut(&cid) {
+                        match &result {
+                            Ok(()) => {
+                                file.status = FileDownloadStatus::Done;
+                                file.last_error = None;
+                            }
+                            Err(e) => {
+                                file.status = FileDownloadStatus::Failed;
+                                file.last_error = Some(e.to_string());
+                            }
+                        }
+                    }
+                    progress.files_downloaded = downloaded + skipped_existing;
+                    progress.files_failed = failed;
+                }
+            }
+
+            completed += 1;
+            if completed % PROGRESS_SAVE_INTERVAL == 0 {
+                self.save_state().await?;
             }
         }
+        self.save_state().await?;
+
+        log::info!(
+            "Download complete: {} downloaded, {} skipped (existing), {} failed",
+            downloaded,
+            skipped_existing,
+            failed
+        );
+
+        if failed > 0 {
+            return Err(ArchivistError::SyncError(format!(
+                "{} of {} files failed to download",
+                failed, total
+            )));
+        }
+
+        Ok(DownloadResult {
+            downloaded,
+            failed,
+            skipped_existing,
+        })
+    }
+
+    /// Check if a file CID exists in local storage (via the local CID index).
+    /// Fails when the node's content can't be listed, so callers never
+    /// mistake an unreachable node for missing content.
+    async fn check_file_exists(&self, cid: &str) -> Result<bool> {
+        self.ensure_local_index().await?;
+        Ok(self.local_index.read().await.cids.contains(cid))
+    }
+
+    /// Rebuild the local CID index if it was invalidated or has expired
+    async fn ensure_local_index(&self) -> Result<()> {
+        let now = std::time::Instant::now();
+        if self.local_index.read().await.is_fresh(now) {
+            return Ok(());
+        }
+
+        let mut index = self.local_index.write().await;
+        if index.is_fresh(now) {
+            return Ok(());
+        }
+
+        let listing = self
+            .api_client
+            .list_data()
+            .await
+            .map(|data_list| data_list.content.into_iter().map(|item| item.cid).collect());
+        index.rebuild(listing, now).map_err(|e| {
+            log::warn!("Failed to list local content for CID index: {}", e);
+            e
+        })?;
+        log::debug!("Rebuilt local CID index ({} CIDs)", index.cids.len());
+        Ok(())
     }
 
     /// Enforce deletions from manifest tombstones
@@ -676,6 +2216,19 @@ impl BackupDaemon {
         let mut deleted = 0;
         let mut failed = 0;
         let mut not_found = 0;
+        let mut quarantined = 0;
+
+        // Files the source added back are no longer pending deletion
+        {
+            let mut state = self.state.write().await;
+            if !state.pending_deletions.is_empty() {
+                for file in &manifest.files {
+                    if state.pending_deletions.remove(&file.cid).is_some() {
+                        log::info!("{} is back in the source, deletion cancelled", file.path);
+                    }
+                }
+            }
+        }
 
         if manifest.deleted_files.is_empty() {
             log::debug!("No deletions to enforce");
@@ -683,6 +2236,7 @@ impl BackupDaemon {
                 deleted,
                 failed,
                 not_found,
+                quarantined,
             });
         }
 
@@ -691,7 +2245,35 @@ impl BackupDaemon {
             manifest.deleted_files.len()
         );
 
+        // CIDs still referenced by this manifest or by a retained snapshot stay
+        // in storage so older snapshots can be restored
+        let protected = match self.protected_cids(Some(manifest)).await {
+            Ok(cids) => cids,
+            Err(e) => {
+                log::warn!(
+                    "Could not determine CIDs held by retained snapshots, skipping deletions: {}",
+                    e
+                );
+                return Ok(DeletionResult {
+                    deleted,
+                    failed,
+                    not_found,
+                    quarantined,
+                });
+            }
+        };
+        let grace = self.deletion_grace();
+
         for tombstone in &manifest.deleted_files {
+            if protected.contains(&tombstone.cid) {
+                log::debug!(
+                    "Keeping {} ({}): referenced by a retained snapshot",
+                    tombstone.path,
+                    tombstone.cid
+                );
+                continue;
+            }
+
             log::info!(
                 "Processing deletion: {} ({})",
                 tombstone.path,
@@ -699,7 +2281,7 @@ impl BackupDaemon {
             );
 
             // Check if file exists locally
-            let exists = self.check_file_exists(&tombstone.cid).await;
+            let exists = self.check_file_exists(&tombstone.cid).await?;
 
             if !exists {
                 not_found += 1;
@@ -707,10 +2289,33 @@ impl BackupDaemon {
                 continue;
             }
 
+            // Quarantine during the grace period instead of deleting
+            if let Some(grace) = grace {
+                let now = Utc::now();
+                self.state
+                    .write()
+                    .await
+                    .pending_deletions
+                    .entry(tombstone.cid.clone())
+                    .or_insert_with(|| PendingDeletion {
+                        cid: tombstone.cid.clone(),
+                        path: tombstone.path.clone(),
+                        source_peer_id: manifest.source_peer_id.clone(),
+                        folder_id: manifest.folder_id.clone(),
+                        manifest_cid: manifest.manifest_cid.clone().unwrap_or_default(),
+                        reason: DeletionReason::Tombstone,
+                        queued_at: now,
+                        delete_after: now + grace,
+                    });
+                quarantined += 1;
+                continue;
+            }
+
             // Delete file
             match self.api_client.delete_file(&tombstone.cid).await {
                 Ok(_) => {
                     deleted += 1;
+                    self.local_index.write().await.cids.remove(&tombstone.cid);
                     log::info!("Deleted: {} ({})", tombstone.path, tombstone.cid);
                 }
                 Err(e) => {
@@ -726,8 +2331,9 @@ impl BackupDaemon {
         }
 
         log::info!(
-            "Deletion complete: {} deleted, {} not found, {} failed",
+            "Deletion complete: {} deleted, {} quarantined, {} not found, {} failed",
             deleted,
+            quarantined,
             not_found,
             failed
         );
@@ -736,6 +2342,7 @@ impl BackupDaemon {
             deleted,
             failed,
             not_found,
+            quarantined,
         })
     }
 
@@ -744,6 +2351,7 @@ impl BackupDaemon {
         &self,
         manifest_cid: &str,
         manifest: &ManifestFile,
+        path_prefixes: &[String],
         download_result: Result<DownloadResult>,
         deletion_result: Result<DeletionResult>,
         multiaddr: Option<&str>,
@@ -751,11 +2359,18 @@ impl BackupDaemon {
         let mut state = self.state.write().await;
 
         // Remove from in-progress
-        state.in_progress_manifests.remove(manifest_cid);
+        let files = state
+            .in_progress_manifests
+            .remove(manifest_cid)
+            .map(|p| p.files)
+            .unwrap_or_default();
 
-        match (download_result, deletion_result) {
+        let outcome = match (download_result, deletion_result) {
             (Ok(dl), Ok(del)) => {
                 // Success - mark as processed
+                state
+                    .failed_manifests
+                    .retain(|m| m.manifest_cid != manifest_cid);
                 state.processed_manifests.insert(
                     manifest_cid.to_string(),
                     ProcessedManifest {
@@ -767,6 +2382,11 @@ impl BackupDaemon {
                         file_count: dl.downloaded + dl.skipped_existing,
                         total_size_bytes: manifest.stats.total_size_bytes,
                         deleted_count: del.deleted,
+                        manifest_updated_at: Some(manifest.last_updated),
+                        pruned_at: None,
+                        ack_sent_at: None,
+                        path_prefixes: path_prefixes.to_vec(),
+                        file_cids: Some(manifest.files.iter().map(|f| f.cid.clone()).collect()),
                     },
                 );
 
@@ -777,42 +2397,410 @@ impl BackupDaemon {
                 state.stats.last_activity_at = Some(Utc::now());
 
                 log::info!(
-                    "Manifest processed successfully: {} (seq {}, {} files, {} deleted)",
+                    "Manifest processed successfully: {} (seq {}, {} files, {} deleted, {} pending deletion)",
                     manifest_cid,
                     manifest.sequence_number,
                     dl.downloaded + dl.skipped_existing,
-                    del.deleted
+                    del.deleted,
+                    del.quarantined
                 );
+                Ok(())
             }
             (Err(e), _) | (_, Err(e)) => {
-                // Failure - mark for retry
-                state.failed_manifests.push(FailedManifest {
-                    manifest_cid: manifest_cid.to_string(),
-                    source_peer_id: manifest.source_peer_id.clone(),
-                    failed_at: Utc::now(),
-                    error_message: e.to_string(),
-                    retry_count: 0,
-                    multiaddr: multiaddr.map(|s| s.to_string()),
-                });
+                // Failure - mark for retry, keeping per-file state
+                record_failed_manifest(
+                    &mut state,
+                    manifest_cid,
+                    &manifest.source_peer_id,
+                    multiaddr,
+                    &e.to_string(),
+                    files,
+                );
 
                 log::error!("Manifest processing failed: {} - {}", manifest_cid, e);
+                Err(e)
             }
-        }
+        };
 
         drop(state);
         self.save_state().await?;
+        outcome?;
+
+        // Pruning problems must not fail a manifest that was already processed
+        if let Err(e) = self
+            .prune_snapshots(&manifest.source_peer_id, &manifest.folder_id)
+            .await
+        {
+            log::warn!("Failed to prune snapshots of {}: {}", manifest.folder_id, e);
+        }
+        Ok(())
+    }
+
+    /// CIDs referenced by retained snapshots (plus an incoming manifest that
+    /// is about to become the newest snapshot of its folder)
+    async fn protected_cids(&self, incoming: Option<&ManifestFile>) -> Result<HashSet<String>> {
+        let retention = self.snapshot_retention.load(Ordering::Relaxed).max(1) as usize;
+
+        let mut protected = HashSet::new();
+        if let Some(manifest) = incoming {
+            protected.extend(manifest.files.iter().map(|f| f.cid.clone()));
+        }
+
+        // Snapshots processed before file CIDs were cached are fetched once
+        let uncached: Vec<String> = {
+            let state = self.state.read().await;
+            let retained = retained_snapshot_cids(
+                &state.processed_manifests,
+                retention,
+                incoming.map(|m| (m.source_peer_id.as_str(), m.folder_id.as_str())),
+            );
+            let mut uncached = Vec::new();
+            for manifest_cid in retained {
+                match state
+                    .processed_manifests
+                    .get(&manifest_cid)
+                    .and_then(|m| m.file_cids.as_ref())
+                {
+                    Some(cids) => protected.extend(cids.iter().cloned()),
+                    None => uncached.push(manifest_cid),
+                }
+            }
+            uncached
+        };
+
+        for manifest_cid in uncached {
+            let snapshot = fetch_manifest(&self.api_client, &manifest_cid).await?;
+            let cids: Vec<String> = snapshot.files.into_iter().map(|f| f.cid).collect();
+            protected.extend(cids.iter().cloned());
+            if let Some(m) = self
+                .state
+                .write()
+                .await
+                .processed_manifests
+                .get_mut(&manifest_cid)
+            {
+                m.file_cids = Some(cids);
+            }
+        }
+
+        Ok(protected)
+    }
+
+    /// Mark snapshots beyond the retention limit as pruned and, when tombstone
+    /// deletion is enabled, delete CIDs no retained snapshot references anymore
+    async fn prune_snapshots(&self, source_peer_id: &str, folder_id: &str) -> Result<()> {
+        let retention = self.snapshot_retention.load(Ordering::Relaxed).max(1) as usize;
+
+        let expired: Vec<String> = self
+            .folder_snapshots(source_peer_id, folder_id)
+            .await
+            .into_iter()
+            .filter(|m| m.pruned_at.is_none())
+            .skip(retention)
+            .map(|m| m.manifest_cid)
+            .collect();
+
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        log::info!(
+            "Pruning {} snapshots of folder {} beyond retention of {}",
+            expired.len(),
+            folder_id,
+            retention
+        );
+
+        // Collect expired CIDs (with the path and snapshot they came from)
+        // before the snapshots are marked pruned
+        let mut candidates: HashMap<String, (String, String)> = HashMap::new();
+        if self.auto_delete_tombstones.load(Ordering::Relaxed) {
+            for manifest_cid in &expired {
+                match fetch_manifest(&self.api_client, manifest_cid).await {
+                    Ok(snapshot) => candidates.extend(
+                        snapshot
+                            .files
+                            .into_iter()
+                            .map(|f| (f.cid, (f.path, manifest_cid.clone()))),
+                    ),
+                    Err(e) => log::warn!(
+                        "Failed to load pruned snapshot {}, its files are kept: {}",
+                        manifest_cid,
+                        e
+                    ),
+                }
+            }
+        }
+
+        {
+            let mut state = self.state.write().await;
+            let now = Utc::now();
+            for manifest_cid in &expired {
+                if let Some(m) = state.processed_manifests.get_mut(manifest_cid) {
+                    m.pruned_at = Some(now);
+                    m.file_cids = None;
+                }
+            }
+        }
+        self.save_state().await?;
+
+        if candidates.is_empty() {
+            return Ok(());
+        }
+
+        let protected = self.protected_cids(None).await?;
+        let grace = self.deletion_grace();
+
+        let mut deleted = 0u64;
+        for (cid, (path, manifest_cid)) in candidates
+            .iter()
+            .filter(|(cid, _)| !protected.contains(*cid))
+        {
+            if !self.check_file_exists(cid).await? {
+                continue;
+            }
+            if let Some(grace) = grace {
+                let now = Utc::now();
+                self.state
+                    .write()
+                    .await
+                    .pending_deletions
+                    .entry(cid.clone())
+                    .or_insert_with(|| PendingDeletion {
+                        cid: cid.clone(),
+                        path: path.clone(),
+                        source_peer_id: source_peer_id.to_string(),
+                        folder_id: folder_id.to_string(),
+                        manifest_cid: manifest_cid.clone(),
+                        reason: DeletionReason::SnapshotPruned,
+                        queued_at: now,
+                        delete_after: now + grace,
+                    });
+                continue;
+            }
+            match self.api_client.delete_file(cid).await {
+                Ok(_) => {
+                    deleted += 1;
+                    self.local_index.write().await.cids.remove(cid);
+                }
+                Err(e) => log::error!("Failed to delete unreferenced CID {}: {}", cid, e),
+            }
+        }
+
+        if deleted > 0 {
+            log::info!(
+                "Deleted {} CIDs no longer referenced by retained snapshots",
+                deleted
+            );
+            let mut state = self.state.write().await;
+            state.stats.total_files_deleted += deleted;
+        }
+        self.save_state().await?;
+
+        Ok(())
+    }
+
+    /// CIDs waiting out the deletion grace period (soonest deletion first)
+    pub async fn pending_deletions(&self) -> Vec<PendingDeletion> {
+        let state = self.state.read().await;
+        let mut pending: Vec<PendingDeletion> = state.pending_deletions.values().cloned().collect();
+        pending.sort_by_key(|p| p.delete_after);
+        pending
+    }
+
+    /// Whether a snapshot's files are still in storage: it is retained, or it
+    /// was pruned recently enough that its CIDs are still quarantined
+    pub fn snapshot_restorable(&self, snapshot: &ProcessedManifest) -> bool {
+        match (snapshot.pruned_at, self.deletion_grace()) {
+            (None, _) => true,
+            (Some(pruned_at), Some(grace)) => pruned_at + grace > Utc::now(),
+            (Some(_), None) => false,
+        }
+    }
+
+    /// Delete pending CIDs now, without waiting for their grace period.
+    /// Deletes all pending CIDs when `cids` is `None`. Returns the number deleted.
+    pub async fn confirm_pending_deletions(&self, cids: Option<Vec<String>>) -> Result<u32> {
+        let cids = match cids {
+            Some(cids) => cids,
+            None => self
+                .state
+                .read()
+                .await
+                .pending_deletions
+                .keys()
+                .cloned()
+                .collect(),
+        };
+        log::info!("Confirmed deletion of {} pending CIDs", cids.len());
+        self.delete_pending(cids).await
+    }
+
+    /// Delete pending CIDs whose grace period has ended
+    async fn purge_expired_deletions(&self) -> Result<()> {
+        let due = {
+            let state = self.state.read().await;
+            due_deletions(&state.pending_deletions, Utc::now())
+        };
+        if due.is_empty() {
+            return Ok(());
+        }
+
+        log::info!("Grace period ended for {} pending deletions", due.len());
+        self.delete_pending(due).await?;
         Ok(())
     }
 
+    /// Delete the given pending CIDs from local storage. CIDs that a retained
+    /// snapshot references again are dropped from the queue but kept in
+    /// storage; CIDs that fail to delete stay queued.
+    async fn delete_pending(&self, cids: Vec<String>) -> Result<u32> {
+        let protected = self.protected_cids(None).await?;
+        // Fail before deleting anything if the node's content can't be listed
+        self.ensure_local_index().await?;
+
+        let mut deleted = 0u32;
+        for cid in cids {
+            let Some(pending) = self.state.read().await.pending_deletions.get(&cid).cloned() else {
+                continue;
+            };
+
+            if protected.contains(&cid) {
+                log::debug!(
+                    "Keeping {} ({}): referenced by a retained snapshot",
+                    pending.path,
+                    cid
+                );
+            } else if self.check_file_exists(&cid).await? {
+                match self.api_client.delete_file(&cid).await {
+                    Ok(_) => {
+                        deleted += 1;
+                        self.local_index.write().await.cids.remove(&cid);
+                        log::info!("Deleted: {} ({})", pending.path, cid);
+                    }
+                    Err(e) => {
+                        log::error!("Failed to delete {} ({}): {}", pending.path, cid, e);
+                        continue;
+                    }
+                }
+            }
+
+            self.state.write().await.pending_deletions.remove(&cid);
+        }
+
+        {
+            let mut state = self.state.write().await;
+            state.stats.total_files_deleted += deleted as u64;
+            if deleted > 0 {
+                state.stats.last_activity_at = Some(Utc::now());
+            }
+        }
+        self.save_state().await?;
+
+        Ok(deleted)
+    }
+
+    /// Pick up manifests that were being processed when the app stopped.
+    /// Recent ones resume where they left off (files already stored are
+    /// skipped); stale ones, or ones that fail to resume, move to the failed
+    /// list with a reason so the retry pass handles them.
+    async fn recover_interrupted_manifests(&self) {
+        let interrupted: Vec<InProgressManifest> = self
+            .state
+            .read()
+            .await
+            .in_progress_manifests
+            .values()
+            .cloned()
+            .collect();
+
+        if interrupted.is_empty() {
+            return;
+        }
+
+        log::info!(
+            "Recovering {} manifests interrupted by a restart",
+            interrupted.len()
+        );
+
+        let now = Utc::now();
+        let (stale, resumable): (Vec<_>, Vec<_>) = interrupted
+            .into_iter()
+            .partition(|m| is_stale_in_progress(m, now));
+
+        if !stale.is_empty() {
+            let mut state = self.state.write().await;
+            for m in &stale {
+                log::warn!(
+                    "Interrupted manifest {} started at {} is stale, moving to failed",
+                    m.manifest_cid,
+                    m.started_at
+                );
+                record_failed_manifest(
+                    &mut state,
+                    &m.manifest_cid,
+                    &m.source_peer_id,
+                    m.multiaddr.as_deref(),
+                    &format!(
+                        "Interrupted by restart; stale since {} ({} of {} files done)",
+                        m.started_at.to_rfc3339(),
+                        m.files_downloaded,
+                        m.total_files
+                    ),
+                    HashMap::new(),
+                );
+            }
+        }
+        if let Err(e) = self.save_state().await {
+            log::error!("Failed to save daemon state: {}", e);
+        }
+
+        for m in resumable {
+            log::info!(
+                "Resuming manifest {} ({} of {} files done)",
+                m.manifest_cid,
+                m.files_downloaded,
+                m.total_files
+            );
+
+            let peer_id = (!m.source_peer_id.is_empty()).then_some(m.source_peer_id.as_str());
+            match self
+                .process_manifest(&m.manifest_cid, peer_id, m.multiaddr.as_deref())
+                .await
+            {
+                Ok(_) | Err(ArchivistError::QuotaExceeded(_)) => {}
+                Err(e) => {
+                    log::error!("Failed to resume manifest {}: {}", m.manifest_cid, e);
+                    let mut state = self.state.write().await;
+                    record_failed_manifest(
+                        &mut state,
+                        &m.manifest_cid,
+                        &m.source_peer_id,
+                        m.multiaddr.as_deref(),
+                        &format!("Resume after restart failed: {}", e),
+                        HashMap::new(),
+                    );
+                }
+            }
+        }
+
+        if let Err(e) = self.save_state().await {
+            log::error!("Failed to save daemon state: {}", e);
+        }
+    }
+
     /// Start the backup daemon background loop
     pub async fn start(self: Arc<Self>) {
         log::info!(
-            "Starting backup daemon (poll interval: {}s, max concurrent downloads: {}, trigger port: {})",
-            self.poll_interval_secs,
-            self.max_concurrent_downloads,
-            self.trigger_port
+            "Starting backup daemon (poll interval: {}s, max concurrent downloads: {})",
+            self.poll_interval_secs.load(Ordering::Relaxed),
+            self.max_concurrent_downloads.load(Ordering::Relaxed)
         );
 
+        let mut recovered = false;
+        // Source the next cycle is limited to (set by targeted triggers)
+        let mut only_source: Option<String> = None;
+
         loop {
             // Check if daemon is enabled
             if !self.is_enabled() {
@@ -820,8 +2808,13 @@ impl BackupDaemon {
                 continue;
             }
 
+            if !recovered {
+                self.recover_interrupted_manifests().await;
+                recovered = true;
+            }
+
             // Main processing cycle
-            match self.run_cycle().await {
+            match self.run_cycle(only_source.take().as_deref()).await {
                 Ok(processed_count) => {
                     if processed_count > 0 {
                         log::info!("Processed {} manifests this cycle", processed_count);
@@ -833,7 +2826,8 @@ impl BackupDaemon {
             }
 
             // Wait for next cycle OR trigger signal
-            let poll_interval = Duration::from_secs(self.poll_interval_secs);
+            let poll_interval =
+                Duration::from_secs(self.poll_interval_secs.load(Ordering::Relaxed));
             let mut trigger_rx = self.trigger_rx.write().await;
 
             tokio::select! {
@@ -841,21 +2835,36 @@ impl BackupDaemon {
                     // Normal poll interval elapsed
                     log::debug!("Poll interval elapsed, running cycle");
                 }
-                Some(_) = trigger_rx.recv() => {
-                    // Trigger received - run immediately
-                    log::info!("Trigger received, running cycle immediately");
+                Some(source) = trigger_rx.recv() => {
+                    // Trigger received - run immediately. Triggers that queued up
+                    // for different sources widen the cycle to all of them.
+                    only_source = source;
+                    while let Ok(more) = trigger_rx.try_recv() {
+                        if more != only_source {
+                            only_source = None;
+                        }
+                    }
+                    log::info!(
+                        "Trigger received, running cycle for {} immediately",
+                        only_source.as_deref().unwrap_or("all sources")
+                    );
                 }
             }
         }
     }
 
-    /// Run one processing cycle
-    async fn run_cycle(&self) -> Result<u32> {
+    /// Run one processing cycle, polling only `only_source` when given
+    async fn run_cycle(&self, only_source: Option<&str>) -> Result<u32> {
+        // 0. Start each cycle from a fresh view of local storage
+        self.local_index.write().await.invalidate();
+
         // 1. Discover manifests
-        let all_manifests = self.discover_manifests().await?;
+        let all_manifests = self.discover_manifests(only_source).await?;
 
-        // 2. Filter unprocessed
+        // 2. Filter unprocessed, putting manifests skipped by a sequence gap
+        // in front of the newest one
         let unprocessed = self.filter_unprocessed(all_manifests).await;
+        let unprocessed = self.fill_sequence_gaps(unprocessed).await;
 
         if unprocessed.is_empty() {
             log::debug!("No new manifests to process");
@@ -863,8 +2872,19 @@ impl BackupDaemon {
             log::info!("Found {} unprocessed manifests", unprocessed.len());
         }
 
-        // 3. Process each manifest
+        // 3. Process each manifest. Once one of a folder fails, its later
+        // sequences wait so they are never applied out of order.
+        let mut failed_folders: HashSet<(String, String)> = HashSet::new();
         for manifest in &unprocessed {
+            let folder_key = (manifest.source_peer_id.clone(), manifest.folder_id.clone());
+            if failed_folders.contains(&folder_key) {
+                log::info!(
+                    "Deferring manifest {} until earlier sequences of folder {} succeed",
+                    manifest.cid,
+                    manifest.folder_id
+                );
+                continue;
+            }
             match self
                 .process_manifest(
                     &manifest.cid,
@@ -876,26 +2896,40 @@ impl BackupDaemon {
                 Ok(_) => {
                     log::info!("Successfully processed manifest: {}", manifest.cid);
                 }
+                Err(ArchivistError::QuotaExceeded(_)) => {
+                    failed_folders.insert(folder_key);
+                }
                 Err(e) => {
+                    failed_folders.insert(folder_key);
                     log::error!("Failed to process manifest {}: {}", manifest.cid, e);
                     // Store as failed with multiaddr for retry
                     let mut state = self.state.write().await;
-                    state.failed_manifests.push(FailedManifest {
-                        manifest_cid: manifest.cid.clone(),
-                        source_peer_id: manifest.source_peer_id.clone(),
-                        failed_at: Utc::now(),
-                        error_message: e.to_string(),
-                        retry_count: 0,
-                        multiaddr: manifest.multiaddr.clone(),
-                    });
+                    record_failed_manifest(
+                        &mut state,
+                        &manifest.cid,
+                        &manifest.source_peer_id,
+                        manifest.multiaddr.as_deref(),
+                        &e.to_string(),
+                        HashMap::new(),
+                    );
                 }
             }
         }
 
-        // 4. Retry failed manifests (if retry count < max)
+        // 4. Retry failed manifests (if retry count < max), then re-check
+        // manifests blocked by quota
         self.retry_failed_manifests().await?;
+        self.recheck_blocked_manifests().await;
+
+        // 5. Delete quarantined CIDs whose grace period ended
+        if let Err(e) = self.purge_expired_deletions().await {
+            log::warn!("Failed to purge expired pending deletions: {}", e);
+        }
+
+        // 6. Deliver acks the sources haven't recorded yet
+        self.send_pending_acks().await;
 
-        // 5. Update last poll time
+        // 7. Update last poll time
         {
             let mut state = self.state.write().await;
             state.last_poll_time = Utc::now();
@@ -909,21 +2943,33 @@ impl BackupDaemon {
     async fn retry_failed_manifests(&self) -> Result<()> {
         let mut state = self.state.write().await;
         let mut to_retry = Vec::new();
+        let now = Utc::now();
+        let max_retries = self.max_retries.load(Ordering::Relaxed);
 
-        // Find manifests eligible for retry (retry_count < max_retries)
-        state.failed_manifests.retain(|m| {
-            if m.retry_count < self.max_retries {
-                to_retry.push(m.clone());
-                false // Remove from failed list
-            } else {
-                log::warn!(
-                    "Manifest {} exceeded max retries ({}), giving up",
+        // Sources whose circuit is open: retrying their manifests would only fail
+        let open_circuits: HashSet<String> = state
+            .source_health
+            .values()
+            .filter(|h| h.status == SourceHealthStatus::CircuitOpen)
+            .filter_map(|h| h.peer_id.clone())
+            .collect();
+
+        // Find manifests eligible for retry (retry_count < max_retries, backoff
+        // elapsed). They stay in the failed list (with their per-file state)
+        // until they succeed; ones out of retries rest there until re-admitted.
+        for m in state.failed_manifests.iter_mut() {
+            let circuit_open = open_circuits.contains(&m.source_peer_id);
+            match retry_decision(m, max_retries, circuit_open, now) {
+                RetryDecision::Retry => to_retry.push(m.clone()),
+                RetryDecision::Wait => {}
+                RetryDecision::GiveUp => log::warn!(
+                    "Manifest {} exceeded max retries ({}), giving up for {}h",
                     m.manifest_cid,
-                    self.max_retries
-                );
-                true // Keep (exceeded max retries)
+                    max_retries,
+                    EXHAUSTED_RETRY_COOLDOWN_SECS / 3600
+                ),
             }
-        });
+        }
 
         drop(state);
 
@@ -932,12 +2978,12 @@ impl BackupDaemon {
         }
 
         // Retry each
-        for mut failed in to_retry {
+        for failed in to_retry {
             log::info!(
                 "Retrying failed manifest: {} (attempt {}/{})",
                 failed.manifest_cid,
-                failed.retry_count + 1,
-                self.max_retries
+                failed.retry_count,
+                max_retries
             );
 
             match self
@@ -952,14 +2998,18 @@ impl BackupDaemon {
                     // Success - already marked as processed in finalize_manifest_processing
                     log::info!("Retry succeeded for manifest: {}", failed.manifest_cid);
                 }
+                Err(ArchivistError::QuotaExceeded(_)) => {}
                 Err(e) => {
-                    // Failed again - increment retry count
-                    failed.retry_count += 1;
-                    failed.error_message = e.to_string();
-                    failed.failed_at = Utc::now();
-
+                    // Failed again - record the latest error
                     let mut state = self.state.write().await;
-                    state.failed_manifests.push(failed);
+                    record_failed_manifest(
+                        &mut state,
+                        &failed.manifest_cid,
+                        &failed.source_peer_id,
+                        failed.multiaddr.as_deref(),
+                        &e.to_string(),
+                        HashMap::new(),
+                    );
                 }
             }
         }
@@ -971,33 +3021,50 @@ impl BackupDaemon {
     /// Manually retry a specific failed manifest
     pub async fn retry_manifest(&self, manifest_cid: &str) -> Result<()> {
         log::info!("Manual retry requested for manifest: {}", manifest_cid);
+        self.local_index.write().await.invalidate();
 
-        // Find and remove from failed list, capturing peer info
-        let mut state = self.state.write().await;
+        // Capture peer info; the entry stays until the retry succeeds
+        let state = self.state.read().await;
         let failed_info = state
             .failed_manifests
             .iter()
             .find(|m| m.manifest_cid == manifest_cid)
             .map(|m| (m.source_peer_id.clone(), m.multiaddr.clone()));
-        state
-            .failed_manifests
-            .retain(|m| m.manifest_cid != manifest_cid);
         drop(state);
 
         // Process manifest with peer info if available
         let (peer_id, multiaddr) = failed_info.unwrap_or_default();
-        self.process_manifest(
-            manifest_cid,
-            if peer_id.is_empty() {
-                None
-            } else {
-                Some(peer_id.as_str())
-            },
-            multiaddr.as_deref(),
-        )
-        .await?;
+        let result = self
+            .process_manifest(
+                manifest_cid,
+                if peer_id.is_empty() {
+                    None
+                } else {
+                    Some(peer_id.as_str())
+                },
+                multiaddr.as_deref(),
+            )
+            .await;
 
-        Ok(())
+        if let Some(e) = result
+            .as_ref()
+            .err()
+            .filter(|e| !matches!(e, ArchivistError::QuotaExceeded(_)))
+        {
+            let mut state = self.state.write().await;
+            record_failed_manifest(
+                &mut state,
+                manifest_cid,
+                &peer_id,
+                multiaddr.as_deref(),
+                &e.to_string(),
+                HashMap::new(),
+            );
+            drop(state);
+            self.save_state().await?;
+        }
+
+        result
     }
 
     /// Pause the daemon (disable processing)
@@ -1014,3 +3081,1395 @@ impl BackupDaemon {
         Ok(())
     }
 }
+
+/// Add or update the failed entry for a manifest. An existing entry keeps its
+/// retry count, and keeps its per-file state unless newer state is given.
+/// Any leftover in-progress entry is removed (its file state is kept), so a
+/// manifest can never stay marked in-progress after it failed. The attempt
+/// is also appended to the failure history.
+fn record_failed_manifest(
+    state: &mut DaemonState,
+    manifest_cid: &str,
+    source_peer_id: &str,
+    multiaddr: Option<&str>,
+    error_message: &str,
+    files: HashMap<String, FileDownloadState>,
+) {
+    let in_progress = state.in_progress_manifests.remove(manifest_cid);
+    let folder_id = in_progress.as_ref().and_then(|p| p.folder_id.clone());
+    let sequence_number = in_progress.as_ref().map(|p| p.sequence_number);
+    let in_progress_files = in_progress.map(|p| p.files).unwrap_or_default();
+    let files = if files.is_empty() {
+        in_progress_files
+    } else {
+        files
+    };
+
+    if let Some(existing) = state
+        .failed_manifests
+        .iter_mut()
+        .find(|m| m.manifest_cid == manifest_cid)
+    {
+        existing.failed_at = Utc::now();
+        existing.error_message = error_message.to_string();
+        existing.next_retry_at = Some(manifest_retry_at(existing.retry_count + 1));
+        if multiaddr.is_some() {
+            existing.multiaddr = multiaddr.map(|s| s.to_string());
+        }
+        if !files.is_empty() {
+            existing.files = files;
+        }
+        if folder_id.is_some() {
+            existing.folder_id = folder_id;
+            existing.sequence_number = sequence_number;
+        }
+        let record = failure_record(existing);
+        push_failure_record(state, record);
+        return;
+    }
+
+    let failed = FailedManifest {
+        manifest_cid: manifest_cid.to_string(),
+        source_peer_id: source_peer_id.to_string(),
+        failed_at: Utc::now(),
+        error_message: error_message.to_string(),
+        retry_count: 0,
+        multiaddr: multiaddr.map(|s| s.to_string()),
+        files,
+        next_retry_at: Some(manifest_retry_at(1)),
+        folder_id,
+        sequence_number,
+        exhausted_at: None,
+    };
+    push_failure_record(state, failure_record(&failed));
+    state.failed_manifests.push(failed);
+}
+
+/// History record of a failed manifest's latest attempt
+pub(crate) fn failure_record(m: &FailedManifest) -> ManifestFailureRecord {
+    let count =
+        |status: FileDownloadStatus| m.files.values().filter(|f| f.status == status).count() as u32;
+    ManifestFailureRecord {
+        manifest_cid: m.manifest_cid.clone(),
+        source_peer_id: m.source_peer_id.clone(),
+        folder_id: m.folder_id.clone(),
+        sequence_number: m.sequence_number,
+        failed_at: m.failed_at,
+        error_message: m.error_message.clone(),
+        retry_count: m.retry_count,
+        files_done: count(FileDownloadStatus::Done),
+        files_failed: count(FileDownloadStatus::Failed),
+    }
+}
+
+fn push_failure_record(state: &mut DaemonState, record: ManifestFailureRecord) {
+    state.failure_history.push(record);
+    if state.failure_history.len() > MAX_FAILURE_HISTORY {
+        let excess = state.failure_history.len() - MAX_FAILURE_HISTORY;
+        state.failure_history.drain(..excess);
+    }
+}
+
+/// What the retry pass does with a failed manifest this cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RetryDecision {
+    /// Retry now
+    Retry,
+    /// Backoff not elapsed, source circuit open, or resting after giving up
+    Wait,
+    /// Retries just ran out; the manifest rests until the cooldown ends
+    GiveUp,
+}
+
+/// Decide what to do with a failed manifest, updating its retry bookkeeping.
+/// A manifest that gave up is re-admitted with a fresh retry count once
+/// `EXHAUSTED_RETRY_COOLDOWN_SECS` have passed, so none is stuck for good.
+fn retry_decision(
+    m: &mut FailedManifest,
+    max_retries: u32,
+    circuit_open: bool,
+    now: DateTime<Utc>,
+) -> RetryDecision {
+    if m.retry_count >= max_retries {
+        match m.exhausted_at {
+            None => {
+                m.exhausted_at = Some(now);
+                return RetryDecision::GiveUp;
+            }
+            Some(at) if now - at < chrono::Duration::seconds(EXHAUSTED_RETRY_COOLDOWN_SECS) => {
+                return RetryDecision::Wait;
+            }
+            Some(_) => {
+                log::info!(
+                    "Re-admitting manifest {} after its retry cooldown",
+                    m.manifest_cid
+                );
+                m.retry_count = 0;
+                m.exhausted_at = None;
+                m.next_retry_at = None;
+            }
+        }
+    }
+
+    if m.next_retry_at.is_some_and(|t| t > now) || circuit_open {
+        return RetryDecision::Wait;
+    }
+    m.retry_count += 1;
+    RetryDecision::Retry
+}
+
+/// When a manifest that has failed `failures` times may be retried next
+fn manifest_retry_at(failures: u32) -> DateTime<Utc> {
+    let delay = backoff_with_jitter(MANIFEST_RETRY_BASE_SECS, BACKOFF_MAX_SECS, failures);
+    Utc::now() + chrono::Duration::seconds(delay.as_secs() as i64)
+}
+
+/// Bytes a source occupies: the size of the latest retained snapshot of each
+/// of its folders, except `except_folder` (the folder being replaced)
+fn source_stored_bytes(
+    processed: &HashMap<String, ProcessedManifest>,
+    source_peer_id: &str,
+    except_folder: &str,
+) -> u64 {
+    let mut latest: HashMap<&str, &ProcessedManifest> = HashMap::new();
+    for m in processed.values().filter(|m| {
+        m.source_peer_id == source_peer_id && m.folder_id != except_folder && m.pruned_at.is_none()
+    }) {
+        let entry = latest.entry(m.folder_id.as_str()).or_insert(m);
+        if m.sequence_number > entry.sequence_number {
+            *entry = m;
+        }
+    }
+    latest.values().map(|m| m.total_size_bytes).sum()
+}
+
+/// Why a manifest must wait, or `None` if it can be processed. `required_bytes`
+/// is what still has to be downloaded; `folder_bytes` the folder's full size,
+/// which counts against the source's limit.
+fn admission_block_reason(
+    required_bytes: u64,
+    folder_bytes: u64,
+    available_bytes: Option<u64>,
+    source_limit_bytes: Option<u64>,
+    source_used_bytes: u64,
+) -> Option<String> {
+    if let Some(available) = available_bytes {
+        if required_bytes > available {
+            return Some(format!(
+                "needs {} bytes but only {} bytes of node quota are free",
+                required_bytes, available
+            ));
+        }
+    }
+    if let Some(limit) = source_limit_bytes {
+        let projected = source_used_bytes.saturating_add(folder_bytes);
+        if projected > limit {
+            return Some(format!(
+                "source would use {} bytes, over its limit of {} bytes",
+                projected, limit
+            ));
+        }
+    }
+    None
+}
+
+/// Files stored and failed (and bytes stored) for a manifest, from its
+/// per-file download state; each CID counts once
+fn file_outcome(
+    manifest: &ManifestFile,
+    files: &HashMap<String, FileDownloadState>,
+) -> (u32, u32, u64) {
+    let mut seen = HashSet::new();
+    let (mut stored, mut failed, mut bytes) = (0, 0, 0);
+    for file in manifest
+        .files
+        .iter()
+        .filter(|f| seen.insert(f.cid.as_str()))
+    {
+        match files.get(&file.cid).map(|f| f.status) {
+            Some(FileDownloadStatus::Done) => {
+                stored += 1;
+                bytes += file.size_bytes;
+            }
+            Some(FileDownloadStatus::Failed) => failed += 1,
+            _ => {}
+        }
+    }
+    (stored, failed, bytes)
+}
+
+/// Pending deletions whose grace period has ended
+fn due_deletions(pending: &HashMap<String, PendingDeletion>, now: DateTime<Utc>) -> Vec<String> {
+    pending
+        .values()
+        .filter(|p| p.delete_after <= now)
+        .map(|p| p.cid.clone())
+        .collect()
+}
+
+/// Highest processed sequence of a source folder
+fn last_processed_sequence(
+    state: &DaemonState,
+    source_peer_id: &str,
+    folder_id: &str,
+) -> Option<u64> {
+    state
+        .processed_manifests
+        .values()
+        .filter(|m| m.source_peer_id == source_peer_id && m.folder_id == folder_id)
+        .map(|m| m.sequence_number)
+        .max()
+}
+
+/// Manifests from a folder's history that fall between the last processed
+/// sequence and the newest one (oldest first), and the sequences in that
+/// range the history no longer has
+fn gap_fill_plan(
+    last_processed: u64,
+    newest: u64,
+    history: &[ManifestInfo],
+) -> (Vec<ManifestInfo>, Vec<u64>) {
+    let mut gap: Vec<ManifestInfo> = history
+        .iter()
+        .filter(|m| m.sequence_number > last_processed && m.sequence_number < newest)
+        .cloned()
+        .collect();
+    gap.sort_by_key(|m| m.sequence_number);
+    gap.dedup_by_key(|m| m.sequence_number);
+
+    let unavailable = (last_processed + 1..newest)
+        .filter(|seq| !gap.iter().any(|m| m.sequence_number == *seq))
+        .collect();
+    (gap, unavailable)
+}
+
+/// Health map key for a source peer
+fn source_key(host: &str, port: u16) -> String {
+    format!("{}:{}", host, port)
+}
+
+/// Check a trigger against the secrets of the enabled source peers. A signed
+/// trigger must verify against one of them; unsigned triggers are only
+/// accepted when explicitly allowed and no enabled source has a secret.
+fn authorize_trigger(
+    source_peers: &[SourcePeerConfig],
+    request: &TriggerRequest,
+    allow_unsigned: bool,
+    now: DateTime<Utc>,
+) -> TriggerAuth {
+    let mut with_secret = source_peers
+        .iter()
+        .filter(|p| p.enabled)
+        .filter_map(|p| p.ack_secret.as_deref().map(|secret| (p, secret)))
+        .peekable();
+
+    if request.signature.is_none() {
+        return if allow_unsigned && with_secret.peek().is_none() {
+            TriggerAuth::Open
+        } else {
+            TriggerAuth::Rejected
+        };
+    }
+
+    with_secret
+        .find(|(_, secret)| request.verify(secret, now))
+        .map(|(p, _)| TriggerAuth::Source(source_key(&p.host, p.manifest_port)))
+        .unwrap_or(TriggerAuth::Rejected)
+}
+
+/// Exponential backoff after `failures` consecutive failures, capped at
+/// `max_secs`, with "equal jitter": half the delay is fixed and the other half
+/// random, so sources and manifests that failed together don't retry in lockstep.
+fn backoff_with_jitter(base_secs: u64, max_secs: u64, failures: u32) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    let delay = base_secs.max(1).saturating_mul(1 << exponent).min(max_secs);
+    let half = delay / 2;
+    Duration::from_secs(delay - half + random_below(half + 1))
+}
+
+/// Cheap random number in `0..bound`, good enough for jitter
+fn random_below(bound: u64) -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    hasher.finish() % bound.max(1)
+}
+
+/// Whether an interrupted manifest is too old to resume directly
+fn is_stale_in_progress(manifest: &InProgressManifest, now: DateTime<Utc>) -> bool {
+    now.signed_duration_since(manifest.started_at).num_seconds() > STALE_IN_PROGRESS_SECS
+}
+
+/// Delay before the next attempt at one file
+fn file_retry_backoff(attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    Duration::from_secs((FILE_RETRY_BASE_SECS << exponent).min(FILE_RETRY_MAX_SECS))
+}
+
+/// Fetch one file into the local node, retrying with backoff and trying the
+/// HTTP fallback (if any) once the P2P attempts are used up. A semaphore
+/// permit is held only while a request is in flight, not while backing off.
+async fn download_file_with_retry(
+    api_client: &NodeApiClient,
+    semaphore: &Semaphore,
+    state: &RwLock<DaemonState>,
+    manifest_cid: &str,
+    cid: &str,
+    path: &str,
+    fallback: Option<&HttpFallback>,
+) -> Result<()> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+
+        let result = {
+            let _permit = semaphore.acquire().await.map_err(|e| {
+                ArchivistError::SyncError(format!("Download scheduler closed: {}", e))
+            })?;
+
+            {
+                let mut state = state.write().await;
+                if let Some(file) = state
+                    .in_progress_manifests
+                    .get_mut(manifest_cid)
+                    .and_then(|p| p.files.get_mut(cid))
+                {
+                    file.status = FileDownloadStatus::Downloading;
+                    file.attempts += 1;
+                }
+            }
+
+            api_client.request_network_download(cid).await
+        };
+
+        match result {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < FILE_DOWNLOAD_ATTEMPTS => {
+                let delay = file_retry_backoff(attempt);
+                log::warn!(
+                    "Download of {} ({}) failed (attempt {}/{}), retrying in {}s: {}",
+                    path,
+                    cid,
+                    attempt,
+                    FILE_DOWNLOAD_ATTEMPTS,
+                    delay.as_secs(),
+                    e
+                );
+
+                {
+                    let mut state = state.write().await;
+                    if let Some(file) = state
+                        .in_progress_manifests
+                        .get_mut(manifest_cid)
+                        .and_then(|p| p.files.get_mut(cid))
+                    {
+                        file.status = FileDownloadStatus::Pending;
+                        file.last_error = Some(e.to_string());
+                    }
+                }
+
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => {
+                let Some(fallback) = fallback else {
+                    return Err(e);
+                };
+                log::warn!(
+                    "Download of {} ({}) failed over P2P, trying HTTP fallback: {}",
+                    path,
+                    cid,
+                    e
+                );
+                let _permit = semaphore.acquire().await.map_err(|e| {
+                    ArchivistError::SyncError(format!("Download scheduler closed: {}", e))
+                })?;
+                return fallback
+                    .fetch_into_node(api_client, cid)
+                    .await
+                    .map_err(|fallback_err| {
+                        ArchivistError::SyncError(format!(
+                            "{} (HTTP fallback: {})",
+                            e, fallback_err
+                        ))
+                    });
+            }
+        }
+    }
+}
+
+/// Manifest CIDs of the snapshots kept under `retention` for every source folder,
+/// leaving a slot free in `incoming`'s folder for the manifest being processed
+fn retained_snapshot_cids(
+    processed: &HashMap<String, ProcessedManifest>,
+    retention: usize,
+    incoming: Option<(&str, &str)>,
+) -> Vec<String> {
+    let mut by_folder: HashMap<(&str, &str), Vec<&ProcessedManifest>> = HashMap::new();
+    for m in processed.values().filter(|m| m.pruned_at.is_none()) {
+        by_folder
+            .entry((m.source_peer_id.as_str(), m.folder_id.as_str()))
+            .or_default()
+            .push(m);
+    }
+
+    let mut retained = Vec::new();
+    for (folder, mut snapshots) in by_folder {
+        snapshots.sort_by_key(|m| std::cmp::Reverse(m.sequence_number));
+        let keep = if incoming == Some(folder) {
+            retention.saturating_sub(1)
+        } else {
+            retention
+        };
+        retained.extend(
+            snapshots
+                .into_iter()
+                .take(keep)
+                .map(|m| m.manifest_cid.clone()),
+        );
+    }
+    retained
+}
+
+/// Folders a source advertised, annotated with how `peer` mirrors them.
+/// First-seen times carry over from the `previous` poll.
+fn source_folders(
+    peer: &SourcePeerConfig,
+    manifests: &[ManifestInfo],
+    previous: &[SourceFolder],
+    now: DateTime<Utc>,
+) -> Vec<SourceFolder> {
+    let mut folders: Vec<SourceFolder> = manifests
+        .iter()
+        .map(|m| SourceFolder {
+            folder_id: m.folder_id.clone(),
+            folder_path: m.folder_path.clone(),
+            latest_sequence: m.sequence_number,
+            file_count: m.file_count,
+            total_size_bytes: m.total_size_bytes,
+            mirrored: peer.mirrors_folder(&m.folder_id),
+            path_prefixes: peer.path_prefixes(&m.folder_id).to_vec(),
+            first_seen_at: previous
+                .iter()
+                .find(|f| f.folder_id == m.folder_id)
+                .and_then(|f| f.first_seen_at)
+                .or(Some(now)),
+        })
+        .collect();
+    folders.sort_by(|a, b| a.folder_id.cmp(&b.folder_id));
+    folders
+}
+
+/// Whether a manifest path lies under one of `prefixes` (empty = any path).
+/// Prefixes match whole path components, with either separator.
+fn path_matches_prefixes(path: &str, prefixes: &[String]) -> bool {
+    if prefixes.is_empty() {
+        return true;
+    }
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches('/');
+    prefixes.iter().any(|prefix| {
+        let prefix = prefix.replace('\\', "/");
+        let prefix = prefix.trim_matches('/');
+        prefix.is_empty()
+            || path == prefix
+            || path
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('/'))
+    })
+}
+
+/// Drop the files and tombstones outside `prefixes` from a manifest, keeping
+/// its stats in line with what is left
+pub(crate) fn filter_manifest_paths(manifest: &mut ManifestFile, prefixes: &[String]) {
+    if prefixes.is_empty() {
+        return;
+    }
+    manifest
+        .files
+        .retain(|f| path_matches_prefixes(&f.path, prefixes));
+    manifest
+        .deleted_files
+        .retain(|f| path_matches_prefixes(&f.path, prefixes));
+    manifest.stats = ManifestStats {
+        total_files: manifest.files.len() as u32,
+        total_size_bytes: manifest.files.iter().map(|f| f.size_bytes).sum(),
+    };
+}
+
+/// Load and parse a manifest, reading it from local storage first and
+/// falling back to fetching it from the network
+pub(crate) async fn fetch_manifest(
+    api_client: &NodeApiClient,
+    manifest_cid: &str,
+) -> Result<ManifestFile> {
+    let manifest_bytes = match api_client.download_file(manifest_cid).await {
+        Ok(bytes) => {
+            log::debug!("Manifest {} found in local storage", manifest_cid);
+            bytes
+        }
+        Err(_) => {
+            log::info!(
+                "Manifest {} not in local storage, fetching from network",
+                manifest_cid
+            );
+            api_client.request_network_download(manifest_cid).await?;
+            api_client.download_file(manifest_cid).await?
+        }
+    };
+
+    let manifest_json = String::from_utf8(manifest_bytes)
+        .map_err(|e| ArchivistError::SyncError(format!("Invalid UTF-8 in manifest: {}", e)))?;
+    let manifest: ManifestFile = serde_json::from_str(&manifest_json)?;
+
+    Ok(manifest)
+}
+
+// Custom rejection for triggers with a missing or wrong signature
+#[derive(Debug)]
+struct UnauthorizedTriggerError;
+impl warp::reject::Reject for UnauthorizedTriggerError {}
+
+// Custom rejection for trigger bodies that aren't a `TriggerRequest`
+#[derive(Debug)]
+struct InvalidTriggerBodyError;
+impl warp::reject::Reject for InvalidTriggerBodyError {}
+
+// Custom rejection for triggers the main loop could not be woken for
+#[derive(Debug)]
+struct TriggerUnavailableError;
+impl warp::reject::Reject for TriggerUnavailableError {}
+
+/// Handle POST /trigger
+async fn handle_trigger(
+    body: warp::hyper::body::Bytes,
+    daemon: Arc<BackupDaemon>,
+) -> std::result::Result<impl warp::Reply, warp::Rejection> {
+    let request: TriggerRequest = if body.iter().all(u8::is_ascii_whitespace) {
+        TriggerRequest::default()
+    } else {
+        serde_json::from_slice(&body).map_err(|e| {
+            log::warn!("Invalid trigger body: {}", e);
+            warp::reject::custom(InvalidTriggerBodyError)
+        })?
+    };
+
+    let reply = match daemon.accept_trigger(&request).await? {
+        TriggerOutcome::Polling(source) => serde_json::json!({
+            "status": "ok",
+            "message": "Poll triggered",
+            "source": source
+        }),
+        TriggerOutcome::AlreadyProcessed => serde_json::json!({
+            "status": "ok",
+            "message": "Manifest already processed"
+        }),
+    };
+    log::info!("Trigger request received and processed");
+    Ok(warp::reply::json(&reply))
+}
+
+/// Map trigger server rejections to JSON responses so every request is audited
+async fn handle_trigger_rejection(
+    err: warp::Rejection,
+) -> std::result::Result<impl warp::Reply, std::convert::Infallible> {
+    if err.find::<RateLimitedError>().is_some() {
+        Ok(rate_limited_reply())
+    } else if err.find::<UnauthorizedTriggerError>().is_some() {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "status": "error",
+                "message": "Missing or invalid trigger signature"
+            })),
+            warp::http::StatusCode::UNAUTHORIZED,
+        ))
+    } else if err.find::<TriggerUnavailableError>().is_some() {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "status": "error",
+                "message": "Backup daemon is not accepting triggers"
+            })),
+            warp::http::StatusCode::SERVICE_UNAVAILABLE,
+        ))
+    } else if err.is_not_found() {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "status": "error",
+                "message": "Not found"
+            })),
+            warp::http::StatusCode::NOT_FOUND,
+        ))
+    } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "status": "error",
+                "message": "Method not allowed"
+            })),
+            warp::http::StatusCode::METHOD_NOT_ALLOWED,
+        ))
+    } else {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "status": "error",
+                "message": "Bad request"
+            })),
+            warp::http::StatusCode::BAD_REQUEST,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::config::FolderMirrorRule;
+
+    fn snapshot(cid: &str, folder: &str, seq: u64, pruned: bool) -> ProcessedManifest {
+        ProcessedManifest {
+            manifest_cid: cid.to_string(),
+            source_peer_id: "peer-a".to_string(),
+            sequence_number: seq,
+            folder_id: folder.to_string(),
+            processed_at: Utc::now(),
+            file_count: 1,
+            total_size_bytes: 10,
+            deleted_count: 0,
+            manifest_updated_at: None,
+            pruned_at: pruned.then(Utc::now),
+            ack_sent_at: None,
+            path_prefixes: Vec::new(),
+            file_cids: None,
+        }
+    }
+
+    #[test]
+    fn test_file_retry_backoff() {
+        assert_eq!(file_retry_backoff(1), Duration::from_secs(2));
+        assert_eq!(file_retry_backoff(2), Duration::from_secs(4));
+        assert_eq!(file_retry_backoff(3), Duration::from_secs(8));
+        assert_eq!(
+            file_retry_backoff(30),
+            Duration::from_secs(FILE_RETRY_MAX_SECS)
+        );
+    }
+
+    #[test]
+    fn test_record_failed_manifest_keeps_retry_count_and_files() {
+        let mut state = DaemonState::default();
+        let mut files = HashMap::new();
+        files.insert(
+            "zFile".to_string(),
+            FileDownloadState {
+                path: "a.txt".to_string(),
+                status: FileDownloadStatus::Failed,
+                attempts: 3,
+                last_error: Some("timeout".to_string()),
+            },
+        );
+
+        record_failed_manifest(&mut state, "zManifest", "peer", None, "first", files);
+        state.failed_manifests[0].retry_count = 2;
+        record_failed_manifest(
+            &mut state,
+            "zManifest",
+            "peer",
+            Some("/ip4/10.0.0.2/tcp/8070"),
+            "second",
+            HashMap::new(),
+        );
+
+        assert_eq!(state.failed_manifests.len(), 1);
+        let failed = &state.failed_manifests[0];
+        assert_eq!(failed.retry_count, 2);
+        assert_eq!(failed.error_message, "second");
+        assert_eq!(failed.files["zFile"].attempts, 3);
+        assert!(failed.multiaddr.is_some());
+
+        // Both attempts stay in the history
+        let errors: Vec<&str> = state
+            .failure_history
+            .iter()
+            .map(|r| r.error_message.as_str())
+            .collect();
+        assert_eq!(errors, vec!["first", "second"]);
+        assert_eq!(state.failure_history[1].retry_count, 2);
+        assert_eq!(state.failure_history[1].files_failed, 1);
+    }
+
+    #[test]
+    fn test_retry_decision_gives_up_once_then_readmits() {
+        let mut state = DaemonState::default();
+        record_failed_manifest(
+            &mut state,
+            "zManifest",
+            "peer",
+            None,
+            "boom",
+            HashMap::new(),
+        );
+        let m = &mut state.failed_manifests[0];
+        let now = Utc::now();
+        m.next_retry_at = None;
+
+        assert_eq!(retry_decision(m, 1, false, now), RetryDecision::Retry);
+        assert_eq!(m.retry_count, 1);
+
+        // Out of retries: give up once, then rest without logging again
+        assert_eq!(retry_decision(m, 1, false, now), RetryDecision::GiveUp);
+        assert_eq!(m.exhausted_at, Some(now));
+        assert_eq!(retry_decision(m, 1, false, now), RetryDecision::Wait);
+
+        // After the cooldown it is retried with a fresh count
+        let later = now + chrono::Duration::seconds(EXHAUSTED_RETRY_COOLDOWN_SECS + 1);
+        assert_eq!(retry_decision(m, 1, false, later), RetryDecision::Retry);
+        assert_eq!(m.retry_count, 1);
+        assert!(m.exhausted_at.is_none());
+    }
+
+    fn in_progress(cid: &str, started_at: DateTime<Utc>) -> InProgressManifest {
+        let mut files = HashMap::new();
+        files.insert(
+            "zDone".to_string(),
+            FileDownloadState {
+                path: "done.txt".to_string(),
+                status: FileDownloadStatus::Done,
+                attempts: 1,
+                last_error: None,
+            },
+        );
+        InProgressManifest {
+            manifest_cid: cid.to_string(),
+            source_peer_id: "peer".to_string(),
+            sequence_number: 4,
+            started_at,
+            total_files: 2,
+            files_downloaded: 1,
+            files_failed: 0,
+            current_status: "Downloading files".to_string(),
+            files,
+            multiaddr: None,
+            folder_id: None,
+        }
+    }
+
+    #[test]
+    fn test_stale_in_progress_detection() {
+        let now = Utc::now();
+        assert!(!is_stale_in_progress(
+            &in_progress("zRecent", now - chrono::Duration::hours(1)),
+            now
+        ));
+        assert!(is_stale_in_progress(
+            &in_progress("zOld", now - chrono::Duration::hours(25)),
+            now
+        ));
+    }
+
+    #[test]
+    fn test_record_failed_manifest_clears_in_progress() {
+        let mut state = DaemonState::default();
+        state.in_progress_manifests.insert(
+            "zManifest".to_string(),
+            in_progress("zManifest", Utc::now()),
+        );
+
+        record_failed_manifest(
+            &mut state,
+            "zManifest",
+            "peer",
+            None,
+            "interrupted",
+            HashMap::new(),
+        );
+
+        assert!(state.in_progress_manifests.is_empty());
+        assert_eq!(state.failed_manifests.len(), 1);
+        assert_eq!(
+            state.failed_manifests[0].files["zDone"].status,
+            FileDownloadStatus::Done
+        );
+    }
+
+    #[test]
+    fn test_backoff_with_jitter_bounds() {
+        for failures in 1..=10 {
+            let full = (10u64 << (failures - 1)).min(300);
+            let delay = backoff_with_jitter(10, 300, failures).as_secs();
+            assert!(
+                delay >= full - full / 2 && delay <= full,
+                "{failures}: {delay}"
+            );
+        }
+        assert!(backoff_with_jitter(10, 300, 40).as_secs() <= 300);
+    }
+
+    #[test]
+    fn test_source_health_transitions() {
+        let now = Utc::now();
+        let mut health = SourceHealth::new("laptop");
+        assert!(health.is_due(now));
+
+        health.record_failure("connection refused", 30, now);
+        assert_eq!(health.status, SourceHealthStatus::Degraded);
+        assert!(!health.is_due(now));
+
+        for _ in 1..SOURCE_CIRCUIT_THRESHOLD {
+            health.record_failure("connection refused", 30, now);
+        }
+        assert_eq!(health.status, SourceHealthStatus::CircuitOpen);
+        assert!(health.is_due(now + chrono::Duration::seconds(BACKOFF_MAX_SECS as i64)));
+
+        health.record_success("peer", now);
+        assert_eq!(health.status, SourceHealthStatus::Healthy);
+        assert_eq!(health.consecutive_failures, 0);
+        assert!(health.is_due(now));
+    }
+
+    #[test]
+    fn test_admission_block_reason() {
+        // Fits everywhere
+        assert!(admission_block_reason(100, 500, Some(1000), Some(1000), 400).is_none());
+        // Unknown quota and no limit never block
+        assert!(admission_block_reason(u64::MAX, u64::MAX, None, None, 0).is_none());
+        // Not enough free node quota for the missing files
+        assert!(admission_block_reason(2000, 2000, Some(1000), None, 0)
+            .unwrap()
+            .contains("node quota"));
+        // Whole folder counts against the source limit, even if mostly stored
+        assert!(admission_block_reason(10, 700, Some(1000), Some(1000), 400)
+            .unwrap()
+            .contains("limit"));
+    }
+
+    #[test]
+    fn test_source_stored_bytes_uses_latest_snapshot_per_folder() {
+        let mut processed = HashMap::new();
+        for (cid, folder, seq, size, pruned) in [
+            ("zA1", "docs", 1, 100, false),
+            ("zA2", "docs", 2, 150, false),
+            ("zB1", "photos", 1, 1000, false),
+            ("zC1", "old", 1, 5000, true),
+        ] {
+            let mut m = snapshot(cid, folder, seq, pruned);
+            m.total_size_bytes = size;
+            processed.insert(cid.to_string(), m);
+        }
+
+        assert_eq!(source_stored_bytes(&processed, "peer-a", "incoming"), 1150);
+        assert_eq!(source_stored_bytes(&processed, "peer-a", "photos"), 150);
+        assert_eq!(source_stored_bytes(&processed, "other", "incoming"), 0);
+    }
+
+    #[test]
+    fn test_due_deletions() {
+        let now = Utc::now();
+        let pending = |cid: &str, delete_after: DateTime<Utc>| PendingDeletion {
+            cid: cid.to_string(),
+            path: format!("{cid}.txt"),
+            source_peer_id: "peer".to_string(),
+            folder_id: "folder".to_string(),
+            manifest_cid: "zManifest".to_string(),
+            reason: DeletionReason::Tombstone,
+            queued_at: now - chrono::Duration::hours(72),
+            delete_after,
+        };
+        let mut queue = HashMap::new();
+        for p in [
+            pending("zExpired", now - chrono::Duration::minutes(1)),
+            pending("zWaiting", now + chrono::Duration::hours(1)),
+        ] {
+            queue.insert(p.cid.clone(), p);
+        }
+
+        assert_eq!(due_deletions(&queue, now), vec!["zExpired".to_string()]);
+    }
+
+    #[test]
+    fn test_local_cid_index_freshness() {
+        let now = std::time::Instant::now();
+        let mut index = LocalCidIndex::default();
+        assert!(!index.is_fresh(now));
+
+        index.built_at = Some(now);
+        assert!(index.is_fresh(now + Duration::from_secs(10)));
+        assert!(!index.is_fresh(now + LOCAL_INDEX_TTL));
+
+        index.invalidate();
+        assert!(!index.is_fresh(now));
+
+        // A failed listing keeps the index stale instead of empty-and-fresh
+        index
+            .rebuild(Ok(HashSet::from(["zA".to_string()])), now)
+            .unwrap();
+        assert!(index.is_fresh(now));
+        assert!(index
+            .rebuild(Err(ArchivistError::ApiError("down".into())), now)
+            .is_err());
+        assert!(!index.is_fresh(now));
+    }
+
+    #[test]
+    fn test_retained_snapshot_cids() {
+        let mut processed = HashMap::new();
+        for s in [
+            snapshot("f1-s1", "f1", 1, true),
+            snapshot("f1-s2", "f1", 2, false),
+            snapshot("f1-s3", "f1", 3, false),
+            snapshot("f1-s4", "f1", 4, false),
+            snapshot("f2-s1", "f2", 1, false),
+        ] {
+            processed.insert(s.manifest_cid.clone(), s);
+        }
+
+        let mut retained = retained_snapshot_cids(&processed, 2, None);
+        retained.sort();
+        assert_eq!(retained, vec!["f1-s3", "f1-s4", "f2-s1"]);
+
+        // An incoming manifest for f1 takes one of its slots
+        let mut retained = retained_snapshot_cids(&processed, 2, Some(("peer-a", "f1")));
+        retained.sort();
+        assert_eq!(retained, vec!["f1-s4", "f2-s1"]);
+    }
+
+    #[tokio::test]
+    async fn test_protected_cids_uses_cached_snapshot_files() {
+        let dir = tempfile::tempdir().unwrap();
+        // No node listens here, so any manifest fetch would fail
+        let daemon = BackupDaemon::with_state_file(
+            NodeApiClient::new(9),
+            dir.path().join("backup-daemon-state.json"),
+        );
+        daemon.set_snapshot_retention(2);
+        {
+            let mut state = daemon.state.write().await;
+            for (cid, seq, files) in [("s1", 1, ["a", "b"]), ("s2", 2, ["b", "c"])] {
+                let mut s = snapshot(cid, "f1", seq, false);
+                s.file_cids = Some(files.iter().map(|f| f.to_string()).collect());
+                state.processed_manifests.insert(cid.to_string(), s);
+            }
+        }
+
+        let mut protected: Vec<String> = daemon
+            .protected_cids(None)
+            .await
+            .unwrap()
+            .into_iter()
+            .collect();
+        protected.sort();
+        assert_eq!(protected, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_file_outcome_counts_each_cid_once() {
+        let entry = |path: &str, cid: &str, size_bytes: u64| ManifestFileEntry {
+            path: path.to_string(),
+            cid: cid.to_string(),
+            size_bytes,
+            mime_type: None,
+            uploaded_at: Utc::now(),
+        };
+        let manifest = ManifestFile {
+            version: "1.0".to_string(),
+            folder_id: "folder".to_string(),
+            folder_path: "/data/folder".to_string(),
+            source_peer_id: "peer".to_string(),
+            sequence_number: 1,
+            last_updated: Utc::now(),
+            manifest_cid: None,
+            files: vec![
+                entry("a.txt", "zA", 100),
+                entry("copy-of-a.txt", "zA", 100),
+                entry("b.txt", "zB", 50),
+                entry("c.txt", "zC", 25),
+            ],
+            deleted_files: Vec::new(),
+            stats: ManifestStats {
+                total_files: 4,
+                total_size_bytes: 275,
+            },
+        };
+        let state = |status| FileDownloadState {
+            path: String::new(),
+            status,
+            attempts: 1,
+            last_error: None,
+        };
+        let files = HashMap::from([
+            ("zA".to_string(), state(FileDownloadStatus::Done)),
+            ("zB".to_string(), state(FileDownloadStatus::Failed)),
+            ("zC".to_string(), state(FileDownloadStatus::Done)),
+        ]);
+
+        assert_eq!(file_outcome(&manifest, &files), (2, 1, 125));
+    }
+
+    #[test]
+    fn test_path_matches_prefixes() {
+        let prefixes = vec!["2024/".to_string(), "/Albums/Trips".to_string()];
+        assert!(path_matches_prefixes("2024/beach.jpg", &prefixes));
+        assert!(path_matches_prefixes("Albums/Trips/rome.jpg", &prefixes));
+        assert!(path_matches_prefixes("Albums\\Trips\\rome.jpg", &prefixes));
+        assert!(path_matches_prefixes("Albums/Trips", &prefixes));
+        // Prefixes match whole path components only
+        assert!(!path_matches_prefixes("20245/beach.jpg", &prefixes));
+        assert!(!path_matches_prefixes("Albums/Tripsy/x.jpg", &prefixes));
+        assert!(!path_matches_prefixes("2023/beach.jpg", &prefixes));
+        assert!(path_matches_prefixes("anything.txt", &[]));
+    }
+
+    #[test]
+    fn test_filter_manifest_paths() {
+        let entry = |path: &str, cid: &str, size_bytes: u64| ManifestFileEntry {
+            path: path.to_string(),
+            cid: cid.to_string(),
+            size_bytes,
+            mime_type: None,
+            uploaded_at: Utc::now(),
+        };
+        let tombstone = |path: &str, cid: &str| ManifestDeletedEntry {
+            path: path.to_string(),
+            cid: cid.to_string(),
+            deleted_at: Utc::now(),
+        };
+        let mut manifest = ManifestFile {
+            version: "1.0".to_string(),
+            folder_id: "photos".to_string(),
+            folder_path: "/data/photos".to_string(),
+            source_peer_id: "peer".to_string(),
+            sequence_number: 1,
+            last_updated: Utc::now(),
+            manifest_cid: None,
+            files: vec![
+                entry("2024/a.jpg", "zA", 100),
+                entry("2023/b.jpg", "zB", 50),
+                entry("2024/c.jpg", "zC", 25),
+            ],
+            deleted_files: vec![
+                tombstone("2024/old.jpg", "zOld"),
+                tombstone("2023/x.jpg", "zX"),
+            ],
+            stats: ManifestStats {
+                total_files: 3,
+                total_size_bytes: 175,
+            },
+        };
+
+        filter_manifest_paths(&mut manifest, &["2024".to_string()]);
+
+        let cids: Vec<&str> = manifest.files.iter().map(|f| f.cid.as_str()).collect();
+        assert_eq!(cids, vec!["zA", "zC"]);
+        // Tombstones outside the prefixes are not enforced
+        assert_eq!(manifest.deleted_files.len(), 1);
+        assert_eq!(manifest.deleted_files[0].cid, "zOld");
+        assert_eq!(manifest.stats.total_files, 2);
+        assert_eq!(manifest.stats.total_size_bytes, 125);
+    }
+
+    #[test]
+    fn test_source_folders_mark_excluded_folders() {
+        let mut peer = source_peer("10.0.0.2", None);
+        peer.mirror_unlisted_folders = false;
+        peer.folder_rules = vec![FolderMirrorRule {
+            folder_id: "photos".to_string(),
+            enabled: true,
+            path_prefixes: vec!["2024".to_string()],
+        }];
+        let info = |folder_id: &str| ManifestInfo {
+            folder_id: folder_id.to_string(),
+            folder_path: format!("/data/{}", folder_id),
+            manifest_cid: format!("z{}", folder_id),
+            sequence_number: 3,
+            updated_at: Utc::now().to_rfc3339(),
+            file_count: 2,
+            total_size_bytes: 20,
+        };
+
+        let seen = Utc::now() - chrono::Duration::days(3);
+        let previous = vec![SourceFolder {
+            folder_id: "photos".to_string(),
+            folder_path: "/data/photos".to_string(),
+            latest_sequence: 2,
+            file_count: 2,
+            total_size_bytes: 20,
+            mirrored: true,
+            path_prefixes: Vec::new(),
+            first_seen_at: Some(seen),
+        }];
+        let now = Utc::now();
+        let folders = source_folders(&peer, &[info("photos"), info("documents")], &previous, now);
+        assert_eq!(folders.len(), 2);
+        assert_eq!(folders[0].folder_id, "documents");
+        assert!(!folders[0].mirrored);
+        assert!(folders[0].path_prefixes.is_empty());
+        assert_eq!(folders[1].folder_id, "photos");
+        assert!(folders[1].mirrored);
+        assert_eq!(folders[1].path_prefixes, vec!["2024".to_string()]);
+        // First-seen times survive later polls
+        assert_eq!(folders[0].first_seen_at, Some(now));
+        assert_eq!(folders[1].first_seen_at, Some(seen));
+
+        // Without rules, every folder is mirrored
+        assert!(source_peer("10.0.0.2", None).mirrors_folder("documents"));
+    }
+
+    #[test]
+    fn test_processed_manifest_without_snapshot_fields() {
+        let json = r#"{
+            "manifest_cid": "zOld",
+            "source_peer_id": "peer",
+            "sequence_number": 7,
+            "folder_id": "folder",
+            "processed_at": "2025-01-01T00:00:00Z",
+            "file_count": 3,
+            "total_size_bytes": 300,
+            "deleted_count": 0
+        }"#;
+        let m: ProcessedManifest = serde_json::from_str(json).unwrap();
+        assert!(m.pruned_at.is_none());
+        assert!(m.manifest_updated_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_apply_settings_updates_running_daemon() {
+        let dir = tempfile::tempdir().unwrap();
+        let daemon = Arc::new(BackupDaemon::with_state_file(
+            NodeApiClient::new(8080),
+            dir.path().join("backup-daemon-state.json"),
+        ));
+
+        let mut settings = crate::services::config::AppConfig::default().backup_server;
+        settings.enabled = true;
+        settings.poll_interval_secs = 300;
+        settings.max_concurrent_downloads = 8;
+        settings.max_retries = 5;
+        settings.auto_delete_tombstones = false;
+        settings.deletion_grace_period_hours = 0;
+        settings.source_peers = vec![SourcePeerConfig {
+            nickname: "laptop".to_string(),
+            host: "192.168.1.20".to_string(),
+            manifest_port: 8085,
+            peer_id: None,
+            multiaddr: None,
+            enabled: true,
+            max_storage_bytes: None,
+            folder_rules: Vec::new(),
+            mirror_unlisted_folders: true,
+            ack_secret: None,
+        }];
+
+        daemon.apply_settings(&settings).await;
+
+        assert!(daemon.is_enabled());
+        assert_eq!(daemon.poll_interval_secs.load(Ordering::Relaxed), 300);
+        assert_eq!(daemon.max_concurrent_downloads.load(Ordering::Relaxed), 8);
+        assert_eq!(daemon.max_retries.load(Ordering::Relaxed), 5);
+        assert!(!daemon.auto_delete_tombstones.load(Ordering::Relaxed));
+        assert!(daemon.deletion_grace().is_none());
+        assert_eq!(daemon.source_peers.read().await.len(), 1);
+        // The main loop is woken so the new poll interval applies right away
+        assert!(daemon.trigger_rx.write().await.try_recv().is_ok());
+    }
+
+    fn source_peer(host: &str, secret: Option<&str>) -> SourcePeerConfig {
+        SourcePeerConfig {
+            nickname: host.to_string(),
+            host: host.to_string(),
+            manifest_port: 8085,
+            peer_id: None,
+            multiaddr: None,
+            enabled: true,
+            max_storage_bytes: None,
+            folder_rules: Vec::new(),
+            mirror_unlisted_folders: true,
+            ack_secret: secret.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_http_fallback_requires_secret() {
+        let client = ManifestClient::new();
+        assert!(HttpFallback::for_source(&client, &source_peer("10.0.0.2", None)).is_none());
+        assert!(HttpFallback::for_source(&client, &source_peer("10.0.0.2", Some(""))).is_none());
+
+        let fallback =
+            HttpFallback::for_source(&client, &source_peer("10.0.0.2", Some("secret"))).unwrap();
+        assert_eq!(fallback.host, "10.0.0.2");
+        assert_eq!(fallback.port, 8085);
+        assert_eq!(fallback.secret, "secret");
+
+        // Without knowing what is stored locally, nothing is deleted
+        assert!(!fallback.may_delete("zUploaded"));
+        let fallback = fallback.keeping(HashSet::from(["zStored".to_string()]));
+        assert!(!fallback.may_delete("zStored"));
+        assert!(fallback.may_delete("zUploaded"));
+    }
+
+    #[test]
+    fn test_trigger_request_signature() {
+        let mut trigger = TriggerRequest {
+            folder_id: Some("photos".to_string()),
+            manifest_cid: Some("zManifest".to_string()),
+            ..Default::default()
+        };
+        trigger.sign("secret");
+
+        let now = Utc::now();
+        assert!(trigger.verify("secret", now));
+        assert!(!trigger.verify("other", now));
+        // Replayed long after it was signed
+        assert!(!trigger.verify("secret", now + chrono::Duration::minutes(10)));
+
+        trigger.folder_id = Some("documents".to_string());
+        assert!(!trigger.verify("secret", now));
+    }
+
+    #[test]
+    fn test_authorize_trigger() {
+        let now = Utc::now();
+        let open = vec![source_peer("10.0.0.1", None)];
+        let secured = vec![
+            source_peer("10.0.0.1", None),
+            source_peer("10.0.0.2", Some("s2")),
+        ];
+
+        // Unsigned triggers need an explicit opt-in, and no source secrets
+        let unsigned = TriggerRequest::default();
+        assert_eq!(
+            authorize_trigger(&open, &unsigned, false, now),
+            TriggerAuth::Rejected
+        );
+        assert_eq!(
+            authorize_trigger(&open, &unsigned, true, now),
+            TriggerAuth::Open
+        );
+        assert_eq!(
+            authorize_trigger(&secured, &unsigned, true, now),
+            TriggerAuth::Rejected
+        );
+
+        let mut signed = TriggerRequest::default();
+        signed.sign("s2");
+        assert_eq!(
+            authorize_trigger(&secured, &signed, false, now),
+            TriggerAuth::Source("10.0.0.2:8085".to_string())
+        );
+
+        let mut wrong = TriggerRequest::default();
+        wrong.sign("nope");
+        assert_eq!(
+            authorize_trigger(&secured, &wrong, false, now),
+            TriggerAuth::Rejected
+        );
+    }
+
+    #[tokio::test]
+    async fn test_trigger_server_rebinds_and_stops() {
+        use crate::services::request_guard::{RequestAuditLog, ServerKind};
+
+        let dir = tempfile::tempdir().unwrap();
+        let daemon = Arc::new(BackupDaemon::with_state_file(
+            NodeApiClient::new(8080),
+            dir.path().join("backup-daemon-state.json"),
+        ));
+        let mut settings = crate::services::config::AppConfig::default().backup_server;
+        settings.enabled = true;
+        settings.trigger_bind_address = "127.0.0.1".to_string();
+        settings.trigger_port = 0;
+        daemon.apply_settings(&settings).await;
+
+        let guard = RequestGuard::new(
+            ServerKind::TriggerServer,
+            &settings.trigger_rate_limit,
+            Arc::new(RequestAuditLog::in_memory(10)),
+        );
+        daemon.start_trigger_server(guard).await.unwrap();
+        let addr = daemon.trigger_server.read().await.as_ref().unwrap().addr;
+        assert!(addr.ip().is_loopback());
+
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/trigger", addr);
+        // Unsigned triggers are refused unless explicitly allowed
+        let response = client.post(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        settings.allow_unsigned_triggers = true;
+        daemon.apply_settings(&settings).await;
+        let response = client.post(&url).send().await.unwrap();
+        assert!(response.status().is_success());
+
+        // Once a source has a secret, unsigned triggers are refused again
+        settings.source_peers = vec![source_peer("10.0.0.2", Some("s2"))];
+        daemon.apply_settings(&settings).await;
+        let response = client.post(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        // Disabling the backup server shuts the trigger server down too
+        settings.enabled = false;
+        daemon.apply_settings(&settings).await;
+        assert!(daemon.trigger_server.read().await.is_none());
+        assert!(client.post(&url).send().await.is_err());
+
+        settings.enabled = true;
+        daemon.apply_settings(&settings).await;
+        assert!(daemon.trigger_server.read().await.is_some());
+
+        // A changed rate limit rebuilds the guard's limiter
+        settings.trigger_rate_limit.requests_per_minute = 5;
+        daemon.apply_settings(&settings).await;
+        assert_eq!(
+            daemon
+                .trigger_guard
+                .read()
+                .await
+                .as_ref()
+                .unwrap()
+                .rate_limit_settings(),
+            &settings.trigger_rate_limit
+        );
+        assert!(daemon.trigger_server.read().await.is_some());
+
+        // Disabling the trigger server shuts it down
+        settings.trigger_enabled = false;
+        daemon.apply_settings(&settings).await;
+        assert!(daemon.trigger_server.read().await.is_none());
+    }
+
+    #[test]
+    fn test_gap_fill_plan_orders_missing_sequences() {
+        let info = |seq: u64| ManifestInfo {
+            folder_id: "photos".to_string(),
+            folder_path: "/data/photos".to_string(),
+            manifest_cid: format!("zCid{}", seq),
+            sequence_number: seq,
+            updated_at: Utc::now().to_rfc3339(),
+            file_count: 1,
+            total_size_bytes: 10,
+        };
+
+        // History lost seq 4; seq 2 and the newest (7) are outside the gap
+        let history = vec![info(7), info(6), info(2), info(3), info(5)];
+        let (gap, unavailable) = gap_fill_plan(2, 7, &history);
+
+        let seqs: Vec<u64> = gap.iter().map(|m| m.sequence_number).collect();
+        assert_eq!(seqs, vec![3, 5, 6]);
+        assert_eq!(unavailable, vec![4]);
+    }
+
+    #[test]
+    fn test_last_processed_sequence_per_folder() {
+        let mut state = DaemonState::default();
+        for m in [
+            snapshot("zA1", "photos", 1, false),
+            snapshot("zA3", "photos", 3, true),
+            snapshot("zB9", "docs", 9, false),
+        ] {
+            state.processed_manifests.insert(m.manifest_cid.clone(), m);
+        }
+
+        assert_eq!(last_processed_sequence(&state, "peer-a", "photos"), Some(3));
+        assert_eq!(last_processed_sequence(&state, "peer-b", "photos"), None);
+    }
+}
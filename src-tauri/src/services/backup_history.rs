@@ -0,0 +1,547 @@
+//! Backup history reporting
+//!
+//! Builds reports from the backup daemon's state: a per-manifest history
+//! (processed manifests and every failed attempt, including failures that
+//! were later retried successfully) that can be exported as JSON or CSV for a
+//! date range, and a per-folder summary of when each folder was last fully
+//! backed up and how far behind the source it is.
+
+use crate::error::{ArchivistError, Result};
+use crate::services::backup_daemon::{failure_record, DaemonState, ManifestFailureRecord};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Export format for backup history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryStatus {
+    Processed,
+    Failed,
+}
+
+/// One manifest in the backup history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupHistoryEntry {
+    pub source_peer_id: String,
+    /// Unknown for manifests that failed before they could be parsed
+    pub folder_id: Option<String>,
+    pub sequence_number: Option<u64>,
+    pub manifest_cid: String,
+    pub status: HistoryStatus,
+    /// When the manifest was processed, or the attempt failed
+    pub time: DateTime<Utc>,
+    /// When the source last changed the folder
+    pub manifest_updated_at: Option<DateTime<Utc>>,
+    pub file_count: u32,
+    pub total_size_bytes: u64,
+    pub deleted_count: u32,
+    pub files_failed: u32,
+    pub retry_count: u32,
+    pub error: Option<String>,
+}
+
+/// When a folder was last fully backed up, and how far behind it is
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderBackupSummary {
+    pub source_peer_id: String,
+    pub folder_id: String,
+    pub last_full_backup_at: Option<DateTime<Utc>>,
+    pub last_full_sequence: Option<u64>,
+    pub last_full_manifest_cid: Option<String>,
+    /// Newest sequence seen for the folder (advertised by the source,
+    /// processed, in progress, failed or blocked)
+    pub latest_known_sequence: u64,
+    /// Newest sequence the source advertised on its last successful poll
+    pub advertised_sequence: Option<u64>,
+    /// Known sequences newer than the last full backup
+    pub sequences_behind: u64,
+    /// Seconds since the last full backup (or since the folder was first
+    /// seen, if it never completed) while newer sequences are known, 0 when
+    /// up to date
+    pub lag_secs: Option<i64>,
+    /// Earliest time the folder was advertised, processed or attempted
+    pub first_seen_at: Option<DateTime<Utc>>,
+    /// Seconds since the folder's source was last polled successfully, i.e.
+    /// how old the advertised sequence is
+    pub poll_age_secs: Option<i64>,
+    /// Manifests of this folder currently waiting for a retry
+    pub failing_manifests: u32,
+}
+
+/// Processed manifests and failed attempts whose time falls within
+/// `[from, to]`, ordered by source, folder, then time
+pub fn history_entries(
+    state: &DaemonState,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Vec<BackupHistoryEntry> {
+    let in_range = |time: DateTime<Utc>| {
+        from.map_or(true, |from| time >= from) && to.map_or(true, |to| time <= to)
+    };
+
+    let processed = state
+        .processed_manifests
+        .values()
+        .map(|m| BackupHistoryEntry {
+            source_peer_id: m.source_peer_id.clone(),
+            folder_id: Some(m.folder_id.clone()),
+            sequence_number: Some(m.sequence_number),
+            manifest_cid: m.manifest_cid.clone(),
+            status: HistoryStatus::Processed,
+            time: m.processed_at,
+            manifest_updated_at: m.manifest_updated_at,
+            file_count: m.file_count,
+            total_size_bytes: m.total_size_bytes,
+            deleted_count: m.deleted_count,
+            files_failed: 0,
+            retry_count: 0,
+            error: None,
+        });
+
+    let failed_entry = |r: &ManifestFailureRecord| BackupHistoryEntry {
+        source_peer_id: r.source_peer_id.clone(),
+        folder_id: r.folder_id.clone(),
+        sequence_number: r.sequence_number,
+        manifest_cid: r.manifest_cid.clone(),
+        status: HistoryStatus::Failed,
+        time: r.failed_at,
+        manifest_updated_at: None,
+        file_count: r.files_done,
+        total_size_bytes: 0,
+        deleted_count: 0,
+        files_failed: r.files_failed,
+        retry_count: r.retry_count,
+        error: Some(r.error_message.clone()),
+    };
+    let recorded: HashSet<(&str, DateTime<Utc>)> = state
+        .failure_history
+        .iter()
+        .map(|r| (r.manifest_cid.as_str(), r.failed_at))
+        .collect();
+    // Failures from before the history was kept only exist as failed entries
+    let unrecorded: Vec<ManifestFailureRecord> = state
+        .failed_manifests
+        .iter()
+        .filter(|m| !recorded.contains(&(m.manifest_cid.as_str(), m.failed_at)))
+        .map(failure_record)
+        .collect();
+    let failed = state
+        .failure_history
+        .iter()
+        .chain(&unrecorded)
+        .map(failed_entry);
+
+    let mut entries: Vec<BackupHistoryEntry> = processed
+        .chain(failed)
+        .filter(|e| in_range(e.time))
+        .collect();
+    entries.sort_by(|a, b| {
+        (&a.source_peer_id, &a.folder_id, a.time).cmp(&(&b.source_peer_id, &b.folder_id, b.time))
+    });
+    entries
+}
+
+/// Serialize history entries in the requested format
+pub fn export_history(entries: &[BackupHistoryEntry], format: HistoryFormat) -> Result<String> {
+    match format {
+        HistoryFormat::Json => {
+            serde_json::to_string_pretty(entries).map_err(ArchivistError::SerializationError)
+        }
+        HistoryFormat::Csv => Ok(history_csv(entries)),
+    }
+}
+
+fn history_csv(entries: &[BackupHistoryEntry]) -> String {
+    let mut csv = String::from(
+        "source_peer_id,folder_id,sequence_number,manifest_cid,status,time,manifest_updated_at,\
+         file_count,total_size_bytes,deleted_count,files_failed,retry_count,error\n",
+    );
+    for e in entries {
+        let row = [
+            e.source_peer_id.clone(),
+            e.folder_id.clone().unwrap_or_default(),
+            e.sequence_number.map(|s| s.to_string()).unwrap_or_default(),
+            e.manifest_cid.clone(),
+            match e.status {
+                HistoryStatus::Processed => "processed".to_string(),
+                HistoryStatus::Failed => "failed".to_string(),
+            },
+            e.time.to_rfc3339(),
+            e.manifest_updated_at
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+            e.file_count.to_string(),
+            e.total_size_bytes.to_string(),
+            e.deleted_count.to_string(),
+            e.files_failed.to_string(),
+            e.retry_count.to_string(),
+            e.error.clone().unwrap_or_default(),
+        ];
+        let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quote a CSV field when it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl FolderBackupSummary {
+    fn saw_at(&mut self, at: DateTime<Utc>) {
+        self.first_seen_at = Some(self.first_seen_at.map_or(at, |seen| seen.min(at)));
+    }
+}
+
+fn summary_entry<'a>(
+    summaries: &'a mut HashMap<(String, String), FolderBackupSummary>,
+    source: &str,
+    folder: &str,
+) -> &'a mut FolderBackupSummary {
+    summaries
+        .entry((source.to_string(), folder.to_string()))
+        .or_insert_with(|| FolderBackupSummary {
+            source_peer_id: source.to_string(),
+            folder_id: folder.to_string(),
+            last_full_backup_at: None,
+            last_full_sequence: None,
+            last_full_manifest_cid: None,
+            latest_known_sequence: 0,
+            advertised_sequence: None,
+            sequences_behind: 0,
+            lag_secs: None,
+            first_seen_at: None,
+            poll_age_secs: None,
+            failing_manifests: 0,
+        })
+}
+
+/// Last full backup and lag of every folder the daemon knows about, ordered
+/// by source then folder
+pub fn folder_summaries(state: &DaemonState, now: DateTime<Utc>) -> Vec<FolderBackupSummary> {
+    let mut summaries: HashMap<(String, String), FolderBackupSummary> = HashMap::new();
+
+    // Processed manifests only exist once every file was stored
+    for m in state.processed_manifests.values() {
+        let summary = summary_entry(&mut summaries, &m.source_peer_id, &m.folder_id);
+        summary.saw_at(m.processed_at);
+        summary.latest_known_sequence = summary.latest_known_sequence.max(m.sequence_number);
+        if summary
+            .last_full_sequence
+            .map_or(true, |s| m.sequence_number > s)
+        {
+            summary.last_full_sequence = Some(m.sequence_number);
+            summary.last_full_backup_at = Some(m.processed_at);
+            summary.last_full_manifest_cid = Some(m.manifest_cid.clone());
+        }
+    }
+    for m in state.in_progress_manifests.values() {
+        if let Some(folder_id) = &m.folder_id {
+            let summary = summary_entry(&mut summaries, &m.source_peer_id, folder_id);
+            summary.saw_at(m.started_at);
+            summary.latest_known_sequence = summary.latest_known_sequence.max(m.sequence_number);
+        }
+    }
+    for m in &state.failed_manifests {
+        if let Some(folder_id) = &m.folder_id {
+            let summary = summary_entry(&mut summaries, &m.source_peer_id, folder_id);
+            summary.saw_at(m.failed_at);
+            summary.failing_manifests += 1;
+            if let Some(seq) = m.sequence_number {
+                summary.latest_known_sequence = summary.latest_known_sequence.max(seq);
+            }
+        }
+    }
+    for m in state.blocked_manifests.values() {
+        let summary = summary_entry(&mut summaries, &m.source_peer_id, &m.folder_id);
+        summary.saw_at(m.blocked_at);
+        summary.latest_known_sequence = summary.latest_known_sequence.max(m.sequence_number);
+    }
+    // Folders excluded from mirroring are never behind
+    for (source, folders) in &state.source_folders {
+        let Some(peer_id) = state
+            .source_health
+            .get(source)
+            .and_then(|h| h.peer_id.as_deref())
+        else {
+            continue;
+        };
+        for folder in folders.iter().filter(|f| f.mirrored) {
+            let summary = summary_entry(&mut summaries, peer_id, &folder.folder_id);
+            if let Some(at) = folder.first_seen_at {
+                summary.saw_at(at);
+            }
+            summary.advertised_sequence = Some(folder.latest_sequence);
+            summary.latest_known_sequence =
+                summary.latest_known_sequence.max(folder.latest_sequence);
+        }
+    }
+
+    // A source may be reachable under several addresses; the newest poll counts
+    let mut last_polls: HashMap<&str, DateTime<Utc>> = HashMap::new();
+    for health in state.source_health.values() {
+        if let (Some(peer_id), Some(at)) = (health.peer_id.as_deref(), health.last_success_at) {
+            let last = last_polls.entry(peer_id).or_insert(at);
+            *last = (*last).max(at);
+        }
+    }
+
+    let mut summaries: Vec<FolderBackupSummary> = summaries
+        .into_values()
+        .map(|mut s| {
+            s.sequences_behind = s
+                .latest_known_sequence
+                .saturating_sub(s.last_full_sequence.unwrap_or(0));
+            s.lag_secs = s.last_full_backup_at.or(s.first_seen_at).map(|at| {
+                if s.sequences_behind == 0 {
+                    0
+                } else {
+                    (now - at).num_seconds().max(0)
+                }
+            });
+            s.poll_age_secs = last_polls
+                .get(s.source_peer_id.as_str())
+                .map(|at| (now - *at).num_seconds().max(0));
+            s
+        })
+        .collect();
+    summaries
+        .sort_by(|a, b| (&a.source_peer_id, &a.folder_id).cmp(&(&b.source_peer_id, &b.folder_id)));
+    summaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::backup_daemon::{
+        FailedManifest, FileDownloadState, FileDownloadStatus, ProcessedManifest, SourceFolder,
+        SourceHealth,
+    };
+
+    fn processed(cid: &str, folder: &str, seq: u64, at: DateTime<Utc>) -> ProcessedManifest {
+        ProcessedManifest {
+            manifest_cid: cid.to_string(),
+            source_peer_id: "peer-a".to_string(),
+            sequence_number: seq,
+            folder_id: folder.to_string(),
+            processed_at: at,
+            file_count: 3,
+            total_size_bytes: 300,
+            deleted_count: 1,
+            manifest_updated_at: None,
+            pruned_at: None,
+            ack_sent_at: None,
+            path_prefixes: Vec::new(),
+        }
+    }
+
+    fn failed(cid: &str, folder: &str, seq: u64, at: DateTime<Utc>) -> FailedManifest {
+        let mut files = HashMap::new();
+        files.insert(
+            "zBad".to_string(),
+            FileDownloadState {
+                path: "bad.txt".to_string(),
+                status: FileDownloadStatus::Failed,
+                attempts: 3,
+                last_error: Some("timeout".to_string()),
+            },
+        );
+        FailedManifest {
+            manifest_cid: cid.to_string(),
+            source_peer_id: "peer-a".to_string(),
+            failed_at: at,
+            error_message: "1 of 2 files failed, \"timeout\"".to_string(),
+            retry_count: 2,
+            multiaddr: None,
+            files,
+            next_retry_at: None,
+            folder_id: Some(folder.to_string()),
+            sequence_number: Some(seq),
+            exhausted_at: None,
+        }
+    }
+
+    fn state(now: DateTime<Utc>) -> DaemonState {
+        let mut state = DaemonState::default();
+        for m in [
+            processed("zPhotos1", "photos", 1, now - chrono::Duration::days(10)),
+            processed("zPhotos2", "photos", 2, now - chrono::Duration::days(2)),
+            processed("zDocs1", "docs", 1, now - chrono::Duration::hours(1)),
+        ] {
+            state.processed_manifests.insert(m.manifest_cid.clone(), m);
+        }
+        state.failed_manifests.push(failed(
+            "zPhotos3",
+            "photos",
+            3,
+            now - chrono::Duration::hours(5),
+        ));
+        state
+    }
+
+    #[test]
+    fn test_history_entries_filters_by_date_range() {
+        let now = Utc::now();
+        let state = state(now);
+
+        let all = history_entries(&state, None, None);
+        assert_eq!(all.len(), 4);
+
+        let recent = history_entries(&state, Some(now - chrono::Duration::days(3)), None);
+        let cids: Vec<&str> = recent.iter().map(|e| e.manifest_cid.as_str()).collect();
+        assert_eq!(cids, vec!["zDocs1", "zPhotos2", "zPhotos3"]);
+        assert_eq!(recent[2].status, HistoryStatus::Failed);
+        assert_eq!(recent[2].files_failed, 1);
+    }
+
+    #[test]
+    fn test_history_entries_keep_resolved_failures() {
+        let now = Utc::now();
+        let mut state = state(now);
+        // zPhotos3 failed twice, then completed
+        let first = failed("zPhotos3", "photos", 3, now - chrono::Duration::hours(5));
+        let mut second = failed("zPhotos3", "photos", 3, now - chrono::Duration::hours(4));
+        second.retry_count = 3;
+        state.failure_history = vec![failure_record(&first), failure_record(&second)];
+        state.failed_manifests.clear();
+        let done = processed("zPhotos3", "photos", 3, now - chrono::Duration::hours(3));
+        state
+            .processed_manifests
+            .insert(done.manifest_cid.clone(), done);
+
+        let recent = history_entries(&state, Some(now - chrono::Duration::hours(6)), None);
+        let statuses: Vec<(HistoryStatus, u32)> =
+            recent.iter().map(|e| (e.status, e.retry_count)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (HistoryStatus::Processed, 0),
+                (HistoryStatus::Failed, 2),
+                (HistoryStatus::Failed, 3),
+                (HistoryStatus::Processed, 0),
+            ]
+        );
+        assert_eq!(recent[1].files_failed, 1);
+    }
+
+    #[test]
+    fn test_export_history_csv_quotes_fields() {
+        let now = Utc::now();
+        let entries = history_entries(&state(now), Some(now - chrono::Duration::days(1)), None);
+        let csv = export_history(&entries, HistoryFormat::Csv).unwrap();
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].starts_with("source_peer_id,folder_id,sequence_number"));
+        assert_eq!(lines.len(), 3);
+        assert!(lines[2].ends_with(",\"1 of 2 files failed, \"\"timeout\"\"\""));
+    }
+
+    #[test]
+    fn test_folder_summaries_report_lag() {
+        let now = Utc::now();
+        let summaries = folder_summaries(&state(now), now);
+
+        let docs = &summaries[0];
+        assert_eq!(docs.folder_id, "docs");
+        assert_eq!(docs.lag_secs, Some(0));
+
+        let photos = &summaries[1];
+        assert_eq!(photos.last_full_sequence, Some(2));
+        assert_eq!(photos.latest_known_sequence, 3);
+        assert_eq!(photos.sequences_behind, 1);
+        assert_eq!(photos.failing_manifests, 1);
+        assert_eq!(photos.lag_secs, Some(2 * 24 * 3600));
+    }
+
+    #[test]
+    fn test_folder_summaries_use_advertised_sequence() {
+        let now = Utc::now();
+        let mut state = state(now);
+        let mut health = SourceHealth::new("laptop");
+        health.peer_id = Some("peer-a".to_string());
+        state
+            .source_health
+            .insert("10.0.0.2:8085".to_string(), health);
+        let folder = |folder_id: &str, latest_sequence: u64, mirrored: bool| SourceFolder {
+            folder_id: folder_id.to_string(),
+            folder_path: format!("/data/{}", folder_id),
+            latest_sequence,
+            file_count: 1,
+            total_size_bytes: 10,
+            mirrored,
+            path_prefixes: Vec::new(),
+            first_seen_at: Some(now - chrono::Duration::days(2)),
+        };
+        state.source_folders.insert(
+            "10.0.0.2:8085".to_string(),
+            vec![
+                folder("docs", 4, true),
+                folder("photos", 3, true),
+                folder("music", 9, false),
+            ],
+        );
+
+        let summaries = folder_summaries(&state, now);
+        assert_eq!(summaries.len(), 2);
+
+        let docs = &summaries[0];
+        assert_eq!(docs.advertised_sequence, Some(4));
+        assert_eq!(docs.sequences_behind, 3);
+        assert_eq!(docs.lag_secs, Some(3600));
+
+        let photos = &summaries[1];
+        assert_eq!(photos.advertised_sequence, Some(3));
+        assert_eq!(photos.sequences_behind, 1);
+        assert_eq!(photos.first_seen_at, Some(now - chrono::Duration::days(10)));
+        // The source was never polled successfully
+        assert_eq!(photos.poll_age_secs, None);
+    }
+
+    #[test]
+    fn test_folder_summaries_never_completed_and_poll_age() {
+        let now = Utc::now();
+        let mut state = DaemonState::default();
+        let mut health = SourceHealth::new("laptop");
+        health.peer_id = Some("peer-a".to_string());
+        health.last_success_at = Some(now - chrono::Duration::hours(30));
+        state
+            .source_health
+            .insert("10.0.0.2:8085".to_string(), health);
+        state.failed_manifests.push(failed(
+            "zMusic1",
+            "music",
+            1,
+            now - chrono::Duration::hours(2),
+        ));
+        state.failed_manifests.push(failed(
+            "zMusic2",
+            "music",
+            2,
+            now - chrono::Duration::hours(40),
+        ));
+
+        let summaries = folder_summaries(&state, now);
+        let music = &summaries[0];
+        assert_eq!(music.last_full_sequence, None);
+        assert_eq!(music.sequences_behind, 2);
+        assert_eq!(music.first_seen_at, Some(now - chrono::Duration::hours(40)));
+        assert_eq!(music.lag_secs, Some(40 * 3600));
+        assert_eq!(music.poll_age_secs, Some(30 * 3600));
+    }
+}
@@ -0,0 +1,413 @@
+//! Backup lag monitoring
+//!
+//! Periodically checks how far each mirrored folder is behind the sequence its
+//! source advertises, when it was last fully backed up, and when the source
+//! was last reached. Folders crossing the configured thresholds raise a
+//! desktop notification (once, until they catch up again) and the tray icon's
+//! tooltip summarizes the overall status.
+
+use crate::services::backup_daemon::{BackupDaemon, DaemonState};
+use crate::services::backup_history::{folder_summaries, FolderBackupSummary};
+use crate::services::config::{ConfigService, LagAlertSettings};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tauri::AppHandle;
+use tokio::sync::RwLock;
+use tokio::time::Duration;
+
+/// Time between lag checks
+const LAG_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Tray tooltip while the backup server is disabled
+const DEFAULT_TRAY_TOOLTIP: &str = "Archivist - Decentralized Storage";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LagStatus {
+    /// Every known sequence is backed up
+    UpToDate,
+    /// Newer sequences are known but within the thresholds
+    Behind,
+    /// A threshold is crossed
+    Alert,
+}
+
+/// Lag of one mirrored folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderLag {
+    #[serde(flatten)]
+    pub summary: FolderBackupSummary,
+    /// Nickname of the configured source the folder comes from
+    pub source_nickname: Option<String>,
+    pub status: LagStatus,
+    /// Which threshold is crossed, for folders in alert
+    pub alert_reason: Option<String>,
+}
+
+/// A desktop notification to raise
+#[derive(Debug, Clone, PartialEq)]
+pub struct LagNotification {
+    pub title: String,
+    pub body: String,
+}
+
+/// Status of a folder against the thresholds, with the reason for an alert.
+/// A folder only counts as up to date while its source keeps being polled:
+/// a stale advertised sequence says nothing about newer changes.
+pub fn evaluate_lag(
+    summary: &FolderBackupSummary,
+    settings: &LagAlertSettings,
+) -> (LagStatus, Option<String>) {
+    let max_secs = settings.max_hours_behind as i64 * 3600;
+    if let Some(poll_age_secs) = summary.poll_age_secs.filter(|_| max_secs > 0) {
+        if poll_age_secs > max_secs {
+            return (
+                LagStatus::Alert,
+                Some(format!(
+                    "source not reached for {} hours",
+                    poll_age_secs / 3600
+                )),
+            );
+        }
+    }
+    if summary.sequences_behind == 0 {
+        return (LagStatus::UpToDate, None);
+    }
+    if settings.max_sequences_behind > 0 && summary.sequences_behind > settings.max_sequences_behind
+    {
+        return (
+            LagStatus::Alert,
+            Some(format!("{} sequences behind", summary.sequences_behind)),
+        );
+    }
+    if let Some(lag_secs) = summary.lag_secs.filter(|_| max_secs > 0) {
+        if lag_secs > max_secs {
+            let reason = if summary.last_full_sequence.is_some() {
+                format!("no full backup for {} hours", lag_secs / 3600)
+            } else {
+                format!("never fully backed up in {} hours", lag_secs / 3600)
+            };
+            return (LagStatus::Alert, Some(reason));
+        }
+    }
+    (LagStatus::Behind, None)
+}
+
+/// Lag of every folder the daemon knows about, ordered by source then folder
+pub fn folder_lags(
+    state: &DaemonState,
+    settings: &LagAlertSettings,
+    now: DateTime<Utc>,
+) -> Vec<FolderLag> {
+    let nicknames: HashMap<&str, &str> = state
+        .source_health
+        .values()
+        .filter_map(|h| Some((h.peer_id.as_deref()?, h.nickname.as_str())))
+        .collect();
+
+    folder_summaries(state, now)
+        .into_iter()
+        .map(|summary| {
+            let (status, alert_reason) = evaluate_lag(&summary, settings);
+            FolderLag {
+                source_nickname: nicknames
+                    .get(summary.source_peer_id.as_str())
+                    .map(|n| n.to_string()),
+                status,
+                alert_reason,
+                summary,
+            }
+        })
+        .collect()
+}
+
+/// One-line status for the tray icon's tooltip
+pub fn tray_summary(lags: &[FolderLag]) -> String {
+    let total = lags.len();
+    let alerts = lags.iter().filter(|l| l.status == LagStatus::Alert).count();
+    let behind = lags
+        .iter()
+        .filter(|l| l.status == LagStatus::Behind)
+        .count();
+
+    let status = if total == 0 {
+        "no folders backed up yet".to_string()
+    } else if alerts > 0 {
+        format!("{} of {} folders falling behind", alerts, total)
+    } else if behind > 0 {
+        format!("{} of {} folders catching up", behind, total)
+    } else {
+        format!("all {} folders up to date", total)
+    };
+    format!("Archivist - Backups: {}", status)
+}
+
+/// Remembers which folders were alerted on, so each folder raises one
+/// notification when it falls behind and one when it has caught up
+#[derive(Debug, Default)]
+pub struct LagAlertTracker {
+    alerted: HashSet<(String, String)>,
+}
+
+impl LagAlertTracker {
+    /// Notifications for folders whose alert state changed since the last check
+    pub fn update(&mut self, lags: &[FolderLag]) -> Vec<LagNotification> {
+        let mut notifications = Vec::new();
+        let mut still_alerted = HashSet::new();
+
+        for lag in lags {
+            let key = (
+                lag.summary.source_peer_id.clone(),
+                lag.summary.folder_id.clone(),
+            );
+            let source = lag
+                .source_nickname
+                .as_deref()
+                .unwrap_or(&lag.summary.source_peer_id);
+            match lag.status {
+                LagStatus::Alert => {
+                    if !self.alerted.contains(&key) {
+                        notifications.push(LagNotification {
+                            title: "Backup falling behind".to_string(),
+                            body: format!(
+                                "{} from {}: {}",
+                                lag.summary.folder_id,
+                                source,
+                                lag.alert_reason.as_deref().unwrap_or("behind")
+                            ),
+                        });
+                    }
+                    still_alerted.insert(key);
+                }
+                LagStatus::Behind => {
+                    // Recovering but not caught up yet: stay quiet either way
+                    if self.alerted.contains(&key) {
+                        still_alerted.insert(key);
+                    }
+                }
+                LagStatus::UpToDate => {
+                    if self.alerted.contains(&key) {
+                        notifications.push(LagNotification {
+                            title: "Backup caught up".to_string(),
+                            body: format!(
+                                "{} from {} is up to date again",
+                                lag.summary.folder_id, source
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+
+        self.alerted = still_alerted;
+        notifications
+    }
+}
+
+/// Background task that checks backup lag, raises notifications and keeps
+/// the tray tooltip up to date
+pub struct BackupLagMonitor {
+    daemon: Arc<BackupDaemon>,
+    config: Arc<RwLock<ConfigService>>,
+    app_handle: AppHandle,
+}
+
+impl BackupLagMonitor {
+    pub fn new(
+        daemon: Arc<BackupDaemon>,
+        config: Arc<RwLock<ConfigService>>,
+        app_handle: AppHandle,
+    ) -> Self {
+        Self {
+            daemon,
+            config,
+            app_handle,
+        }
+    }
+
+    /// Start the monitoring loop
+    pub async fn start_monitoring(self) {
+        let mut tracker = LagAlertTracker::default();
+        let mut tooltip = String::new();
+
+        loop {
+            tokio::time::sleep(LAG_CHECK_INTERVAL).await;
+
+            let backup_settings = self.config.read().await.get().backup_server;
+            let summary = if backup_settings.enabled {
+                let state = self.daemon.get_state().await;
+                let lags = folder_lags(&state, &backup_settings.lag_alerts, Utc::now());
+
+                if backup_settings.lag_alerts.enabled {
+                    for notification in tracker.update(&lags) {
+                        self.notify(&notification);
+                    }
+                } else {
+                    // Start fresh when alerts are turned back on, so folders
+                    // already lagging then are reported
+                    tracker = LagAlertTracker::default();
+                }
+                tray_summary(&lags)
+            } else {
+                tracker = LagAlertTracker::default();
+                DEFAULT_TRAY_TOOLTIP.to_string()
+            };
+
+            if summary != tooltip {
+                self.set_tray_tooltip(&summary);
+                tooltip = summary;
+            }
+        }
+    }
+
+    fn notify(&self, notification: &LagNotification) {
+        use tauri_plugin_notification::NotificationExt;
+
+        log::warn!("{}: {}", notification.title, notification.body);
+        if let Err(e) = self
+            .app_handle
+            .notification()
+            .builder()
+            .title(&notification.title)
+            .body(&notification.body)
+            .show()
+        {
+            log::warn!("Failed to show backup lag notification: {}", e);
+        }
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios")))]
+    fn set_tray_tooltip(&self, tooltip: &str) {
+        if let Some(tray) = self.app_handle.tray_by_id(crate::TRAY_ID) {
+            if let Err(e) = tray.set_tooltip(Some(tooltip)) {
+                log::warn!("Failed to update tray tooltip: {}", e);
+            }
+        }
+    }
+
+    #[cfg(any(target_os = "android", target_os = "ios"))]
+    fn set_tray_tooltip(&self, _tooltip: &str) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(folder: &str, behind: u64, lag_secs: Option<i64>) -> FolderBackupSummary {
+        FolderBackupSummary {
+            source_peer_id: "peer-a".to_string(),
+            folder_id: folder.to_string(),
+            last_full_backup_at: None,
+            last_full_sequence: Some(1),
+            last_full_manifest_cid: None,
+            latest_known_sequence: 1 + behind,
+            advertised_sequence: Some(1 + behind),
+            sequences_behind: behind,
+            lag_secs,
+            first_seen_at: None,
+            poll_age_secs: Some(60),
+            failing_manifests: 0,
+        }
+    }
+
+    fn lag(folder: &str, behind: u64, lag_secs: Option<i64>) -> FolderLag {
+        let summary = summary(folder, behind, lag_secs);
+        let (status, alert_reason) = evaluate_lag(&summary, &LagAlertSettings::default());
+        FolderLag {
+            summary,
+            source_nickname: Some("laptop".to_string()),
+            status,
+            alert_reason,
+        }
+    }
+
+    #[test]
+    fn test_evaluate_lag_thresholds() {
+        let settings = LagAlertSettings::default();
+        assert_eq!(
+            evaluate_lag(&summary("a", 0, Some(0)), &settings).0,
+            LagStatus::UpToDate
+        );
+        assert_eq!(
+            evaluate_lag(&summary("a", 2, Some(3600)), &settings).0,
+            LagStatus::Behind
+        );
+        let (status, reason) = evaluate_lag(&summary("a", 4, Some(3600)), &settings);
+        assert_eq!(status, LagStatus::Alert);
+        assert_eq!(reason.as_deref(), Some("4 sequences behind"));
+        let (status, reason) = evaluate_lag(&summary("a", 1, Some(30 * 3600)), &settings);
+        assert_eq!(status, LagStatus::Alert);
+        assert_eq!(reason.as_deref(), Some("no full backup for 30 hours"));
+
+        // A source that stopped answering alerts even without known new sequences
+        let mut stale = summary("a", 0, Some(0));
+        stale.poll_age_secs = Some(26 * 3600);
+        let (status, reason) = evaluate_lag(&stale, &settings);
+        assert_eq!(status, LagStatus::Alert);
+        assert_eq!(reason.as_deref(), Some("source not reached for 26 hours"));
+
+        // Folders that never completed use the time they were first seen
+        let mut never = summary("a", 1, Some(30 * 3600));
+        never.last_full_sequence = None;
+        let (status, reason) = evaluate_lag(&never, &settings);
+        assert_eq!(status, LagStatus::Alert);
+        assert_eq!(reason.as_deref(), Some("never fully backed up in 30 hours"));
+
+        // Zero disables a threshold
+        let disabled = LagAlertSettings {
+            enabled: true,
+            max_sequences_behind: 0,
+            max_hours_behind: 0,
+        };
+        assert_eq!(
+            evaluate_lag(&summary("a", 50, Some(100 * 3600)), &disabled).0,
+            LagStatus::Behind
+        );
+        assert_eq!(evaluate_lag(&stale, &disabled).0, LagStatus::UpToDate);
+    }
+
+    #[test]
+    fn test_alert_tracker_notifies_on_transitions() {
+        let mut tracker = LagAlertTracker::default();
+
+        let first = tracker.update(&[lag("photos", 5, Some(3600)), lag("docs", 0, Some(0))]);
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].title, "Backup falling behind");
+        assert_eq!(first[0].body, "photos from laptop: 5 sequences behind");
+
+        // Still in alert: no repeat
+        assert!(tracker.update(&[lag("photos", 6, Some(3600))]).is_empty());
+        // Recovering: quiet until caught up
+        assert!(tracker.update(&[lag("photos", 1, Some(60))]).is_empty());
+
+        let caught_up = tracker.update(&[lag("photos", 0, Some(0))]);
+        assert_eq!(caught_up.len(), 1);
+        assert_eq!(caught_up[0].title, "Backup caught up");
+
+        // Falling behind again raises a new alert
+        assert_eq!(tracker.update(&[lag("photos", 4, None)]).len(), 1);
+    }
+
+    #[test]
+    fn test_tray_summary() {
+        assert_eq!(
+            tray_summary(&[]),
+            "Archivist - Backups: no folders backed up yet"
+        );
+        assert_eq!(
+            tray_summary(&[lag("a", 0, Some(0)), lag("b", 0, Some(0))]),
+            "Archivist - Backups: all 2 folders up to date"
+        );
+        assert_eq!(
+            tray_summary(&[lag("a", 1, Some(60)), lag("b", 0, Some(0))]),
+            "Archivist - Backups: 1 of 2 folders catching up"
+        );
+        assert_eq!(
+            tray_summary(&[lag("a", 1, Some(60)), lag("b", 9, Some(60))]),
+            "Archivist - Backups: 1 of 2 folders falling behind"
+        );
+    }
+}
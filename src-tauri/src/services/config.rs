@@ -1,5 +1,8 @@
 use crate::error::{ArchivistError, Result};
+use crate::services::node_launch::NodeLaunchOptions;
+use crate::services::node_logs::NodeLogLevel;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
@@ -33,6 +36,10 @@ pub struct AppConfig {
     #[serde(default)]
     pub media_streaming: MediaStreamingSettings,
 
+    // LAN device discovery (mDNS) settings
+    #[serde(default)]
+    pub discovery: DiscoverySettings,
+
     // V2 Marketplace settings (optional)
     #[cfg(feature = "marketplace")]
     pub blockchain: Option<BlockchainSettings>,
@@ -58,6 +65,36 @@ pub struct NodeSettings {
     pub max_storage_gb: u32,
     pub auto_start: bool,
     pub log_level: String, // Log level: TRACE, DEBUG, INFO, NOTICE, WARN, ERROR, FATAL
+    /// Per-topic overrides of `log_level`, e.g. `libp2p = "trace"`
+    #[serde(default)]
+    pub log_topics: BTreeMap<String, NodeLogLevel>,
+    /// Seconds the node gets to exit after SIGTERM before it is killed
+    /// (Unix; Windows has no signal the sidecar can receive and stops it
+    /// right away)
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+    /// Size at which node.log is rotated (0 disables rotation)
+    #[serde(default = "default_log_max_size_mb")]
+    pub log_max_size_mb: u64,
+    /// Rotated node.log files kept besides the current one
+    #[serde(default = "default_log_max_files")]
+    pub log_max_files: u32,
+    /// NAT strategy, listen IP, bootstrap nodes, announce addresses and
+    /// extra flags passed to archivist-node
+    #[serde(default)]
+    pub launch: NodeLaunchOptions,
+}
+
+pub(crate) fn default_shutdown_grace_secs() -> u64 {
+    10
+}
+
+pub(crate) fn default_log_max_size_mb() -> u64 {
+    10
+}
+
+pub(crate) fn default_log_max_files() -> u32 {
+    5
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,6 +113,10 @@ pub struct SyncSettings {
     /// Port for the backup server's HTTP trigger endpoint (default: 8086)
     #[serde(default = "default_trigger_port")]
     pub backup_trigger_port: u16,
+    /// Machines folders are replicated to; when empty, `backup_peer_address`
+    /// (if set) is used as the only target
+    #[serde(default)]
+    pub backup_targets: Vec<BackupTarget>,
 
     // NEW: Continuous sync settings
     pub manifest_update_threshold: u32,
@@ -83,6 +124,53 @@ pub struct SyncSettings {
     pub manifest_max_retries: u32,
 }
 
+/// Id of the target synthesized from the single-peer settings
+pub const LEGACY_BACKUP_TARGET_ID: &str = "default";
+
+impl SyncSettings {
+    /// Configured backup targets. Configs from before multiple targets were
+    /// supported only set `backup_peer_address`, which becomes one target.
+    pub fn backup_targets(&self) -> Vec<BackupTarget> {
+        if !self.backup_targets.is_empty() {
+            return self.backup_targets.clone();
+        }
+        self.backup_peer_address
+            .iter()
+            .map(|address| BackupTarget {
+                id: LEGACY_BACKUP_TARGET_ID.to_string(),
+                nickname: self
+                    .backup_peer_nickname
+                    .clone()
+                    .unwrap_or_else(|| "Backup peer".to_string()),
+                peer_address: address.clone(),
+                trigger_port: self.backup_trigger_port,
+                enabled: true,
+            })
+            .collect()
+    }
+}
+
+/// A machine this device's folders are backed up to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupTarget {
+    /// Stable identifier, used to key per-target status
+    pub id: String,
+    /// Human-friendly name for this target
+    pub nickname: String,
+    /// Multiaddr of the backup peer (/ip4/<ip>/tcp/<port>/p2p/<peer-id>)
+    pub peer_address: String,
+    /// Port of the target's HTTP trigger endpoint (default: 8086)
+    #[serde(default = "default_trigger_port")]
+    pub trigger_port: u16,
+    /// Whether this target is notified
+    #[serde(default = "default_target_enabled")]
+    pub enabled: bool,
+}
+
+fn default_target_enabled() -> bool {
+    true
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotificationSettings {
     pub sound_enabled: bool,
@@ -108,17 +196,89 @@ pub struct BackupServerSettings {
     /// Port for receiving trigger notifications from source peers (default: 8086)
     #[serde(default = "default_trigger_port")]
     pub trigger_port: u16,
+    /// Whether the trigger server runs; without it the daemon only polls
+    #[serde(default = "default_trigger_enabled")]
+    pub trigger_enabled: bool,
+    /// Address the trigger server binds to (default: 0.0.0.0, all interfaces;
+    /// triggers still have to be signed unless `allow_unsigned_triggers`)
+    #[serde(default = "default_trigger_bind_address")]
+    pub trigger_bind_address: String,
+    /// Accept unsigned triggers while no source peer has an `ack_secret`.
+    /// Off by default, so only sources sharing a secret can force polls.
+    #[serde(default)]
+    pub allow_unsigned_triggers: bool,
     /// Source peers to poll for manifests (list of host:port pairs)
     #[serde(default)]
     pub source_peers: Vec<SourcePeerConfig>,
+    /// Per-IP rate limit for the trigger server
+    #[serde(default = "default_trigger_rate_limit")]
+    pub trigger_rate_limit: RateLimitSettings,
+    /// Snapshots (manifest sequences) kept restorable per source folder
+    #[serde(default = "default_snapshot_retention")]
+    pub snapshot_retention: u32,
+    /// Hours tombstoned files stay quarantined (and restorable) before they
+    /// are deleted; 0 deletes them as soon as a manifest lists them
+    #[serde(default = "default_deletion_grace_period_hours")]
+    pub deletion_grace_period_hours: u32,
+    /// Hours between integrity scrubs of backed-up content; 0 disables them
+    #[serde(default = "default_scrub_interval_hours")]
+    pub scrub_interval_hours: u32,
+    /// When a mirrored folder counts as falling behind its source
+    #[serde(default)]
+    pub lag_alerts: LagAlertSettings,
+}
+
+/// Thresholds for backup lag alerts (desktop notifications and tray status)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LagAlertSettings {
+    /// Whether desktop notifications are raised when a threshold is crossed
+    pub enabled: bool,
+    /// Alert when a folder is more than this many sequences behind the
+    /// source; 0 disables the check
+    pub max_sequences_behind: u64,
+    /// Alert when a folder that is behind was last fully backed up (or, if it
+    /// never completed, first seen) more than this many hours ago, or when
+    /// its source was last polled more than this many hours ago; 0 disables
+    /// the check
+    pub max_hours_behind: u32,
+}
+
+impl Default for LagAlertSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_sequences_behind: 3,
+            max_hours_behind: 24,
+        }
+    }
 }
 
 fn default_trigger_port() -> u16 {
     8086
 }
 
+fn default_trigger_enabled() -> bool {
+    true
+}
+
+fn default_trigger_bind_address() -> String {
+    "0.0.0.0".to_string()
+}
+
+fn default_snapshot_retention() -> u32 {
+    5
+}
+
+fn default_deletion_grace_period_hours() -> u32 {
+    72
+}
+
+pub(crate) fn default_scrub_interval_hours() -> u32 {
+    24
+}
+
 /// Configuration for a source peer to poll for manifests
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SourcePeerConfig {
     /// Human-friendly name for this peer
     pub nickname: String,
@@ -132,6 +292,59 @@ pub struct SourcePeerConfig {
     pub multiaddr: Option<String>,
     /// Whether this source is enabled
     pub enabled: bool,
+    /// Maximum bytes this source may occupy on the backup node (latest
+    /// snapshot of each of its folders); unlimited when unset
+    #[serde(default)]
+    pub max_storage_bytes: Option<u64>,
+    /// Shared secret for signing backup acks sent to this source and checking
+    /// the triggers it sends (must match the source's
+    /// `manifest_server.ack_secret`). Unsigned triggers are rejected unless
+    /// `allow_unsigned_triggers` is set and no source has one.
+    #[serde(default)]
+    pub ack_secret: Option<String>,
+    /// Per-folder mirroring rules, by the folder IDs the source advertises
+    #[serde(default)]
+    pub folder_rules: Vec<FolderMirrorRule>,
+    /// Whether folders without a rule are mirrored (default: true)
+    #[serde(default = "default_mirror_unlisted_folders")]
+    pub mirror_unlisted_folders: bool,
+}
+
+fn default_mirror_unlisted_folders() -> bool {
+    true
+}
+
+impl SourcePeerConfig {
+    /// Mirroring rule configured for one of the source's folders
+    pub fn folder_rule(&self, folder_id: &str) -> Option<&FolderMirrorRule> {
+        self.folder_rules.iter().find(|r| r.folder_id == folder_id)
+    }
+
+    /// Whether manifests of `folder_id` are processed at all
+    pub fn mirrors_folder(&self, folder_id: &str) -> bool {
+        self.folder_rule(folder_id)
+            .map_or(self.mirror_unlisted_folders, |r| r.enabled)
+    }
+
+    /// Path prefixes mirrored inside `folder_id` (empty = the whole folder)
+    pub fn path_prefixes(&self, folder_id: &str) -> &[String] {
+        self.folder_rule(folder_id)
+            .map_or(&[], |r| r.path_prefixes.as_slice())
+    }
+}
+
+/// Which part of one of a source's folders the backup server mirrors
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FolderMirrorRule {
+    /// Folder ID as advertised by the source's manifest server
+    pub folder_id: String,
+    /// Whether the folder is mirrored; excluded folders stay listed in the
+    /// daemon state but their manifests are ignored
+    pub enabled: bool,
+    /// Only files under these paths (relative to the folder root) are
+    /// downloaded and have their tombstones enforced; empty = everything
+    #[serde(default)]
+    pub path_prefixes: Vec<String>,
 }
 
 /// Settings for the manifest discovery server (Machine A exposes this)
@@ -144,6 +357,63 @@ pub struct ManifestServerSettings {
     /// Whitelisted IP addresses that can query this server
     #[serde(default)]
     pub allowed_ips: Vec<String>,
+    /// Per-IP rate limit for manifest requests
+    #[serde(default = "default_manifest_rate_limit")]
+    pub rate_limit: RateLimitSettings,
+    /// Per-IP rate limit for `GET /data/{cid}`, separate so a fallback
+    /// download of a whole folder isn't throttled like manifest polling
+    #[serde(default = "default_content_rate_limit")]
+    pub content_rate_limit: RateLimitSettings,
+    /// Shared secret backup peers sign their acknowledgements with, and this
+    /// device signs its triggers to backup targets with; when unset, acks are
+    /// only recorded as unverified and never mark a folder as backed up
+    #[serde(default)]
+    pub ack_secret: Option<String>,
+    /// Serve stored content by CID over `GET /data/{cid}` so backup peers
+    /// can fall back to HTTP when P2P fetches fail (requires `ack_secret`,
+    /// which requests are signed with)
+    #[serde(default)]
+    pub http_fallback_enabled: bool,
+}
+
+/// Per-IP token bucket settings for the embedded HTTP servers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitSettings {
+    /// Sustained requests allowed per minute from one IP (0 = unlimited)
+    pub requests_per_minute: u32,
+    /// Requests allowed in a burst before the sustained rate applies
+    pub burst: u32,
+}
+
+fn default_manifest_rate_limit() -> RateLimitSettings {
+    RateLimitSettings {
+        requests_per_minute: 60,
+        burst: 10,
+    }
+}
+
+pub(crate) fn default_trigger_rate_limit() -> RateLimitSettings {
+    RateLimitSettings {
+        requests_per_minute: 30,
+        burst: 5,
+    }
+}
+
+// A fallback download fetches every file of a folder in turn
+fn default_content_rate_limit() -> RateLimitSettings {
+    RateLimitSettings {
+        requests_per_minute: 1200,
+        burst: 200,
+    }
+}
+
+// Video players issue many range requests while seeking, so streaming gets a
+// much higher ceiling than the control endpoints
+fn default_streaming_rate_limit() -> RateLimitSettings {
+    RateLimitSettings {
+        requests_per_minute: 1200,
+        burst: 200,
+    }
 }
 
 /// Media download settings for yt-dlp integration
@@ -171,6 +441,28 @@ pub struct MediaStreamingSettings {
     pub port: u16,
     #[serde(default)]
     pub allowed_ips: Vec<String>,
+    /// Per-IP rate limit for library and stream requests
+    #[serde(default = "default_streaming_rate_limit")]
+    pub rate_limit: RateLimitSettings,
+}
+
+/// LAN device discovery settings (mDNS / DNS-SD)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoverySettings {
+    /// Whether this device advertises itself on the local network
+    pub enabled: bool,
+    /// Name shown to other devices (defaults to the hostname)
+    #[serde(default)]
+    pub device_name: Option<String>,
+}
+
+impl Default for DiscoverySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            device_name: None,
+        }
+    }
 }
 
 impl Default for MediaStreamingSettings {
@@ -179,6 +471,7 @@ impl Default for MediaStreamingSettings {
             enabled: false,
             port: 8087,
             allowed_ips: Vec::new(),
+            rate_limit: default_streaming_rate_limit(),
         }
     }
 }
@@ -189,6 +482,10 @@ impl Default for ManifestServerSettings {
             enabled: false,
             port: 8085,
             allowed_ips: Vec::new(),
+            rate_limit: default_manifest_rate_limit(),
+            content_rate_limit: default_content_rate_limit(),
+            ack_secret: None,
+            http_fallback_enabled: false,
         }
     }
 }
@@ -230,6 +527,11 @@ impl Default for AppConfig {
                 max_storage_gb: 10,
                 auto_start: true,
                 log_level: "DEBUG".to_string(), // Good balance of verbosity for debugging
+                log_topics: BTreeMap::new(),
+                shutdown_grace_secs: default_shutdown_grace_secs(),
+                log_max_size_mb: default_log_max_size_mb(),
+                log_max_files: default_log_max_files(),
+                launch: NodeLaunchOptions::default(),
             },
             sync: SyncSettings {
                 auto_sync: true,
@@ -247,6 +549,7 @@ impl Default for AppConfig {
                 backup_manifest_enabled: true,
                 backup_auto_notify: false,
                 backup_trigger_port: 8086,
+                backup_targets: Vec::new(),
                 manifest_update_threshold: 1,
                 manifest_retry_interval_secs: 300,
                 manifest_max_retries: 5,
@@ -268,11 +571,20 @@ impl Default for AppConfig {
                 max_retries: 3,
                 auto_delete_tombstones: true,
                 trigger_port: 8086,
+                trigger_enabled: default_trigger_enabled(),
+                trigger_bind_address: default_trigger_bind_address(),
+                allow_unsigned_triggers: false,
                 source_peers: Vec::new(),
+                trigger_rate_limit: default_trigger_rate_limit(),
+                snapshot_retention: default_snapshot_retention(),
+                deletion_grace_period_hours: default_deletion_grace_period_hours(),
+                scrub_interval_hours: default_scrub_interval_hours(),
+                lag_alerts: LagAlertSettings::default(),
             },
             manifest_server: ManifestServerSettings::default(),
             media_download: MediaDownloadSettings::default(),
             media_streaming: MediaStreamingSettings::default(),
+            discovery: DiscoverySettings::default(),
             #[cfg(feature = "marketplace")]
             blockchain: None,
             #[cfg(feature = "marketplace")]
@@ -0,0 +1,933 @@
+//! LAN device discovery over mDNS / DNS-SD
+//!
+//! Each Archivist instance advertises a `_archivist._tcp.local` service with
+//! its peer ID, ports and capabilities in TXT records. Another machine on the
+//! same network can browse for these records and turn a discovered device into
+//! a `SourcePeerConfig` (backup server side) or a backup peer address (source
+//! side) without typing hosts, ports or multiaddrs by hand.
+//!
+//! Only the small subset of DNS needed for this is implemented: PTR queries for
+//! our service type, answered with PTR + SRV + TXT records. Queries sent from a
+//! port other than 5353 get a unicast reply (RFC 6762 legacy unicast), which is
+//! what the browser uses and what lets tests run on loopback.
+
+use crate::error::{ArchivistError, Result};
+use crate::node_api::NodeApiClient;
+use crate::services::config::{AppConfig, SourcePeerConfig};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::net::UdpSocket;
+use tokio::sync::RwLock;
+use tokio::time::{Duration, Instant};
+
+/// DNS-SD service type advertised by Archivist devices
+pub const SERVICE_TYPE: &str = "_archivist._tcp.local";
+
+/// Standard mDNS multicast group and port
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+/// Cache-flush bit set on unique records (SRV/TXT)
+const CLASS_CACHE_FLUSH: u16 = 0x8000;
+const RECORD_TTL_SECS: u32 = 120;
+
+/// How often the announcement is rebuilt from the settings and node status
+pub const ANNOUNCEMENT_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Capability advertised when the manifest discovery server is running
+pub const CAP_MANIFEST_SERVER: &str = "manifest";
+/// Capability advertised when the backup daemon accepts triggers
+pub const CAP_BACKUP_DAEMON: &str = "backup";
+
+/// What this device advertises about itself
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceAnnouncement {
+    /// Human-friendly instance name (e.g. the hostname)
+    pub instance_name: String,
+    /// Node peer ID, if the node is running
+    pub peer_id: Option<String>,
+    /// TCP port of the node's P2P listener
+    pub listen_port: u16,
+    /// Manifest server port (only set when the manifest server is enabled)
+    pub manifest_port: Option<u16>,
+    /// Backup daemon trigger port (only set when the backup daemon accepts
+    /// triggers)
+    pub trigger_port: Option<u16>,
+    /// Capabilities such as "manifest" and "backup"
+    pub capabilities: Vec<String>,
+}
+
+impl DeviceAnnouncement {
+    /// What the current settings advertise, with the node's peer ID if known
+    pub fn from_config(config: &AppConfig, peer_id: Option<String>) -> Self {
+        // Backup servers are only selectable with a trigger port to notify
+        let trigger_port = (config.backup_server.enabled && config.backup_server.trigger_enabled)
+            .then_some(config.backup_server.trigger_port);
+
+        let mut capabilities = Vec::new();
+        if config.manifest_server.enabled {
+            capabilities.push(CAP_MANIFEST_SERVER.to_string());
+        }
+        if trigger_port.is_some() {
+            capabilities.push(CAP_BACKUP_DAEMON.to_string());
+        }
+
+        Self {
+            instance_name: config
+                .discovery
+                .device_name
+                .clone()
+                .unwrap_or_else(default_instance_name),
+            peer_id,
+            listen_port: config.node.listen_port,
+            manifest_port: config
+                .manifest_server
+                .enabled
+                .then_some(config.manifest_server.port),
+            trigger_port,
+            capabilities,
+        }
+    }
+}
+
+/// A device found on the local network
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveredDevice {
+    pub instance_name: String,
+    /// IP address the announcement was received from
+    pub host: String,
+    pub peer_id: Option<String>,
+    pub listen_port: u16,
+    pub manifest_port: Option<u16>,
+    pub trigger_port: Option<u16>,
+    pub capabilities: Vec<String>,
+    /// P2P multiaddr built from host, listen port and peer ID
+    pub multiaddr: Option<String>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl DiscoveredDevice {
+    fn from_announcement(announcement: DeviceAnnouncement, host: IpAddr) -> Self {
+        let multiaddr = announcement.peer_id.as_ref().map(|peer_id| {
+            let proto = if host.is_ipv4() { "ip4" } else { "ip6" };
+            format!(
+                "/{}/{}/tcp/{}/p2p/{}",
+                proto, host, announcement.listen_port, peer_id
+            )
+        });
+
+        Self {
+            instance_name: announcement.instance_name,
+            host: host.to_string(),
+            peer_id: announcement.peer_id,
+            listen_port: announcement.listen_port,
+            manifest_port: announcement.manifest_port,
+            trigger_port: announcement.trigger_port,
+            capabilities: announcement.capabilities,
+            multiaddr,
+            last_seen: Utc::now(),
+        }
+    }
+
+    /// Whether the device advertises the given capability
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    /// Build a source peer config for the backup daemon (device must run a manifest server)
+    pub fn to_source_peer_config(&self) -> Result<SourcePeerConfig> {
+        let manifest_port = self
+            .manifest_port
+            .filter(|_| self.has_capability(CAP_MANIFEST_SERVER))
+            .ok_or_else(|| {
+                ArchivistError::ConfigError(format!(
+                    "Device {} does not advertise a manifest server",
+                    self.instance_name
+                ))
+            })?;
+
+        Ok(SourcePeerConfig {
+            nickname: self.instance_name.clone(),
+            host: self.host.clone(),
+            manifest_port,
+            peer_id: self.peer_id.clone(),
+            multiaddr: self.multiaddr.clone(),
+            enabled: true,
+            max_storage_bytes: None,
+            ack_secret: None,
+            folder_rules: Vec::new(),
+            mirror_unlisted_folders: true,
+        })
+    }
+}
+
+/// Advertises this device over mDNS and browses for other devices
+pub struct DiscoveryService {
+    announcement: Arc<RwLock<Option<DeviceAnnouncement>>>,
+    shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
+}
+
+impl DiscoveryService {
+    pub fn new() -> Self {
+        Self {
+            announcement: Arc::new(RwLock::new(None)),
+            shutdown_tx: None,
+        }
+    }
+
+    /// Update what this device advertises (takes effect for the next query)
+    pub async fn set_announcement(&self, announcement: DeviceAnnouncement) {
+        let mut current = self.announcement.write().await;
+        if current.as_ref() == Some(&announcement) {
+            return;
+        }
+        log::info!(
+            "mDNS announcement updated: {} (capabilities: {:?})",
+            announcement.instance_name,
+            announcement.capabilities
+        );
+        *current = Some(announcement);
+    }
+
+    /// Whether the responder is running
+    pub fn is_running(&self) -> bool {
+        self.shutdown_tx.is_some()
+    }
+
+    /// Start answering mDNS queries on the standard multicast group
+    pub async fn start(&mut self) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+
+        let socket = bind_multicast_socket().map_err(|e| {
+            ArchivistError::ConfigError(format!("Failed to bind mDNS socket: {}", e))
+        })?;
+        self.start_with_socket(socket);
+
+        log::info!("mDNS responder started for {}", SERVICE_TYPE);
+        Ok(())
+    }
+
+    /// Start answering queries received on an already-bound socket
+    fn start_with_socket(&mut self, socket: UdpSocket) {
+        let (tx, mut rx) = tokio::sync::oneshot::channel();
+        self.shutdown_tx = Some(tx);
+        let announcement = self.announcement.clone();
+
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 4096];
+            loop {
+                tokio::select! {
+                    _ = &mut rx => break,
+                    received = socket.recv_from(&mut buf) => {
+                        let (len, src) = match received {
+                            Ok(r) => r,
+                            Err(e) => {
+                                log::debug!("mDNS receive error: {}", e);
+                                continue;
+                            }
+                        };
+
+                        let Some(query_id) = parse_service_query(&buf[..len]) else {
+                            continue;
+                        };
+                        let Some(current) = announcement.read().await.clone() else {
+                            continue;
+                        };
+
+                        // Legacy unicast queries (not from port 5353) get a direct reply
+                        let legacy_unicast = src.port() != MDNS_PORT;
+                        let response = build_response(
+                            if legacy_unicast { query_id } else { 0 },
+                            &current,
+                            legacy_unicast,
+                        );
+                        let dest = if legacy_unicast {
+                            src
+                        } else {
+                            SocketAddr::from((MDNS_GROUP, MDNS_PORT))
+                        };
+
+                        if let Err(e) = socket.send_to(&response, dest).await {
+                            log::debug!("Failed to send mDNS response to {}: {}", dest, e);
+                        }
+                    }
+                }
+            }
+            log::info!("mDNS responder stopped");
+        });
+    }
+
+    /// Stop the responder
+    pub fn stop(&mut self) {
+        if let Some(tx) = self.shutdown_tx.take() {
+            let _ = tx.send(());
+        }
+    }
+
+    /// Bring the responder in line with `config`: advertise the current
+    /// settings and peer ID while discovery is enabled, stop otherwise
+    pub async fn refresh(discovery: &RwLock<DiscoveryService>, config: &AppConfig) {
+        if !config.discovery.enabled {
+            let mut discovery = discovery.write().await;
+            if discovery.is_running() {
+                discovery.stop();
+                log::info!("LAN discovery disabled");
+            }
+            return;
+        }
+
+        let peer_id = NodeApiClient::new(config.node.api_port)
+            .get_info()
+            .await
+            .ok()
+            .map(|info| info.id);
+        let announcement = DeviceAnnouncement::from_config(config, peer_id);
+
+        let mut discovery = discovery.write().await;
+        discovery.set_announcement(announcement).await;
+        if let Err(e) = discovery.start().await {
+            log::error!("Failed to start mDNS responder: {}", e);
+        }
+    }
+
+    /// Browse the local network for Archivist devices
+    pub async fn browse(timeout: Duration) -> Result<Vec<DiscoveredDevice>> {
+        Self::browse_at(SocketAddr::from((MDNS_GROUP, MDNS_PORT)), timeout).await
+    }
+
+    /// Send a service query to `target` and collect answers until `timeout`
+    async fn browse_at(target: SocketAddr, timeout: Duration) -> Result<Vec<DiscoveredDevice>> {
+        let bind_addr: SocketAddr = if target.ip().is_loopback() {
+            (Ipv4Addr::LOCALHOST, 0).into()
+        } else {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        };
+        let socket = UdpSocket::bind(bind_addr).await?;
+        if target.ip().is_multicast() {
+            socket.set_multicast_ttl_v4(255)?;
+        }
+
+        let query_id = (Utc::now().timestamp_subsec_micros() & 0xffff) as u16;
+        socket.send_to(&build_query(query_id), target).await?;
+
+        let mut devices: HashMap<String, DiscoveredDevice> = HashMap::new();
+        let deadline = Instant::now() + timeout;
+        let mut buf = vec![0u8; 4096];
+
+        loop {
+            let recv = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf));
+            let (len, src) = match recv.await {
+                Ok(Ok(r)) => r,
+                Ok(Err(e)) => {
+                    log::debug!("mDNS browse receive error: {}", e);
+                    continue;
+                }
+                Err(_) => break,
+            };
+
+            for announcement in parse_response(&buf[..len]) {
+                let device = DiscoveredDevice::from_announcement(announcement, src.ip());
+                let key = device
+                    .peer_id
+                    .clone()
+                    .unwrap_or_else(|| format!("{}@{}", device.instance_name, device.host));
+                devices.insert(key, device);
+            }
+        }
+
+        log::info!("mDNS browse found {} devices", devices.len());
+        Ok(devices.into_values().collect())
+    }
+}
+
+impl Default for DiscoveryService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bind a UDP socket to the mDNS port, sharing it with any system responder
+fn bind_multicast_socket() -> std::io::Result<UdpSocket> {
+    use socket2::{Domain, Protocol, Socket, Type};
+
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, MDNS_PORT)).into())?;
+    socket.join_multicast_v4(&MDNS_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_nonblocking(true)?;
+
+    UdpSocket::from_std(socket.into())
+}
+
+/// Default instance name for this device (hostname when available)
+pub fn default_instance_name() -> String {
+    system_hostname()
+        .map(|h| h.trim().trim_end_matches(".local").to_string())
+        .filter(|h| !h.is_empty())
+        .unwrap_or_else(|| "Archivist Device".to_string())
+}
+
+/// The system's hostname; `HOSTNAME` is a shell variable GUI apps don't see
+#[cfg(unix)]
+fn system_hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) } != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Some(String::from_utf8_lossy(&buf[..len]).to_string())
+}
+
+/// The computer name, which Windows sets for every process
+#[cfg(not(unix))]
+fn system_hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+// --- DNS wire format ---
+
+fn write_name(out: &mut Vec<u8>, name: &str) {
+    for label in name.trim_end_matches('.').split('.') {
+        let bytes = label.as_bytes();
+        let len = bytes.len().min(63);
+        out.push(len as u8);
+        out.extend_from_slice(&bytes[..len]);
+    }
+    out.push(0);
+}
+
+fn write_header(out: &mut Vec<u8>, id: u16, flags: u16, qd: u16, an: u16) {
+    out.extend_from_slice(&id.to_be_bytes());
+    out.extend_from_slice(&flags.to_be_bytes());
+    out.extend_from_slice(&qd.to_be_bytes());
+    out.extend_from_slice(&an.to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes()); // NSCOUNT
+    out.extend_from_slice(&0u16.to_be_bytes()); // ARCOUNT
+}
+
+fn write_record(out: &mut Vec<u8>, name: &str, rtype: u16, class: u16, rdata: &[u8]) {
+    write_name(out, name);
+    out.extend_from_slice(&rtype.to_be_bytes());
+    out.extend_from_slice(&class.to_be_bytes());
+    out.extend_from_slice(&RECORD_TTL_SECS.to_be_bytes());
+    out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    out.extend_from_slice(rdata);
+}
+
+fn instance_label(name: &str) -> String {
+    name.chars()
+        .map(|c| if c == '.' { '-' } else { c })
+        .take(63)
+        .collect()
+}
+
+/// Build a PTR query for the Archivist service type
+fn build_query(id: u16) -> Vec<u8> {
+    let mut out = Vec::with_capacity(64);
+    write_header(&mut out, id, 0, 1, 0);
+    write_name(&mut out, SERVICE_TYPE);
+    out.extend_from_slice(&TYPE_PTR.to_be_bytes());
+    out.extend_from_slice(&CLASS_IN.to_be_bytes());
+    out
+}
+
+/// Build a PTR + SRV + TXT response for this device
+fn build_response(id: u16, announcement: &DeviceAnnouncement, include_question: bool) -> Vec<u8> {
+    let label = instance_label(&announcement.instance_name);
+    let instance_fqdn = format!("{}.{}", label, SERVICE_TYPE);
+    let host_fqdn = format!("{}.local", label.replace(' ', "-"));
+
+    let mut out = Vec::with_capacity(512);
+    write_header(&mut out, id, 0x8400, u16::from(include_question), 3);
+    if include_question {
+        write_name(&mut out, SERVICE_TYPE);
+        out.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        out.extend_from_slice(&CLASS_IN.to_be_bytes());
+    }
+
+    // PTR: service type -> instance
+    let mut ptr = Vec::new();
+    write_name(&mut ptr, &instance_fqdn);
+    write_record(&mut out, SERVICE_TYPE, TYPE_PTR, CLASS_IN, &ptr);
+
+    // SRV: instance -> host:port (manifest port if served, else P2P port)
+    let mut srv = Vec::new();
+    srv.extend_from_slice(&0u16.to_be_bytes()); // priority
+    srv.extend_from_slice(&0u16.to_be_bytes()); // weight
+    srv.extend_from_slice(
+        &announcement
+            .manifest_port
+            .unwrap_or(announcement.listen_port)
+            .to_be_bytes(),
+    );
+    write_name(&mut srv, &host_fqdn);
+    write_record(
+        &mut out,
+        &instance_fqdn,
+        TYPE_SRV,
+        CLASS_IN | CLASS_CACHE_FLUSH,
+        &srv,
+    );
+
+    // TXT: key=value metadata
+    let mut entries = vec![
+        "v=1".to_string(),
+        format!("name={}", announcement.instance_name),
+        format!("lport={}", announcement.listen_port),
+        format!("caps={}", announcement.capabilities.join(",")),
+    ];
+    if let Some(peer_id) = &announcement.peer_id {
+        entries.push(format!("peer={}", peer_id));
+    }
+    if let Some(port) = announcement.manifest_port {
+        entries.push(format!("mport={}", port));
+    }
+    if let Some(port) = announcement.trigger_port {
+        entries.push(format!("tport={}", port));
+    }
+    let mut txt = Vec::new();
+    for entry in entries {
+        let bytes = entry.as_bytes();
+        let len = bytes.len().min(255);
+        txt.push(len as u8);
+        txt.extend_from_slice(&bytes[..len]);
+    }
+    write_record(
+        &mut out,
+        &instance_fqdn,
+        TYPE_TXT,
+        CLASS_IN | CLASS_CACHE_FLUSH,
+        &txt,
+    );
+
+    out
+}
+
+/// Read a (possibly compressed) domain name starting at `pos`.
+/// Returns the name and the position just after it in the original stream.
+fn read_name(buf: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *buf.get(pos)? as usize;
+        if len == 0 {
+            pos += 1;
+            break;
+        }
+        if len & 0xC0 == 0xC0 {
+            let pointer = ((len & 0x3F) << 8) | *buf.get(pos + 1)? as usize;
+            if end.is_none() {
+                end = Some(pos + 2);
+            }
+            jumps += 1;
+            if jumps > 16 {
+                return None;
+            }
+            pos = pointer;
+            continue;
+        }
+        let label = buf.get(pos + 1..pos + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        pos += 1 + len;
+    }
+
+    Some((labels.join("."), end.unwrap_or(pos)))
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*buf.get(pos)?, *buf.get(pos + 1)?]))
+}
+
+/// If `buf` is a query for our service type, return its transaction ID
+fn parse_service_query(buf: &[u8]) -> Option<u16> {
+    let id = read_u16(buf, 0)?;
+    let flags = read_u16(buf, 2)?;
+    if flags & 0x8000 != 0 {
+        return None; // response, not a query
+    }
+
+    let qdcount = read_u16(buf, 4)?;
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        let (name, next) = read_name(buf, pos)?;
+        let qtype = read_u16(buf, next)?;
+        pos = next + 4;
+        if name.eq_ignore_ascii_case(SERVICE_TYPE) && (qtype == TYPE_PTR || qtype == TYPE_ANY) {
+            return Some(id);
+        }
+    }
+    None
+}
+
+/// TXT key/value pairs of a record's rdata; keys without a value are kept
+/// with an empty one
+fn parse_txt(rdata: &[u8]) -> HashMap<String, String> {
+    let mut txt = HashMap::new();
+    let mut i = 0;
+    while i < rdata.len() {
+        let len = rdata[i] as usize;
+        let Some(entry) = rdata.get(i + 1..i + 1 + len) else {
+            break;
+        };
+        let entry = String::from_utf8_lossy(entry);
+        let (key, value) = entry.split_once('=').unwrap_or((&entry, ""));
+        if !key.is_empty() {
+            // The first occurrence of a key wins (RFC 6763 section 6.4)
+            txt.entry(key.to_ascii_lowercase())
+                .or_insert_with(|| value.to_string());
+        }
+        i += 1 + len;
+    }
+    txt
+}
+
+/// Parse the Archivist services in a response, one announcement per
+/// instance. Answers and additional records are matched by instance name,
+/// so responders that bundle several instances or other services (as Bonjour
+/// does) are handled; goodbye records (TTL 0) are ignored.
+fn parse_response(buf: &[u8]) -> Vec<DeviceAnnouncement> {
+    parse_records(buf).unwrap_or_default()
+}
+
+fn parse_records(buf: &[u8]) -> Option<Vec<DeviceAnnouncement>> {
+    let flags = read_u16(buf, 2)?;
+    if flags & 0x8000 == 0 {
+        return None; // query, not a response
+    }
+
+    let qdcount = read_u16(buf, 4)?;
+    let record_count =
+        read_u16(buf, 6)? as usize + read_u16(buf, 8)? as usize + read_u16(buf, 10)? as usize;
+
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        let (_, next) = read_name(buf, pos)?;
+        pos = next + 4;
+    }
+
+    // Instance FQDNs (lowercased key, original spelling) in the order their
+    // PTR records appear
+    let mut instances: Vec<(String, String)> = Vec::new();
+    let mut srv_ports: HashMap<String, u16> = HashMap::new();
+    let mut txts: HashMap<String, HashMap<String, String>> = HashMap::new();
+
+    for _ in 0..record_count {
+        let (name, next) = read_name(buf, pos)?;
+        let rtype = read_u16(buf, next)?;
+        let ttl = u32::from_be_bytes(buf.get(next + 4..next + 8)?.try_into().ok()?);
+        let rdlen = read_u16(buf, next + 8)? as usize;
+        let rdata_start = next + 10;
+        let rdata = buf.get(rdata_start..rdata_start + rdlen)?;
+        pos = rdata_start + rdlen;
+
+        let name = name.to_ascii_lowercase();
+        if ttl == 0 {
+            continue;
+        }
+        match rtype {
+            TYPE_PTR if name == SERVICE_TYPE => {
+                if let Some((target, _)) = read_name(buf, rdata_start) {
+                    let key = target.to_ascii_lowercase();
+                    if !instances.iter().any(|(k, _)| *k == key) {
+                        instances.push((key, target));
+                    }
+                }
+            }
+            TYPE_SRV if name.ends_with(SERVICE_TYPE) => {
+                if let Some(port) = read_u16(rdata, 4) {
+                    srv_ports.insert(name, port);
+                }
+            }
+            TYPE_TXT if name.ends_with(SERVICE_TYPE) => {
+                txts.insert(name, parse_txt(rdata));
+            }
+            _ => {}
+        }
+    }
+
+    let announcements = instances
+        .into_iter()
+        .filter_map(|(key, fqdn)| {
+            let txt = txts.get(&key).filter(|t| !t.is_empty())?;
+            let port = |key: &str| txt.get(key).and_then(|v| v.parse::<u16>().ok());
+            let instance_name = txt.get("name").cloned().unwrap_or_else(|| {
+                // Same byte length as the lowercased key, so the suffix
+                // boundary is valid in both
+                let end = if key.ends_with(SERVICE_TYPE) {
+                    fqdn.len() - SERVICE_TYPE.len()
+                } else {
+                    fqdn.len()
+                };
+                fqdn[..end].trim_end_matches('.').to_string()
+            });
+
+            Some(DeviceAnnouncement {
+                instance_name,
+                peer_id: txt.get("peer").cloned(),
+                listen_port: port("lport").or_else(|| srv_ports.get(&key).copied())?,
+                manifest_port: port("mport"),
+                trigger_port: port("tport"),
+                capabilities: txt
+                    .get("caps")
+                    .map(|c| {
+                        c.split(',')
+                            .filter(|s| !s.is_empty())
+                            .map(|s| s.to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+        })
+        .collect();
+    Some(announcements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn announcement() -> DeviceAnnouncement {
+        DeviceAnnouncement {
+            instance_name: "laptop.home".to_string(),
+            peer_id: Some("16Uiu2HAmTestPeer".to_string()),
+            listen_port: 8070,
+            manifest_port: Some(8085),
+            trigger_port: None,
+            capabilities: vec![CAP_MANIFEST_SERVER.to_string()],
+        }
+    }
+
+    #[test]
+    fn test_query_is_recognised() {
+        let query = build_query(0x1234);
+        assert_eq!(parse_service_query(&query), Some(0x1234));
+        // Responses must not be treated as queries
+        assert_eq!(
+            parse_service_query(&build_response(1, &announcement(), true)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_announcement_from_config() {
+        let mut config = AppConfig::default();
+        config.discovery.device_name = Some("laptop.home".to_string());
+        config.manifest_server.enabled = true;
+        config.backup_server.enabled = false;
+
+        let announcement = DeviceAnnouncement::from_config(&config, None);
+        assert_eq!(announcement.instance_name, "laptop.home");
+        assert_eq!(
+            announcement.manifest_port,
+            Some(config.manifest_server.port)
+        );
+        assert_eq!(announcement.trigger_port, None);
+        assert_eq!(announcement.capabilities, vec![CAP_MANIFEST_SERVER]);
+
+        // A backup server without triggers can't be selected, so it isn't advertised
+        config.backup_server.enabled = true;
+        config.backup_server.trigger_enabled = false;
+        let announcement = DeviceAnnouncement::from_config(&config, None);
+        assert_eq!(announcement.capabilities, vec![CAP_MANIFEST_SERVER]);
+
+        config.backup_server.trigger_enabled = true;
+        let announcement = DeviceAnnouncement::from_config(&config, None);
+        assert_eq!(
+            announcement.trigger_port,
+            Some(config.backup_server.trigger_port)
+        );
+        assert!(announcement
+            .capabilities
+            .contains(&CAP_BACKUP_DAEMON.to_string()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_default_instance_name_uses_hostname() {
+        let hostname = system_hostname().unwrap();
+        let expected = hostname.trim().trim_end_matches(".local");
+        if !expected.is_empty() {
+            assert_eq!(default_instance_name(), expected);
+        }
+    }
+
+    #[test]
+    fn test_response_roundtrip() {
+        let response = build_response(7, &announcement(), true);
+        assert_eq!(parse_response(&response), vec![announcement()]);
+    }
+
+    /// Avahi layout: PTR answer, then SRV, TXT, A and AAAA as additional
+    /// records, all names compressed against the first one
+    const AVAHI_RESPONSE: &[u8] = &[
+        0x00, 0x00, 0x84, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x0a, 0x5f, 0x61,
+        0x72, 0x63, 0x68, 0x69, 0x76, 0x69, 0x73, 0x74, 0x04, 0x5f, 0x74, 0x63, 0x70, 0x05, 0x6c,
+        0x6f, 0x63, 0x61, 0x6c, 0x00, 0x00, 0x0c, 0x00, 0x01, 0x00, 0x00, 0x11, 0x94, 0x00, 0x06,
+        0x03, 0x6e, 0x61, 0x73, 0xc0, 0x0c, 0xc0, 0x2d, 0x00, 0x21, 0x80, 0x01, 0x00, 0x00, 0x00,
+        0x78, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x1f, 0x95, 0x03, 0x6e, 0x61, 0x73, 0xc0, 0x1c,
+        0xc0, 0x2d, 0x00, 0x10, 0x80, 0x01, 0x00, 0x00, 0x11, 0x94, 0x00, 0x55, 0x03, 0x76, 0x3d,
+        0x31, 0x08, 0x6e, 0x61, 0x6d, 0x65, 0x3d, 0x6e, 0x61, 0x73, 0x0a, 0x6c, 0x70, 0x6f, 0x72,
+        0x74, 0x3d, 0x38, 0x30, 0x37, 0x30, 0x14, 0x63, 0x61, 0x70, 0x73, 0x3d, 0x6d, 0x61, 0x6e,
+        0x69, 0x66, 0x65, 0x73, 0x74, 0x2c, 0x62, 0x61, 0x63, 0x6b, 0x75, 0x70, 0x11, 0x70, 0x65,
+        0x65, 0x72, 0x3d, 0x31, 0x36, 0x55, 0x69, 0x75, 0x32, 0x48, 0x41, 0x6d, 0x4e, 0x61, 0x73,
+        0x0a, 0x6d, 0x70, 0x6f, 0x72, 0x74, 0x3d, 0x38, 0x30, 0x38, 0x35, 0x0a, 0x74, 0x70, 0x6f,
+        0x72, 0x74, 0x3d, 0x38, 0x30, 0x38, 0x36, 0xc0, 0x45, 0x00, 0x01, 0x80, 0x01, 0x00, 0x00,
+        0x00, 0x78, 0x00, 0x04, 0xc0, 0xa8, 0x01, 0x14, 0xc0, 0x45, 0x00, 0x1c, 0x80, 0x01, 0x00,
+        0x00, 0x00, 0x78, 0x00, 0x10, 0xfe, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x11,
+        0x32, 0xff, 0xfe, 0x4a, 0x5b, 0x6c,
+    ];
+
+    /// Bonjour layout: two instances plus an unrelated `_http._tcp` service
+    /// in one packet, a repeated TXT key, a key without a value and an NSEC
+    /// record
+    const BONJOUR_RESPONSE: &[u8] = &[
+        0x00, 0x00, 0x84, 0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x07, 0x0a, 0x5f, 0x61,
+        0x72, 0x63, 0x68, 0x69, 0x76, 0x69, 0x73, 0x74, 0x04, 0x5f, 0x74, 0x63, 0x70, 0x05, 0x6c,
+        0x6f, 0x63, 0x61, 0x6c, 0x00, 0x00, 0x0c, 0x00, 0x01, 0x00, 0x00, 0x11, 0x94, 0x00, 0x0e,
+        0x0b, 0x4d, 0x61, 0x63, 0x42, 0x6f, 0x6f, 0x6b, 0x2d, 0x50, 0x72, 0x6f, 0xc0, 0x0c, 0xc0,
+        0x0c, 0x00, 0x0c, 0x00, 0x01, 0x00, 0x00, 0x11, 0x94, 0x00, 0x09, 0x06, 0x53, 0x74, 0x75,
+        0x64, 0x69, 0x6f, 0xc0, 0x0c, 0x05, 0x5f, 0x68, 0x74, 0x74, 0x70, 0xc0, 0x17, 0x00, 0x0c,
+        0x00, 0x01, 0x00, 0x00, 0x11, 0x94, 0x00, 0x0a, 0x07, 0x50, 0x72, 0x69, 0x6e, 0x74, 0x65,
+        0x72, 0xc0, 0x50, 0xc0, 0x2d, 0x00, 0x21, 0x80, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x14,
+        0x00, 0x00, 0x00, 0x00, 0x1f, 0x86, 0x0b, 0x4d, 0x61, 0x63, 0x42, 0x6f, 0x6f, 0x6b, 0x2d,
+        0x50, 0x72, 0x6f, 0xc0, 0x1c, 0xc0, 0x2d, 0x00, 0x10, 0x80, 0x01, 0x00, 0x00, 0x11, 0x94,
+        0x00, 0x5c, 0x03, 0x76, 0x3d, 0x31, 0x10, 0x6e, 0x61, 0x6d, 0x65, 0x3d, 0x4d, 0x61, 0x63,
+        0x42, 0x6f, 0x6f, 0x6b, 0x2d, 0x50, 0x72, 0x6f, 0x0a, 0x6c, 0x70, 0x6f, 0x72, 0x74, 0x3d,
+        0x38, 0x30, 0x37, 0x30, 0x0a, 0x6c, 0x70, 0x6f, 0x72, 0x74, 0x3d, 0x39, 0x39, 0x39, 0x39,
+        0x0d, 0x63, 0x61, 0x70, 0x73, 0x3d, 0x6d, 0x61, 0x6e, 0x69, 0x66, 0x65, 0x73, 0x74, 0x11,
+        0x70, 0x65, 0x65, 0x72, 0x3d, 0x31, 0x36, 0x55, 0x69, 0x75, 0x32, 0x48, 0x41, 0x6d, 0x4d,
+        0x61, 0x63, 0x0a, 0x6d, 0x70, 0x6f, 0x72, 0x74, 0x3d, 0x38, 0x30, 0x38, 0x35, 0x05, 0x64,
+        0x65, 0x62, 0x75, 0x67, 0xc0, 0x47, 0x00, 0x21, 0x80, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00,
+        0x0f, 0x00, 0x00, 0x00, 0x00, 0x1f, 0x87, 0x06, 0x53, 0x74, 0x75, 0x64, 0x69, 0x6f, 0xc0,
+        0x1c, 0xc0, 0x47, 0x00, 0x10, 0x80, 0x01, 0x00, 0x00, 0x11, 0x94, 0x00, 0x1b, 0x03, 0x76,
+        0x3d, 0x31, 0x0b, 0x63, 0x61, 0x70, 0x73, 0x3d, 0x62, 0x61, 0x63, 0x6b, 0x75, 0x70, 0x0a,
+        0x74, 0x70, 0x6f, 0x72, 0x74, 0x3d, 0x38, 0x30, 0x38, 0x36, 0xc0, 0x62, 0x00, 0x10, 0x80,
+        0x01, 0x00, 0x00, 0x11, 0x94, 0x00, 0x17, 0x0c, 0x6e, 0x61, 0x6d, 0x65, 0x3d, 0x50, 0x72,
+        0x69, 0x6e, 0x74, 0x65, 0x72, 0x09, 0x6c, 0x70, 0x6f, 0x72, 0x74, 0x3d, 0x36, 0x33, 0x31,
+        0xc0, 0x7e, 0x00, 0x01, 0x80, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x04, 0xc0, 0xa8, 0x01,
+        0x1e, 0xc0, 0x2d, 0x00, 0x2f, 0x80, 0x01, 0x00, 0x00, 0x11, 0x94, 0x00, 0x09, 0xc0, 0x2d,
+        0x00, 0x05, 0x00, 0x00, 0x80, 0x00, 0x40,
+    ];
+
+    /// Avahi goodbye sent when a service is withdrawn (TTL 0)
+    const AVAHI_GOODBYE: &[u8] = &[
+        0x00, 0x00, 0x84, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x0a, 0x5f, 0x61,
+        0x72, 0x63, 0x68, 0x69, 0x76, 0x69, 0x73, 0x74, 0x04, 0x5f, 0x74, 0x63, 0x70, 0x05, 0x6c,
+        0x6f, 0x63, 0x61, 0x6c, 0x00, 0x00, 0x0c, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x06,
+        0x03, 0x6e, 0x61, 0x73, 0xc0, 0x0c, 0xc0, 0x2d, 0x00, 0x10, 0x80, 0x01, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x18, 0x03, 0x76, 0x3d, 0x31, 0x08, 0x6e, 0x61, 0x6d, 0x65, 0x3d, 0x6e, 0x61,
+        0x73, 0x0a, 0x6c, 0x70, 0x6f, 0x72, 0x74, 0x3d, 0x38, 0x30, 0x37, 0x30,
+    ];
+
+    /// Bonjour query: several QU questions sharing compressed names, plus a
+    /// known answer
+    const BONJOUR_QUERY: &[u8] = &[
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x05, 0x5f, 0x68,
+        0x74, 0x74, 0x70, 0x04, 0x5f, 0x74, 0x63, 0x70, 0x05, 0x6c, 0x6f, 0x63, 0x61, 0x6c, 0x00,
+        0x00, 0x0c, 0x80, 0x01, 0x0a, 0x5f, 0x61, 0x72, 0x63, 0x68, 0x69, 0x76, 0x69, 0x73, 0x74,
+        0xc0, 0x12, 0x00, 0x0c, 0x80, 0x01, 0xc0, 0x22, 0x00, 0x0c, 0x00, 0x01, 0x00, 0x00, 0x11,
+        0x94, 0x00, 0x09, 0x06, 0x53, 0x74, 0x75, 0x64, 0x69, 0x6f, 0xc0, 0x22,
+    ];
+
+    #[test]
+    fn test_parse_avahi_response() {
+        assert_eq!(
+            parse_response(AVAHI_RESPONSE),
+            vec![DeviceAnnouncement {
+                instance_name: "nas".to_string(),
+                peer_id: Some("16Uiu2HAmNas".to_string()),
+                listen_port: 8070,
+                manifest_port: Some(8085),
+                trigger_port: Some(8086),
+                capabilities: vec![
+                    CAP_MANIFEST_SERVER.to_string(),
+                    CAP_BACKUP_DAEMON.to_string()
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_bonjour_response() {
+        let announcements = parse_response(BONJOUR_RESPONSE);
+        assert_eq!(
+            announcements,
+            vec![
+                DeviceAnnouncement {
+                    instance_name: "MacBook-Pro".to_string(),
+                    peer_id: Some("16Uiu2HAmMac".to_string()),
+                    listen_port: 8070,
+                    manifest_port: Some(8085),
+                    trigger_port: None,
+                    capabilities: vec![CAP_MANIFEST_SERVER.to_string()],
+                },
+                // No name or lport in TXT: fall back to the instance label
+                // and the SRV port
+                DeviceAnnouncement {
+                    instance_name: "Studio".to_string(),
+                    peer_id: None,
+                    listen_port: 8071,
+                    manifest_port: None,
+                    trigger_port: Some(8086),
+                    capabilities: vec![CAP_BACKUP_DAEMON.to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_goodbye_is_ignored() {
+        assert!(parse_response(AVAHI_GOODBYE).is_empty());
+    }
+
+    #[test]
+    fn test_bonjour_query_is_recognised() {
+        assert_eq!(parse_service_query(BONJOUR_QUERY), Some(0));
+        // Packets from other responders aren't queries
+        assert_eq!(parse_service_query(AVAHI_RESPONSE), None);
+    }
+
+    #[tokio::test]
+    async fn test_browse_on_loopback() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+
+        let mut service = DiscoveryService::new();
+        service.set_announcement(announcement()).await;
+        service.start_with_socket(socket);
+
+        let devices = DiscoveryService::browse_at(addr, Duration::from_millis(300))
+            .await
+            .unwrap();
+        service.stop();
+
+        assert_eq!(devices.len(), 1);
+        let device = &devices[0];
+        assert_eq!(device.host, "127.0.0.1");
+        assert_eq!(
+            device.multiaddr.as_deref(),
+            Some("/ip4/127.0.0.1/tcp/8070/p2p/16Uiu2HAmTestPeer")
+        );
+
+        let source = device.to_source_peer_config().unwrap();
+        assert_eq!(source.manifest_port, 8085);
+        assert_eq!(source.nickname, "laptop.home");
+    }
+}
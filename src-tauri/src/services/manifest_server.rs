@@ -4,12 +4,37 @@
 //! This allows Machine B to query Machine A for manifest information, then fetch
 //! the actual data over the P2P network.
 //!
-//! Security: Only whitelisted IPs can access this endpoint.
+//! Security: Only whitelisted IPs can access this endpoint, requests are rate
+//! limited per IP and recorded in the shared request audit log.
+//!
+//! Backup peers report back through `POST /manifests/ack` once they have
+//! processed a manifest. Acknowledgements are timestamped and signed with
+//! HMAC-SHA256 over a shared secret; when a secret is configured, unsigned,
+//! stale or badly signed acks are rejected. Acks are recorded per folder and
+//! per backup peer, and only verified ones count as a completed backup.
+//!
+//! The registry is persisted to `manifest-registry.json` so the latest manifest
+//! per folder (and the acks received for it) survive a restart.
+//!
+//! When `http_fallback_enabled` is set (together with a shared secret), the
+//! server also streams locally stored content over `GET /data/{cid}` for backup
+//! peers whose P2P fetches keep failing. Those requests are signed with the
+//! shared secret and must be fresh, and only advertised manifests and the
+//! files they reference are served.
 
 use crate::error::{ArchivistError, Result};
+use crate::node_api::NodeApiClient;
+use crate::services::backup_daemon::ManifestFile;
+use crate::services::config::{ManifestServerSettings, RateLimitSettings};
+use crate::services::request_guard::{
+    rate_limited_reply, RateLimitedError, RequestAuditLog, RequestGuard, ServerKind,
+};
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use warp::Filter;
@@ -36,34 +61,378 @@ pub struct ManifestDiscoveryResponse {
     pub timestamp: String,
 }
 
+/// Signed acks older (or further in the future) than this are rejected
+const ACK_MAX_AGE_SECS: i64 = 5 * 60;
+
+/// Acknowledgement a backup peer sends after processing a manifest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupAck {
+    pub manifest_cid: String,
+    pub folder_id: String,
+    pub sequence_number: u64,
+    /// Peer ID of the backup node
+    pub backup_peer_id: String,
+    pub files_stored: u32,
+    pub files_failed: u32,
+    pub bytes_stored: u64,
+    pub processed_at: String,
+    /// RFC 3339 time the ack was signed at
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    /// Hex HMAC-SHA256 of the other fields, keyed with the shared ack secret
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+impl BackupAck {
+    /// Canonical string covered by the signature
+    fn signing_payload(&self) -> String {
+        format!(
+            "{}|{}|{}|{}|{}|{}|{}|{}|{}",
+            self.manifest_cid,
+            self.folder_id,
+            self.sequence_number,
+            self.backup_peer_id,
+            self.files_stored,
+            self.files_failed,
+            self.bytes_stored,
+            self.processed_at,
+            self.timestamp.as_deref().unwrap_or_default()
+        )
+    }
+
+    fn mac(secret: &str) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length")
+    }
+
+    /// Timestamp and sign the ack with the shared secret
+    pub fn sign(&mut self, secret: &str) {
+        self.timestamp = Some(chrono::Utc::now().to_rfc3339());
+        let mut mac = Self::mac(secret);
+        mac.update(self.signing_payload().as_bytes());
+        self.signature = Some(hex::encode(mac.finalize().into_bytes()));
+    }
+
+    /// Check the signature against the shared secret (constant time) and
+    /// that it was made recently, so a captured ack can't be replayed later
+    pub fn verify(&self, secret: &str, now: chrono::DateTime<chrono::Utc>) -> bool {
+        let fresh = self
+            .timestamp
+            .as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .is_some_and(|t| {
+                (now - t.with_timezone(&chrono::Utc)).num_seconds().abs() <= ACK_MAX_AGE_SECS
+            });
+        let Some(signature) = self.signature.as_deref().and_then(|s| hex::decode(s).ok()) else {
+            return false;
+        };
+        let mut mac = Self::mac(secret);
+        mac.update(self.signing_payload().as_bytes());
+        fresh && mac.verify_slice(&signature).is_ok()
+    }
+}
+
+/// An acknowledgement as recorded by the source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupAckRecord {
+    pub ack: BackupAck,
+    /// Whether the signature was checked against a configured secret; only
+    /// verified acks mark a folder as backed up
+    pub verified: bool,
+    pub received_at: String,
+}
+
+/// Header carrying the Unix timestamp of a signed content request
+pub const CONTENT_TIMESTAMP_HEADER: &str = "x-archivist-timestamp";
+/// Header carrying the hex HMAC-SHA256 of a signed content request
+pub const CONTENT_SIGNATURE_HEADER: &str = "x-archivist-signature";
+/// Content requests older (or further in the future) than this are rejected
+const CONTENT_REQUEST_MAX_AGE_SECS: i64 = 5 * 60;
+
+fn content_request_mac(secret: &str, cid: &str, timestamp: i64) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(format!("{}|{}", cid, timestamp).as_bytes());
+    mac
+}
+
+/// Sign a request for `GET /data/{cid}` with the shared secret
+pub fn sign_content_request(secret: &str, cid: &str, timestamp: i64) -> String {
+    hex::encode(
+        content_request_mac(secret, cid, timestamp)
+            .finalize()
+            .into_bytes(),
+    )
+}
+
+/// Check a content request's signature (constant time) and that its
+/// timestamp is within the freshness window around `now`
+pub fn verify_content_request(
+    secret: &str,
+    cid: &str,
+    timestamp: i64,
+    signature: &str,
+    now: i64,
+) -> bool {
+    if (now - timestamp).abs() > CONTENT_REQUEST_MAX_AGE_SECS {
+        return false;
+    }
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    content_request_mac(secret, cid, timestamp)
+        .verify_slice(&signature)
+        .is_ok()
+}
+
+/// Manifests kept per folder in the registry's history, so backup peers that
+/// missed sequences can catch up
+const MANIFEST_HISTORY_LIMIT: usize = 50;
+
+/// Response from the manifest history endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestHistoryResponse {
+    pub peer_id: String,
+    pub folder_id: String,
+    /// Manifests newer than the requested sequence, oldest first
+    pub manifests: Vec<ManifestInfo>,
+    /// Oldest sequence still kept for the folder; earlier ones can't be served
+    pub oldest_available_sequence: Option<u64>,
+}
+
+/// Query parameters of the manifest history endpoint
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ManifestHistoryQuery {
+    /// Only return manifests with a higher sequence number
+    pub since: Option<u64>,
+}
+
+/// On-disk format of the manifest registry (stored in manifest-registry.json)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedRegistry {
+    #[serde(default)]
+    peer_id: Option<String>,
+    #[serde(default)]
+    manifests: HashMap<String, ManifestInfo>,
+    #[serde(default)]
+    acks: HashMap<String, HashMap<String, BackupAckRecord>>,
+    #[serde(default)]
+    history: HashMap<String, Vec<ManifestInfo>>,
+}
+
 /// Registry that tracks the latest manifest CID for each folder
 #[derive(Debug, Clone, Default)]
 pub struct ManifestRegistry {
     /// Map of folder_id -> ManifestInfo
-    manifests: std::collections::HashMap<String, ManifestInfo>,
+    manifests: HashMap<String, ManifestInfo>,
     /// This node's peer ID
     peer_id: Option<String>,
+    /// Latest ack per folder_id, then per backup peer ID
+    acks: HashMap<String, HashMap<String, BackupAckRecord>>,
+    /// Recent manifests per folder_id, oldest first (at most
+    /// `MANIFEST_HISTORY_LIMIT` each)
+    history: HashMap<String, Vec<ManifestInfo>>,
+    /// Where the registry is persisted (None = in-memory only)
+    state_file_path: Option<PathBuf>,
 }
 
 impl ManifestRegistry {
-    pub fn new() -> Self {
-        Self::default()
+    /// Create a registry backed by the default state file, loading any
+    /// previously persisted manifests
+    pub fn load_default() -> Self {
+        let path = dirs::data_dir()
+            .map(|p| p.join("archivist").join("manifest-registry.json"))
+            .unwrap_or_else(|| PathBuf::from("manifest-registry.json"));
+        Self::with_state_file(path)
+    }
+
+    /// Create a registry backed by the given state file
+    pub fn with_state_file(path: PathBuf) -> Self {
+        let persisted = match Self::load_state(&path) {
+            Ok(persisted) => persisted,
+            Err(e) => {
+                log::warn!("Failed to load manifest registry, starting empty: {}", e);
+                PersistedRegistry::default()
+            }
+        };
+
+        Self {
+            manifests: persisted.manifests,
+            peer_id: persisted.peer_id,
+            acks: persisted.acks,
+            history: persisted.history,
+            state_file_path: Some(path),
+        }
+    }
+
+    /// Load registry state from disk
+    fn load_state(path: &Path) -> Result<PersistedRegistry> {
+        if !path.exists() {
+            log::info!("No existing manifest registry found, starting fresh");
+            return Ok(PersistedRegistry::default());
+        }
+
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!("Failed to read manifest registry: {}", e))
+        })?;
+
+        let persisted: PersistedRegistry =
+            serde_json::from_str(&contents).map_err(ArchivistError::SerializationError)?;
+
+        log::info!(
+            "Loaded manifest registry: {} folder manifests",
+            persisted.manifests.len()
+        );
+
+        Ok(persisted)
+    }
+
+    /// Save registry state to disk (no-op for in-memory registries)
+    fn save_state(&self) -> Result<()> {
+        let Some(path) = &self.state_file_path else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ArchivistError::FileOperationFailed(format!(
+                    "Failed to create registry directory: {}",
+                    e
+                ))
+            })?;
+        }
+
+        let persisted = PersistedRegistry {
+            peer_id: self.peer_id.clone(),
+            manifests: self.manifests.clone(),
+            acks: self.acks.clone(),
+            history: self.history.clone(),
+        };
+        let json =
+            serde_json::to_string_pretty(&persisted).map_err(ArchivistError::SerializationError)?;
+
+        std::fs::write(path, json).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!("Failed to write manifest registry: {}", e))
+        })?;
+
+        Ok(())
     }
 
     /// Set the peer ID for this node
     pub fn set_peer_id(&mut self, peer_id: String) {
+        if self.peer_id.as_deref() == Some(peer_id.as_str()) {
+            return;
+        }
         self.peer_id = Some(peer_id);
+        if let Err(e) = self.save_state() {
+            log::warn!("Failed to persist manifest registry: {}", e);
+        }
     }
 
     /// Register or update a manifest for a folder
+    ///
+    /// Manifests with a lower sequence number than the one already registered
+    /// for the folder are ignored, so the registry always advertises the latest.
     pub fn register_manifest(&mut self, info: ManifestInfo) {
+        if let Some(existing) = self.manifests.get(&info.folder_id) {
+            if existing.sequence_number > info.sequence_number {
+                log::warn!(
+                    "Ignoring stale manifest for folder {}: seq {} < registered seq {}",
+                    info.folder_id,
+                    info.sequence_number,
+                    existing.sequence_number
+                );
+                return;
+            }
+        }
+
         log::info!(
             "Registering manifest for folder {}: CID={}, seq={}",
             info.folder_id,
             info.manifest_cid,
             info.sequence_number
         );
+        let history = self.history.entry(info.folder_id.clone()).or_default();
+        history.retain(|m| m.sequence_number != info.sequence_number);
+        history.push(info.clone());
+        history.sort_by_key(|m| m.sequence_number);
+        if history.len() > MANIFEST_HISTORY_LIMIT {
+            history.drain(..history.len() - MANIFEST_HISTORY_LIMIT);
+        }
         self.manifests.insert(info.folder_id.clone(), info);
+
+        if let Err(e) = self.save_state() {
+            log::warn!("Failed to persist manifest registry: {}", e);
+        }
+    }
+
+    /// Stop advertising a folder, e.g. after it was removed from sync. Its
+    /// history and acks go with it. Returns false if it wasn't registered.
+    pub fn unregister(&mut self, folder_id: &str) -> bool {
+        let removed = self.manifests.remove(folder_id).is_some();
+        self.history.remove(folder_id);
+        self.acks.remove(folder_id);
+        if !removed {
+            return false;
+        }
+
+        log::info!("Unregistered manifest for folder {}", folder_id);
+        if let Err(e) = self.save_state() {
+            log::warn!("Failed to persist manifest registry: {}", e);
+        }
+        true
+    }
+
+    /// Record a backup peer's acknowledgement. Returns false (and records
+    /// nothing) for folders that are not registered. An ack never replaces a
+    /// newer one from the same backup peer.
+    pub fn record_ack(&mut self, ack: BackupAck, verified: bool) -> bool {
+        if !self.manifests.contains_key(&ack.folder_id) {
+            return false;
+        }
+
+        let peer_acks = self.acks.entry(ack.folder_id.clone()).or_default();
+        if let Some(existing) = peer_acks.get(&ack.backup_peer_id) {
+            if existing.ack.sequence_number > ack.sequence_number {
+                return true;
+            }
+        }
+
+        log::info!(
+            "Backup peer {} acknowledged manifest {} of folder {} (seq {}, {} stored, {} failed{})",
+            ack.backup_peer_id,
+            ack.manifest_cid,
+            ack.folder_id,
+            ack.sequence_number,
+            ack.files_stored,
+            ack.files_failed,
+            if verified { "" } else { ", unverified" }
+        );
+        peer_acks.insert(
+            ack.backup_peer_id.clone(),
+            BackupAckRecord {
+                ack,
+                verified,
+                received_at: chrono::Utc::now().to_rfc3339(),
+            },
+        );
+
+        if let Err(e) = self.save_state() {
+            log::warn!("Failed to persist manifest registry: {}", e);
+        }
+        true
+    }
+
+    /// Acks received for a folder, one per backup peer
+    pub fn get_acks(&self, folder_id: &str) -> Vec<BackupAckRecord> {
+        self.acks
+            .get(folder_id)
+            .map(|peers| peers.values().cloned().collect())
+            .unwrap_or_default()
     }
 
     /// Get all registered manifests
@@ -77,6 +446,66 @@ impl ManifestRegistry {
         self.manifests.get(folder_id).cloned()
     }
 
+    /// Manifests of a folder newer than `since`, oldest first. None for
+    /// folders that aren't registered.
+    pub fn get_history(&self, folder_id: &str, since: Option<u64>) -> Option<Vec<ManifestInfo>> {
+        if !self.manifests.contains_key(folder_id) {
+            return None;
+        }
+        let history = self
+            .history
+            .get(folder_id)
+            .map(Vec::as_slice)
+            .unwrap_or(&[]);
+        Some(
+            history
+                .iter()
+                .filter(|m| since.map_or(true, |since| m.sequence_number > since))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// History response for a folder (None for folders that aren't registered)
+    pub fn get_history_response(
+        &self,
+        folder_id: &str,
+        since: Option<u64>,
+    ) -> Option<ManifestHistoryResponse> {
+        let manifests = self.get_history(folder_id, since)?;
+        Some(ManifestHistoryResponse {
+            peer_id: self
+                .peer_id
+                .clone()
+                .unwrap_or_else(|| "unknown".to_string()),
+            folder_id: folder_id.to_string(),
+            manifests,
+            oldest_available_sequence: self
+                .history
+                .get(folder_id)
+                .and_then(|h| h.first())
+                .map(|m| m.sequence_number),
+        })
+    }
+
+    /// Find the registered manifest for a folder path
+    pub fn find_by_path(&self, folder_path: &str) -> Option<ManifestInfo> {
+        self.manifests
+            .values()
+            .filter(|m| m.folder_path == folder_path)
+            .max_by_key(|m| m.sequence_number)
+            .cloned()
+    }
+
+    /// CIDs of every advertised manifest, current and in the history
+    pub fn published_manifest_cids(&self) -> HashSet<String> {
+        self.manifests
+            .values()
+            .chain(self.history.values().flatten())
+            .map(|m| m.manifest_cid.clone())
+            .collect()
+    }
+
     /// Get the discovery response
     pub fn get_discovery_response(&self) -> ManifestDiscoveryResponse {
         ManifestDiscoveryResponse {
@@ -99,6 +528,14 @@ pub struct ManifestServerConfig {
     pub enabled: bool,
     /// Whitelisted IP addresses that can access the API
     pub allowed_ips: HashSet<IpAddr>,
+    /// Per-IP rate limit
+    pub rate_limit: RateLimitSettings,
+    /// Per-IP rate limit for content served over `GET /data/{cid}`
+    pub content_rate_limit: RateLimitSettings,
+    /// Shared secret backup acks must be signed with (None = accept unsigned)
+    pub ack_secret: Option<String>,
+    /// Serve stored content over `GET /data/{cid}` (needs `ack_secret`)
+    pub http_fallback_enabled: bool,
 }
 
 impl Default for ManifestServerConfig {
@@ -107,14 +544,80 @@ impl Default for ManifestServerConfig {
             port: 8085,
             enabled: false,
             allowed_ips: HashSet::new(),
+            rate_limit: ManifestServerSettings::default().rate_limit,
+            content_rate_limit: ManifestServerSettings::default().content_rate_limit,
+            ack_secret: None,
+            http_fallback_enabled: false,
         }
     }
 }
 
+/// File CIDs of published manifests, keyed by manifest CID, so the HTTP
+/// fallback only serves content this node advertises
+#[derive(Default)]
+struct PublishedContent {
+    files: RwLock<HashMap<String, HashSet<String>>>,
+}
+
+impl PublishedContent {
+    /// Whether `cid` is a published manifest or a file one references.
+    /// Manifests are read from the local node once and then cached.
+    async fn contains(
+        &self,
+        cid: &str,
+        registry: &RwLock<ManifestRegistry>,
+        api_client: &NodeApiClient,
+    ) -> bool {
+        let published = registry.read().await.published_manifest_cids();
+        if published.contains(cid) {
+            return true;
+        }
+
+        let missing: Vec<String> = {
+            let mut files = self.files.write().await;
+            files.retain(|manifest_cid, _| published.contains(manifest_cid));
+            published
+                .iter()
+                .filter(|manifest_cid| !files.contains_key(*manifest_cid))
+                .cloned()
+                .collect()
+        };
+        for manifest_cid in missing {
+            match load_manifest_files(api_client, &manifest_cid).await {
+                Ok(cids) => {
+                    self.files.write().await.insert(manifest_cid, cids);
+                }
+                Err(e) => log::warn!("Failed to read published manifest {}: {}", manifest_cid, e),
+            }
+        }
+
+        self.files
+            .read()
+            .await
+            .values()
+            .any(|cids| cids.contains(cid))
+    }
+}
+
+/// File CIDs of a manifest stored on the local node
+async fn load_manifest_files(
+    api_client: &NodeApiClient,
+    manifest_cid: &str,
+) -> Result<HashSet<String>> {
+    let bytes = api_client.download_file(manifest_cid).await?;
+    let manifest: ManifestFile = serde_json::from_slice(&bytes)?;
+    Ok(manifest.files.into_iter().map(|f| f.cid).collect())
+}
+
 /// Manifest Discovery Server
 pub struct ManifestServer {
     registry: Arc<RwLock<ManifestRegistry>>,
     config: Arc<RwLock<ManifestServerConfig>>,
+    audit_log: Arc<RequestAuditLog>,
+    /// Local node the HTTP fallback streams content from
+    api_client: Option<NodeApiClient>,
+    /// What the HTTP fallback may serve
+    published: Arc<PublishedContent>,
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
 }
 
@@ -124,6 +627,9 @@ impl ManifestServer {
         Self {
             registry,
             config: Arc::new(RwLock::new(ManifestServerConfig::default())),
+            audit_log: Arc::new(RequestAuditLog::in_memory(100)),
+            api_client: None,
+            published: Arc::new(PublishedContent::default()),
             shutdown_tx: None,
         }
     }
@@ -132,10 +638,15 @@ impl ManifestServer {
     pub fn with_config(
         registry: Arc<RwLock<ManifestRegistry>>,
         config: ManifestServerConfig,
+        audit_log: Arc<RequestAuditLog>,
+        api_client: NodeApiClient,
     ) -> Self {
         Self {
             registry,
             config: Arc::new(RwLock::new(config)),
+            audit_log,
+            api_client: Some(api_client),
+            published: Arc::new(PublishedContent::default()),
             shutdown_tx: None,
         }
     }
@@ -195,10 +706,27 @@ impl ManifestServer {
             return Ok(());
         }
         let port = config.port;
+        let guard = RequestGuard::new(
+            ServerKind::ManifestServer,
+            &config.rate_limit,
+            self.audit_log.clone(),
+        );
+        let content_guard = RequestGuard::new(
+            ServerKind::ManifestServer,
+            &config.content_rate_limit,
+            self.audit_log.clone(),
+        );
         drop(config);
 
         let registry = self.registry.clone();
+        let registry_for_ack = self.registry.clone();
+        let registry_for_history = self.registry.clone();
         let config_for_filter = self.config.clone();
+        let config_for_ack = self.config.clone();
+        let config_for_content = self.config.clone();
+        let registry_for_content = self.registry.clone();
+        let published = self.published.clone();
+        let api_client = self.api_client.clone();
 
         // Create IP whitelist filter
         let ip_filter =
@@ -226,21 +754,69 @@ impl ManifestServer {
                 )
                 .untuple_one();
 
+        // Routes below are relative to their branch prefix ("/manifests" or
+        // "/data"), so a request is only ever checked by one rate limiter
+
+        // POST /manifests/ack - Backup peer acknowledges a processed manifest
+        let ack_route = warp::path!("ack")
+            .and(warp::post())
+            .and(ip_filter.clone())
+            .and(warp::body::content_length_limit(16 * 1024))
+            .and(warp::body::json())
+            .and(warp::any().map(move || registry_for_ack.clone()))
+            .and(warp::any().map(move || config_for_ack.clone()))
+            .and_then(handle_backup_ack);
+
+        // GET /manifests/{folder_id}/history?since=N - Recent manifests of a
+        // folder, for backup peers filling a sequence gap
+        let history_route = warp::path!(String / "history")
+            .and(warp::get())
+            .and(ip_filter.clone())
+            .and(warp::query::<ManifestHistoryQuery>())
+            .and(warp::any().map(move || registry_for_history.clone()))
+            .and_then(handle_get_manifest_history);
+
+        // GET /data/{cid} - Stream published content to a backup peer whose
+        // P2P fetches fail (signed requests only), under its own rate limit
+        let content_route = warp::path!(String)
+            .and(warp::get())
+            .and(ip_filter.clone())
+            .and(content_guard.rate_limit())
+            .and(warp::header::optional::<String>(CONTENT_TIMESTAMP_HEADER))
+            .and(warp::header::optional::<String>(CONTENT_SIGNATURE_HEADER))
+            .and(warp::any().map(move || config_for_content.clone()))
+            .and(warp::any().map(move || registry_for_content.clone()))
+            .and(warp::any().map(move || published.clone()))
+            .and(warp::any().map(move || api_client.clone()))
+            .and_then(handle_get_content);
+
         // GET /manifests - Get all manifest CIDs
-        let manifests_route = warp::path("manifests")
+        let manifests_route = warp::path::end()
             .and(warp::get())
             .and(ip_filter.clone())
             .and(warp::any().map(move || registry.clone()))
             .and_then(handle_get_manifests);
 
-        // Health check (no auth required)
-        let health_route = warp::path("health")
+        // Health check (no auth, not rate limited)
+        let health_route = warp::path!("health")
             .and(warp::get())
             .map(|| warp::reply::json(&serde_json::json!({"status": "ok"})));
 
-        let routes = manifests_route
-            .or(health_route)
+        // Each branch recovers its own rejections, so a rejected request
+        // doesn't fall through to the other branch's limiter
+        let content_branch = warp::path("data").and(content_route.recover(handle_rejection));
+        let manifests_branch = warp::path("manifests").and(
+            guard
+                .rate_limit()
+                .and(ack_route.or(history_route).or(manifests_route))
+                .recover(handle_rejection),
+        );
+
+        let routes = health_route
+            .or(content_branch)
+            .or(manifests_branch)
             .recover(handle_rejection)
+            .with(guard.audit())
             .with(warp::log("manifest_server"));
 
         // Create shutdown channel
@@ -274,11 +850,29 @@ impl ManifestServer {
 struct UnauthorizedError;
 impl warp::reject::Reject for UnauthorizedError {}
 
+// Custom rejection for acks and content requests with a missing or wrong
+// signature
+#[derive(Debug)]
+struct InvalidSignatureError;
+impl warp::reject::Reject for InvalidSignatureError {}
+
+// Custom rejection for acks about folders this node doesn't advertise
+#[derive(Debug)]
+struct UnknownFolderError;
+impl warp::reject::Reject for UnknownFolderError {}
+
+// Custom rejection for content the local node can't serve
+#[derive(Debug)]
+struct ContentUnavailableError;
+impl warp::reject::Reject for ContentUnavailableError {}
+
 /// Handle rejections and return proper HTTP status codes
 async fn handle_rejection(
     err: warp::Rejection,
 ) -> std::result::Result<impl warp::Reply, std::convert::Infallible> {
-    if err.find::<UnauthorizedError>().is_some() {
+    if err.find::<RateLimitedError>().is_some() {
+        Ok(rate_limited_reply())
+    } else if err.find::<UnauthorizedError>().is_some() {
         Ok(warp::reply::with_status(
             warp::reply::json(&serde_json::json!({
                 "error": "Forbidden",
@@ -286,7 +880,15 @@ async fn handle_rejection(
             })),
             warp::http::StatusCode::FORBIDDEN,
         ))
-    } else if err.is_not_found() {
+    } else if err.find::<InvalidSignatureError>().is_some() {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "error": "Forbidden",
+                "message": "Request signature is missing or invalid"
+            })),
+            warp::http::StatusCode::FORBIDDEN,
+        ))
+    } else if err.find::<UnknownFolderError>().is_some() || err.is_not_found() {
         Ok(warp::reply::with_status(
             warp::reply::json(&serde_json::json!({
                 "error": "Not Found",
@@ -294,6 +896,22 @@ async fn handle_rejection(
             })),
             warp::http::StatusCode::NOT_FOUND,
         ))
+    } else if err.find::<ContentUnavailableError>().is_some() {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "error": "Not Found",
+                "message": "The requested content is not stored on this node"
+            })),
+            warp::http::StatusCode::NOT_FOUND,
+        ))
+    } else if err.find::<warp::body::BodyDeserializeError>().is_some() {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({
+                "error": "Bad Request",
+                "message": "Invalid request body"
+            })),
+            warp::http::StatusCode::BAD_REQUEST,
+        ))
     } else {
         Ok(warp::reply::with_status(
             warp::reply::json(&serde_json::json!({
@@ -313,7 +931,125 @@ async fn handle_get_manifests(
     Ok(warp::reply::json(&response))
 }
 
+async fn handle_get_manifest_history(
+    folder_id: String,
+    query: ManifestHistoryQuery,
+    registry: Arc<RwLock<ManifestRegistry>>,
+) -> std::result::Result<impl warp::Reply, warp::Rejection> {
+    let reg = registry.read().await;
+    match reg.get_history_response(&folder_id, query.since) {
+        Some(response) => Ok(warp::reply::json(&response)),
+        None => Err(warp::reject::custom(UnknownFolderError)),
+    }
+}
+
+async fn handle_get_content(
+    cid: String,
+    timestamp: Option<String>,
+    signature: Option<String>,
+    config: Arc<RwLock<ManifestServerConfig>>,
+    registry: Arc<RwLock<ManifestRegistry>>,
+    published: Arc<PublishedContent>,
+    api_client: Option<NodeApiClient>,
+) -> std::result::Result<impl warp::Reply, warp::Rejection> {
+    // The endpoint doesn't exist unless explicitly enabled with a secret
+    let secret = {
+        let cfg = config.read().await;
+        match (&cfg.ack_secret, cfg.http_fallback_enabled) {
+            (Some(secret), true) => secret.clone(),
+            _ => return Err(warp::reject::not_found()),
+        }
+    };
+    let Some(api_client) = api_client else {
+        return Err(warp::reject::not_found());
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    let timestamp = timestamp.and_then(|t| t.parse::<i64>().ok());
+    let authorized = match (timestamp, signature.as_deref()) {
+        (Some(timestamp), Some(signature)) => {
+            verify_content_request(&secret, &cid, timestamp, signature, now)
+        }
+        _ => false,
+    };
+    if !authorized {
+        log::warn!(
+            "Rejected content request for {}: bad or stale signature",
+            cid
+        );
+        return Err(warp::reject::custom(InvalidSignatureError));
+    }
+
+    // Only content of advertised folders, not everything the node stores
+    if !published.contains(&cid, &registry, &api_client).await {
+        log::warn!("Rejected content request for {}: not published", cid);
+        return Err(warp::reject::custom(ContentUnavailableError));
+    }
+
+    let response = api_client.open_local_stream(&cid).await.map_err(|e| {
+        log::warn!("Content request for {} failed: {}", cid, e);
+        warp::reject::custom(ContentUnavailableError)
+    })?;
+
+    let mut builder = warp::http::Response::builder().header(
+        warp::http::header::CONTENT_TYPE,
+        response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("application/octet-stream")
+            .to_string(),
+    );
+    if let Some(disposition) = response
+        .headers()
+        .get(reqwest::header::CONTENT_DISPOSITION)
+        .and_then(|v| v.to_str().ok())
+    {
+        builder = builder.header(
+            warp::http::header::CONTENT_DISPOSITION,
+            disposition.to_string(),
+        );
+    }
+    if let Some(len) = response.content_length() {
+        builder = builder.header(warp::http::header::CONTENT_LENGTH, len);
+    }
+
+    log::info!("Serving {} over HTTP fallback", cid);
+    builder
+        .body(warp::hyper::Body::wrap_stream(response.bytes_stream()))
+        .map_err(|_| warp::reject::custom(ContentUnavailableError))
+}
+
+async fn handle_backup_ack(
+    ack: BackupAck,
+    registry: Arc<RwLock<ManifestRegistry>>,
+    config: Arc<RwLock<ManifestServerConfig>>,
+) -> std::result::Result<impl warp::Reply, warp::Rejection> {
+    let verified = match &config.read().await.ack_secret {
+        Some(secret) => {
+            if !ack.verify(secret, chrono::Utc::now()) {
+                log::warn!(
+                    "Rejected ack from backup peer {} for folder {}: missing, stale or bad signature",
+                    ack.backup_peer_id,
+                    ack.folder_id
+                );
+                return Err(warp::reject::custom(InvalidSignatureError));
+            }
+            true
+        }
+        None => false,
+    };
+
+    if !registry.write().await.record_ack(ack, verified) {
+        return Err(warp::reject::custom(UnknownFolderError));
+    }
+    Ok(warp::reply::json(
+        &serde_json::json!({"status": "recorded"}),
+    ))
+}
+
 /// Client for querying a remote manifest server
+#[derive(Clone)]
 pub struct ManifestClient {
     client: reqwest::Client,
 }
@@ -357,6 +1093,103 @@ impl ManifestClient {
                 ArchivistError::ApiError(format!("Failed to parse manifest response: {}", e))
             })
     }
+
+    /// Fetch a folder's manifests newer than `since` from a remote peer's
+    /// manifest server
+    pub async fn fetch_manifest_history(
+        &self,
+        host: &str,
+        port: u16,
+        folder_id: &str,
+        since: u64,
+    ) -> Result<ManifestHistoryResponse> {
+        let mut url = reqwest::Url::parse(&format!("http://{}:{}/", host, port))
+            .map_err(|e| ArchivistError::ApiError(format!("Invalid manifest server URL: {}", e)))?;
+        url.path_segments_mut()
+            .map_err(|_| ArchivistError::ApiError("Invalid manifest server URL".into()))?
+            .extend(["manifests", folder_id, "history"]);
+        url.query_pairs_mut()
+            .append_pair("since", &since.to_string());
+
+        log::info!("Fetching manifest history from {}", url);
+
+        let response = self.client.get(url).send().await.map_err(|e| {
+            ArchivistError::ApiError(format!("Failed to fetch manifest history: {}", e))
+        })?;
+
+        if !response.status().is_success() {
+            return Err(ArchivistError::ApiError(format!(
+                "Manifest server returned error: HTTP {}",
+                response.status()
+            )));
+        }
+
+        response
+            .json::<ManifestHistoryResponse>()
+            .await
+            .map_err(|e| {
+                ArchivistError::ApiError(format!("Failed to parse manifest history: {}", e))
+            })
+    }
+
+    /// Open a signed content request against a source peer's HTTP fallback
+    /// endpoint; the caller streams the body from the returned response
+    pub async fn fetch_content(
+        &self,
+        host: &str,
+        port: u16,
+        cid: &str,
+        secret: &str,
+    ) -> Result<reqwest::Response> {
+        let url = format!("http://{}:{}/data/{}", host, port, cid);
+        let timestamp = chrono::Utc::now().timestamp();
+
+        log::info!("Fetching {} over HTTP fallback from {}:{}", cid, host, port);
+
+        let response = self
+            .client
+            .get(&url)
+            .header(CONTENT_TIMESTAMP_HEADER, timestamp)
+            .header(
+                CONTENT_SIGNATURE_HEADER,
+                sign_content_request(secret, cid, timestamp),
+            )
+            .timeout(std::time::Duration::from_secs(3600))
+            .send()
+            .await
+            .map_err(|e| ArchivistError::ApiError(format!("Failed to fetch content: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(ArchivistError::ApiError(format!(
+                "Manifest server returned error: HTTP {}",
+                response.status()
+            )));
+        }
+
+        Ok(response)
+    }
+
+    /// Send a backup acknowledgement to a source peer's manifest server
+    pub async fn send_ack(&self, host: &str, port: u16, ack: &BackupAck) -> Result<()> {
+        let url = format!("http://{}:{}/manifests/ack", host, port);
+
+        let response = self
+            .client
+            .post(&url)
+            .json(ack)
+            .send()
+            .await
+            .map_err(|e| ArchivistError::ApiError(format!("Failed to send ack: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(ArchivistError::ApiError(format!(
+                "Manifest server rejected ack: HTTP {}",
+                response.status()
+            )));
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for ManifestClient {
@@ -364,3 +1197,196 @@ impl Default for ManifestClient {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(folder_id: &str, seq: u64) -> ManifestInfo {
+        ManifestInfo {
+            folder_id: folder_id.to_string(),
+            folder_path: format!("/data/{}", folder_id),
+            manifest_cid: format!("zCid{}", seq),
+            sequence_number: seq,
+            updated_at: chrono::Utc::now().to_rfc3339(),
+            file_count: 1,
+            total_size_bytes: 10,
+        }
+    }
+
+    #[test]
+    fn test_registry_survives_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest-registry.json");
+
+        let mut registry = ManifestRegistry::with_state_file(path.clone());
+        registry.set_peer_id("16Uiu2HAmTest".to_string());
+        registry.register_manifest(manifest("photos", 3));
+
+        let reloaded = ManifestRegistry::with_state_file(path);
+        let response = reloaded.get_discovery_response();
+        assert_eq!(response.peer_id, "16Uiu2HAmTest");
+        assert_eq!(response.manifests.len(), 1);
+        assert_eq!(response.manifests[0].sequence_number, 3);
+    }
+
+    #[test]
+    fn test_unregister_persists_removal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest-registry.json");
+
+        let mut registry = ManifestRegistry::with_state_file(path.clone());
+        registry.register_manifest(manifest("photos", 3));
+        registry.register_manifest(manifest("docs", 1));
+
+        assert!(registry.unregister("photos"));
+        assert!(!registry.unregister("photos"));
+        assert!(registry.get_history("photos", None).is_none());
+
+        let reloaded = ManifestRegistry::with_state_file(path);
+        let response = reloaded.get_discovery_response();
+        assert_eq!(response.manifests.len(), 1);
+        assert_eq!(response.manifests[0].folder_id, "docs");
+    }
+
+    fn ack(seq: u64) -> BackupAck {
+        BackupAck {
+            manifest_cid: format!("zCid{}", seq),
+            folder_id: "photos".to_string(),
+            sequence_number: seq,
+            backup_peer_id: "16Uiu2HAmBackup".to_string(),
+            files_stored: 12,
+            files_failed: 0,
+            bytes_stored: 4096,
+            processed_at: chrono::Utc::now().to_rfc3339(),
+            timestamp: None,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_backup_ack_signature() {
+        let mut signed = ack(3);
+        signed.sign("shared-secret");
+        let now = chrono::Utc::now();
+        assert!(signed.verify("shared-secret", now));
+        assert!(!signed.verify("other-secret", now));
+        // Replayed long after it was signed
+        assert!(!signed.verify("shared-secret", now + chrono::Duration::minutes(10)));
+
+        let mut tampered = signed.clone();
+        tampered.files_failed = 5;
+        assert!(!tampered.verify("shared-secret", now));
+
+        assert!(!ack(3).verify("shared-secret", now));
+    }
+
+    #[test]
+    fn test_content_request_signature() {
+        let now = 1_700_000_000;
+        let signature = sign_content_request("shared-secret", "zFile", now);
+        assert!(verify_content_request(
+            "shared-secret",
+            "zFile",
+            now,
+            &signature,
+            now + 30
+        ));
+        assert!(!verify_content_request(
+            "other-secret",
+            "zFile",
+            now,
+            &signature,
+            now
+        ));
+        // Signed for a different CID or timestamp
+        assert!(!verify_content_request(
+            "shared-secret",
+            "zOther",
+            now,
+            &signature,
+            now
+        ));
+        assert!(!verify_content_request(
+            "shared-secret",
+            "zFile",
+            now + 1,
+            &signature,
+            now
+        ));
+        // Replayed outside the freshness window
+        assert!(!verify_content_request(
+            "shared-secret",
+            "zFile",
+            now,
+            &signature,
+            now + CONTENT_REQUEST_MAX_AGE_SECS + 1
+        ));
+        assert!(!verify_content_request(
+            "shared-secret",
+            "zFile",
+            now,
+            "not-hex",
+            now
+        ));
+    }
+
+    #[test]
+    fn test_record_ack_per_folder_and_peer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest-registry.json");
+
+        let mut registry = ManifestRegistry::with_state_file(path.clone());
+        assert!(!registry.record_ack(ack(3), true));
+
+        registry.register_manifest(manifest("photos", 3));
+        assert!(registry.record_ack(ack(3), true));
+        // An older ack doesn't replace a newer one
+        assert!(registry.record_ack(ack(2), true));
+
+        let reloaded = ManifestRegistry::with_state_file(path);
+        let acks = reloaded.get_acks("photos");
+        assert_eq!(acks.len(), 1);
+        assert_eq!(acks[0].ack.sequence_number, 3);
+        assert!(acks[0].verified);
+    }
+
+    #[test]
+    fn test_history_keeps_recent_sequences() {
+        let mut registry = ManifestRegistry::default();
+        for seq in 1..=(MANIFEST_HISTORY_LIMIT as u64 + 5) {
+            registry.register_manifest(manifest("photos", seq));
+        }
+
+        let response = registry.get_history_response("photos", Some(52)).unwrap();
+        let seqs: Vec<u64> = response
+            .manifests
+            .iter()
+            .map(|m| m.sequence_number)
+            .collect();
+        assert_eq!(seqs, vec![53, 54, 55]);
+        assert_eq!(response.oldest_available_sequence, Some(6));
+        assert_eq!(
+            registry.get_history("photos", None).unwrap().len(),
+            MANIFEST_HISTORY_LIMIT
+        );
+        assert!(registry.get_history("unknown", None).is_none());
+
+        // Only advertised manifests may be served over the HTTP fallback
+        let published = registry.published_manifest_cids();
+        assert_eq!(published.len(), MANIFEST_HISTORY_LIMIT);
+        assert!(published.contains("zCid55") && published.contains("zCid6"));
+        assert!(!published.contains("zCid5"));
+    }
+
+    #[test]
+    fn test_register_ignores_stale_sequence() {
+        let mut registry = ManifestRegistry::default();
+        registry.register_manifest(manifest("photos", 5));
+        registry.register_manifest(manifest("photos", 4));
+
+        let info = registry.get_manifest("photos").unwrap();
+        assert_eq!(info.sequence_number, 5);
+        assert_eq!(info.manifest_cid, "zCid5");
+    }
+}
@@ -2,10 +2,15 @@
 //!
 //! HTTP server that streams downloaded media files with range request support.
 //! Used by both the local video player (webview) and mobile browser clients on the LAN.
-//! Follows the ManifestServer pattern: warp 0.3, graceful shutdown, CORS.
+//! Follows the ManifestServer pattern: warp 0.3, graceful shutdown, CORS,
+//! per-IP rate limiting and request auditing.
 
 use crate::error::{ArchivistError, Result};
+use crate::services::config::{MediaStreamingSettings, RateLimitSettings};
 use crate::services::media_download::{DownloadTask, MediaDownloadService};
+use crate::services::request_guard::{
+    rate_limited_reply, RateLimitedError, RequestAuditLog, RequestGuard, ServerKind,
+};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -15,11 +20,15 @@ use warp::Filter;
 #[derive(Debug, Clone)]
 pub struct MediaStreamingConfig {
     pub port: u16,
+    pub rate_limit: RateLimitSettings,
 }
 
 impl Default for MediaStreamingConfig {
     fn default() -> Self {
-        Self { port: 8087 }
+        Self {
+            port: 8087,
+            rate_limit: MediaStreamingSettings::default().rate_limit,
+        }
     }
 }
 
@@ -48,6 +57,7 @@ pub struct LibraryResponse {
 /// Media Streaming Server
 pub struct MediaStreamingServer {
     media_download: Arc<RwLock<MediaDownloadService>>,
+    guard: RequestGuard,
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
     running: bool,
     port: u16,
@@ -57,9 +67,11 @@ impl MediaStreamingServer {
     pub fn new(
         config: MediaStreamingConfig,
         media_download: Arc<RwLock<MediaDownloadService>>,
+        audit_log: Arc<RequestAuditLog>,
     ) -> Self {
         Self {
             media_download,
+            guard: RequestGuard::new(ServerKind::MediaStreaming, &config.rate_limit, audit_log),
             shutdown_tx: None,
             running: false,
             port: config.port,
@@ -128,12 +140,18 @@ impl MediaStreamingServer {
             .and(warp::any().map(move || media_for_stream.clone()))
             .and_then(handle_stream);
 
-        let routes = health_route
-            .or(library_route)
-            .or(stream_route)
-            .or(info_route)
+        let routes = self
+            .guard
+            .rate_limit()
+            .and(
+                health_route
+                    .or(library_route)
+                    .or(stream_route)
+                    .or(info_route),
+            )
             .recover(handle_rejection)
             .with(cors)
+            .with(self.guard.audit())
             .with(warp::log("media_streaming"));
 
         // Create shutdown channel
@@ -341,7 +359,9 @@ fn parse_range(range: &str, file_size: u64) -> std::result::Result<(u64, u64), A
 async fn handle_rejection(
     err: warp::Rejection,
 ) -> std::result::Result<impl warp::Reply, std::convert::Infallible> {
-    if err.is_not_found() {
+    if err.find::<RateLimitedError>().is_some() {
+        Ok(rate_limited_reply())
+    } else if err.is_not_found() {
         Ok(warp::reply::with_status(
             warp::reply::json(&serde_json::json!({
                 "error": "Not Found",
@@ -2,25 +2,37 @@
 
 pub mod backup;
 pub mod backup_daemon;
+pub mod backup_history;
+pub mod backup_lag;
 pub mod binary_manager;
 pub mod config;
+pub mod discovery;
 pub mod files;
 pub mod manifest_server;
 pub mod media_download;
 pub mod media_streaming;
 pub mod node;
+pub mod node_launch;
+pub mod node_logs;
 pub mod peers;
+pub mod request_guard;
+pub mod restore;
+pub mod scrubber;
 pub mod sync;
 
 pub use backup::BackupService;
 pub use backup_daemon::BackupDaemon;
 pub use config::ConfigService;
+pub use discovery::DiscoveryService;
 pub use files::FileService;
 pub use manifest_server::{ManifestRegistry, ManifestServer, ManifestServerConfig};
 pub use media_download::MediaDownloadService;
 pub use media_streaming::{MediaStreamingConfig, MediaStreamingServer};
 pub use node::NodeService;
 pub use peers::PeerService;
+pub use request_guard::RequestAuditLog;
+pub use restore::RestoreService;
+pub use scrubber::IntegrityScrubber;
 pub use sync::SyncService;
 
 // V2 Marketplace services (conditionally compiled)
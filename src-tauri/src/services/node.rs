@@ -1,11 +1,18 @@
 use crate::error::{ArchivistError, Result};
+use crate::node_api::NodeApiClient;
+use crate::services::node_launch::NodeLaunchOptions;
+use crate::services::node_logs::{log_level_spec, NodeLogLevel, NodeLogStream, RotatingLogWriter};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 use tauri_plugin_shell::process::{CommandChild, CommandEvent};
 use tauri_plugin_shell::ShellExt;
-use tokio::sync::{broadcast, mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, watch, RwLock};
+
+/// How long to wait for the node to go away after a force-kill
+const KILL_WAIT: Duration = Duration::from_secs(5);
 
 /// Node running status
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
@@ -74,6 +81,21 @@ pub struct NodeConfig {
     pub max_restart_attempts: u32,
     pub health_check_interval_secs: u64,
     pub log_level: String, // Log level: TRACE, DEBUG, INFO, NOTICE, WARN, ERROR, FATAL
+    /// Per-topic overrides of `log_level`
+    #[serde(default)]
+    pub log_topics: BTreeMap<String, NodeLogLevel>,
+    /// Seconds to wait for a clean exit before the node is killed
+    #[serde(default = "crate::services::config::default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+    /// Size at which node.log is rotated (0 disables rotation)
+    #[serde(default = "crate::services::config::default_log_max_size_mb")]
+    pub log_max_size_mb: u64,
+    /// Rotated node.log files kept besides the current one
+    #[serde(default = "crate::services::config::default_log_max_files")]
+    pub log_max_files: u32,
+    /// NAT, bootstrap, announce and other archivist-node flags
+    #[serde(default)]
+    pub launch: NodeLaunchOptions,
 }
 
 impl Default for NodeConfig {
@@ -95,6 +117,11 @@ impl Default for NodeConfig {
             max_restart_attempts: 3,
             health_check_interval_secs: 30,
             log_level: "DEBUG".to_string(), // Good balance for debugging
+            log_topics: BTreeMap::new(),
+            shutdown_grace_secs: crate::services::config::default_shutdown_grace_secs(),
+            log_max_size_mb: crate::services::config::default_log_max_size_mb(),
+            log_max_files: crate::services::config::default_log_max_files(),
+            launch: NodeLaunchOptions::default(),
         }
     }
 }
@@ -113,6 +140,11 @@ impl NodeConfig {
             max_restart_attempts: 3,
             health_check_interval_secs: 30,
             log_level: settings.log_level.clone(),
+            log_topics: settings.log_topics.clone(),
+            shutdown_grace_secs: settings.shutdown_grace_secs,
+            log_max_size_mb: settings.log_max_size_mb,
+            log_max_files: settings.log_max_files,
+            launch: settings.launch.clone(),
         }
     }
 }
@@ -144,6 +176,19 @@ struct NodeProcessState {
     child: Option<CommandChild>,
     start_time: Option<Instant>,
     restart_count: u32,
+    /// Becomes true (or closes) once the sidecar has terminated
+    exited: watch::Receiver<bool>,
+}
+
+/// How a running node was brought down
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShutdownOutcome {
+    /// Exited on its own within the grace period
+    Graceful,
+    /// Had to be killed after the grace period
+    Killed,
+    /// Killed right away because the platform can't ask it to exit
+    Terminated,
 }
 
 /// Node service that manages the archivist-node sidecar
@@ -156,6 +201,8 @@ pub struct NodeService {
     public_ip_cache: Option<String>,
     /// Last time public IP was fetched
     public_ip_cache_time: Option<Instant>,
+    /// Forwards node output to a subscribed logs view
+    log_stream: NodeLogStream,
 }
 
 impl NodeService {
@@ -172,6 +219,7 @@ impl NodeService {
             shutdown_tx: None,
             public_ip_cache: None,
             public_ip_cache_time: None,
+            log_stream: NodeLogStream::default(),
         }
     }
 
@@ -186,6 +234,17 @@ impl NodeService {
             return Err(ArchivistError::NodeAlreadyRunning);
         }
 
+        // Reject bad launch options before the node counts as starting
+        let launch_error = |e: ArchivistError| {
+            ArchivistError::NodeStartFailed(format!("Invalid launch options: {}", e))
+        };
+        let listen_addr = self
+            .config
+            .launch
+            .listen_addr(self.config.listen_port)
+            .map_err(launch_error)?;
+        let launch_args = self.config.launch.args().map_err(launch_error)?;
+
         self.status.state = NodeState::Starting;
         self.status.last_error = None;
         log::info!("Starting Archivist node...");
@@ -206,11 +265,11 @@ impl NodeService {
         // Use separate ports for discovery (UDP) and listening (TCP)
         // - discovery_port: UDP port for DHT/mDNS peer discovery (default: 8090)
         // - listen_port: TCP port for actual P2P connections (default: 8070)
-        // Enable UPnP for automatic port forwarding on supported routers
-        let listen_addr = format!("/ip4/0.0.0.0/tcp/{}", self.config.listen_port);
+        // NAT strategy (UPnP by default), bootstrap nodes, announce addresses
+        // and extra flags come from the launch options checked above
 
         // Set up log file path (inside data_dir)
-        let log_file = std::path::Path::new(&self.config.data_dir).join("node.log");
+        let log_file = crate::services::node_logs::node_log_path(&self.config.data_dir);
         let log_file_str = log_file.to_string_lossy().to_string();
 
         log::info!("Archivist node logs will be written to: {}", log_file_str);
@@ -225,8 +284,10 @@ impl NodeService {
                 &format!("--disc-port={}", self.config.discovery_port),
                 &format!("--listen-addrs={}", listen_addr),
                 &format!("--storage-quota={}", self.config.max_storage_bytes),
-                "--nat=upnp",
-            ]);
+            ])
+            .args(&launch_args);
+
+        log::info!("Archivist node launch options: {}", launch_args.join(" "));
 
         // Spawn the sidecar process
         let (mut rx, child) = sidecar_command.spawn().map_err(|e| {
@@ -240,11 +301,13 @@ impl NodeService {
         self.status.pid = Some(pid);
         self.status.api_url = Some(format!("http://127.0.0.1:{}", self.config.api_port));
 
-        // Store process state
+        // Store process state; the event task reports when the process exits
+        let (exit_tx, exit_rx) = watch::channel(false);
         self.process_state = Some(NodeProcessState {
             child: Some(child),
             start_time: Some(Instant::now()),
             restart_count: self.status.restart_count,
+            exited: exit_rx,
         });
 
         // Create shutdown channel for the monitor task
@@ -283,21 +346,18 @@ impl NodeService {
             self.status.state = NodeState::Running;
 
             // Set log level via API
-            let log_level_url = format!(
-                "http://127.0.0.1:{}/api/archivist/v1/debug/chronicles/loglevel?level={}",
-                self.config.api_port, self.config.log_level
-            );
-            log::info!("Setting node log level to: {}", self.config.log_level);
-            match client.post(&log_level_url).send().await {
-                Ok(response) if response.status().is_success() => {
-                    log::info!("Log level set successfully");
-                }
-                Ok(response) => {
-                    log::warn!("Failed to set log level, status: {}", response.status());
-                }
-                Err(e) => {
-                    log::warn!("Failed to set log level: {}", e);
-                }
+            let spec = log_level_spec(&self.config.log_level, &self.config.log_topics)
+                .unwrap_or_else(|e| {
+                    log::warn!("Ignoring per-topic node log levels: {}", e);
+                    self.config.log_level.clone()
+                });
+            log::info!("Setting node log level to: {}", spec);
+            match NodeApiClient::new(self.config.api_port)
+                .set_log_level(&spec)
+                .await
+            {
+                Ok(()) => log::info!("Log level set successfully"),
+                Err(e) => log::warn!("Failed to set log level: {}", e),
             }
 
             // Emit event for sound notification
@@ -317,17 +377,15 @@ impl NodeService {
         let (error_tx, mut error_rx) = mpsc::channel::<String>(10);
         let data_dir_clone = self.config.data_dir.clone();
         let log_file_path = log_file.clone();
+        let log_max_bytes = self.config.log_max_size_mb * 1024 * 1024;
+        let log_max_files = self.config.log_max_files;
+        let log_sender = self.log_stream.start(app_handle.clone());
 
         // Spawn task to handle stdout/stderr from the sidecar
         tokio::spawn(async move {
-            // Open log file for writing (create or append)
-            use std::fs::OpenOptions;
-            use std::io::Write;
-
-            let mut log_file_handle = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&log_file_path);
+            // Open log file for writing (create or append), rotating by size
+            let mut log_file_handle =
+                RotatingLogWriter::open(log_file_path, log_max_bytes, log_max_files);
 
             if let Err(e) = &log_file_handle {
                 log::error!("Failed to open log file for writing: {}", e);
@@ -341,10 +399,10 @@ impl NodeService {
                         log::info!("[archivist-node] {}", trimmed);
 
                         // Write to log file
-                        if let Ok(ref mut file) = log_file_handle {
-                            let _ = writeln!(file, "{}", trimmed);
-                            let _ = file.flush();
+                        if let Ok(ref mut writer) = log_file_handle {
+                            let _ = writer.write_line(trimmed);
                         }
+                        log_sender.send(trimmed);
 
                         // Check for recoverable errors
                         if line_str.contains("Should create discovery datastore!") {
@@ -361,10 +419,10 @@ impl NodeService {
                         log::warn!("[archivist-node] {}", trimmed);
 
                         // Write to log file
-                        if let Ok(ref mut file) = log_file_handle {
-                            let _ = writeln!(file, "{}", trimmed);
-                            let _ = file.flush();
+                        if let Ok(ref mut writer) = log_file_handle {
+                            let _ = writer.write_line(trimmed);
                         }
+                        log_sender.send(trimmed);
 
                         // Check for recoverable errors in stderr too
                         if line_str.contains("Should create discovery datastore!") {
@@ -384,6 +442,7 @@ impl NodeService {
                             payload.code,
                             payload.signal
                         );
+                        let _ = exit_tx.send(true);
                         break;
                     }
                     _ => {}
@@ -543,31 +602,49 @@ impl NodeService {
         None
     }
 
-    /// Stop the archivist-node sidecar
+    /// Stop the archivist-node sidecar: ask it to shut down, give it the
+    /// configured grace period to exit, and only then kill it. A node that had
+    /// to be killed is left in the error state.
     pub async fn stop(&mut self) -> Result<()> {
         if self.status.state == NodeState::Stopped || self.status.state == NodeState::Stopping {
             return Err(ArchivistError::NodeNotRunning);
         }
 
+        let grace = Duration::from_secs(self.config.shutdown_grace_secs);
         self.status.state = NodeState::Stopping;
-        log::info!("Stopping Archivist node...");
+        log::info!("Stopping Archivist node (grace period {:?})...", grace);
 
         // Signal shutdown to monitor task
         if let Some(tx) = self.shutdown_tx.take() {
             let _ = tx.send(());
         }
 
-        // Kill the process
+        let mut outcome = ShutdownOutcome::Graceful;
         if let Some(mut process_state) = self.process_state.take() {
             if let Some(child) = process_state.child.take() {
-                child.kill().map_err(|e| {
-                    ArchivistError::NodeStopFailed(format!("Failed to kill process: {}", e))
-                })?;
+                match shutdown_child(child, &mut process_state.exited, grace).await {
+                    Ok(result) => outcome = result,
+                    Err(e) => {
+                        log::error!("Failed to stop Archivist node: {}", e);
+                        self.status.state = NodeState::Error;
+                        self.status.last_error = Some(e.to_string());
+                        return Err(e);
+                    }
+                }
             }
         }
 
         // Update status
-        self.status.state = NodeState::Stopped;
+        self.status.state = match outcome {
+            ShutdownOutcome::Graceful | ShutdownOutcome::Terminated => NodeState::Stopped,
+            ShutdownOutcome::Killed => {
+                self.status.last_error = Some(format!(
+                    "Node did not exit within {}s and was killed",
+                    grace.as_secs()
+                ));
+                NodeState::Error
+            }
+        };
         self.status.pid = None;
         self.status.uptime_seconds = None;
         self.status.api_url = None;
@@ -620,6 +697,31 @@ impl NodeService {
         self.config.clone()
     }
 
+    /// Turn forwarding of new log lines to the frontend on or off
+    pub fn set_log_streaming(&self, enabled: bool) {
+        self.log_stream.set_enabled(enabled);
+    }
+
+    /// Change the node's log level and per-topic levels, applying them right
+    /// away if the node is running (otherwise on next start). Returns the
+    /// chronicles level setting.
+    pub async fn set_log_level(
+        &mut self,
+        level: NodeLogLevel,
+        topics: BTreeMap<String, NodeLogLevel>,
+    ) -> Result<String> {
+        let spec = log_level_spec(level.as_chronicles(), &topics)?;
+        if self.status.state == NodeState::Running {
+            NodeApiClient::new(self.config.api_port)
+                .set_log_level(&spec)
+                .await?;
+        }
+        log::info!("Node log level set to: {}", spec);
+        self.config.log_level = level.as_chronicles().to_string();
+        self.config.log_topics = topics;
+        Ok(spec)
+    }
+
     /// Update node configuration (requires restart to take effect)
     pub fn set_config(&mut self, config: NodeConfig) {
         self.config = config;
@@ -810,6 +912,98 @@ impl Default for NodeService {
     }
 }
 
+/// Ask the sidecar to exit, wait up to `grace` for it to do so, then kill it
+async fn shutdown_child(
+    child: CommandChild,
+    exited: &mut watch::Receiver<bool>,
+    grace: Duration,
+) -> Result<ShutdownOutcome> {
+    let pid = child.pid();
+    let started = Instant::now();
+    let mut outcome = ShutdownOutcome::Killed;
+
+    match request_termination(pid) {
+        Ok(()) => {
+            log::info!("Sent SIGTERM to archivist-node (PID {})", pid);
+            if tokio::time::timeout(grace, wait_for_exit(exited))
+                .await
+                .is_ok()
+            {
+                log::info!(
+                    "archivist-node (PID {}) exited after {:?}",
+                    pid,
+                    started.elapsed()
+                );
+                return Ok(ShutdownOutcome::Graceful);
+            }
+            log::warn!(
+                "archivist-node (PID {}) did not exit within {:?}, killing it",
+                pid,
+                grace
+            );
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {
+            log::info!("Stopping archivist-node (PID {}): {}", pid, e);
+            outcome = ShutdownOutcome::Terminated;
+        }
+        Err(e) => {
+            log::warn!(
+                "Failed to send SIGTERM to archivist-node (PID {}), killing it: {}",
+                pid,
+                e
+            );
+        }
+    }
+
+    child
+        .kill()
+        .map_err(|e| ArchivistError::NodeStopFailed(format!("Failed to kill process: {}", e)))?;
+    log::info!("Killed archivist-node (PID {})", pid);
+
+    if tokio::time::timeout(KILL_WAIT, wait_for_exit(exited))
+        .await
+        .is_err()
+    {
+        return Err(ArchivistError::NodeStopFailed(format!(
+            "Process {} still running {:?} after it was killed",
+            pid, KILL_WAIT
+        )));
+    }
+    Ok(outcome)
+}
+
+/// Wait until the sidecar's event task reports that the process terminated
+/// (or ends without doing so, which means its output stream is gone)
+async fn wait_for_exit(exited: &mut watch::Receiver<bool>) {
+    while !*exited.borrow_and_update() {
+        if exited.changed().await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Ask a process to shut down cleanly
+#[cfg(unix)]
+fn request_termination(pid: u32) -> std::io::Result<()> {
+    if unsafe { libc::kill(pid as i32, libc::SIGTERM) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// There is no signal to ask for a clean shutdown on other platforms. On
+/// Windows, CTRL_BREAK only reaches processes attached to our console, which
+/// a GUI app and its windowless sidecar don't have, so the node is stopped
+/// right away (reported as a normal stop, not a timeout).
+#[cfg(not(unix))]
+fn request_termination(_pid: u32) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "graceful termination is not supported on this platform",
+    ))
+}
+
 /// Node manager that runs health checks and handles auto-restart
 pub struct NodeManager {
     service: Arc<RwLock<NodeService>>,
@@ -890,3 +1084,50 @@ impl NodeManager {
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_for_exit() {
+        let (tx, mut rx) = watch::channel(false);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let _ = tx.send(true);
+        });
+        tokio::time::timeout(Duration::from_secs(1), wait_for_exit(&mut rx))
+            .await
+            .expect("exit was reported");
+
+        // An event task that ends without reporting also counts as exited
+        let (tx, mut rx) = watch::channel(false);
+        drop(tx);
+        tokio::time::timeout(Duration::from_secs(1), wait_for_exit(&mut rx))
+            .await
+            .expect("closed channel ends the wait");
+
+        // Still running: keeps waiting
+        let (_tx, mut rx) = watch::channel(false);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), wait_for_exit(&mut rx))
+                .await
+                .is_err()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_request_termination_sends_sigterm() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        request_termination(child.id()).unwrap();
+
+        let status = child.wait().unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+    }
+}
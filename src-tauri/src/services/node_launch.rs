@@ -0,0 +1,260 @@
+//! archivist-node launch options
+//!
+//! Builds the sidecar's command line from the node configuration. Besides
+//! the ports and data directory the app always manages, users can pick the
+//! NAT strategy, the interface the node listens on, bootstrap nodes (as
+//! SPRs), extra announce addresses and further node flags. Everything is
+//! validated before it is saved, so a bad value is reported in settings
+//! instead of making the node fail to start.
+
+use crate::error::{ArchivistError, Result};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// Flags the app sets itself; extra args may not override them
+const MANAGED_FLAGS: &[&str] = &[
+    "data-dir",
+    "api-port",
+    "disc-port",
+    "listen-addrs",
+    "storage-quota",
+    "nat",
+    "bootstrap-node",
+    "announce-addrs",
+    "log-level",
+];
+
+/// How the node makes itself reachable from outside the local network
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NatStrategy {
+    /// No port mapping; only local addresses are announced
+    None,
+    /// Map ports on the router via UPnP
+    #[default]
+    Upnp,
+    /// Map ports on the router via NAT-PMP
+    Pmp,
+    /// Announce a fixed external IP, e.g. behind a manual port forward
+    Extip,
+}
+
+/// User-configurable archivist-node flags
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeLaunchOptions {
+    #[serde(default)]
+    pub nat: NatStrategy,
+    /// External IP announced when `nat` is `extip`
+    #[serde(default)]
+    pub nat_external_ip: Option<String>,
+    /// Interface the node listens on for P2P connections
+    #[serde(default = "default_listen_ip")]
+    pub listen_ip: String,
+    /// Signed peer records of nodes to join the network through
+    #[serde(default)]
+    pub bootstrap_nodes: Vec<String>,
+    /// Multiaddrs announced in addition to the ones the node detects
+    #[serde(default)]
+    pub announce_addrs: Vec<String>,
+    /// Further `--flag` or `--flag=value` arguments passed to the node as-is
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+fn default_listen_ip() -> String {
+    "0.0.0.0".to_string()
+}
+
+impl Default for NodeLaunchOptions {
+    fn default() -> Self {
+        Self {
+            nat: NatStrategy::default(),
+            nat_external_ip: None,
+            listen_ip: default_listen_ip(),
+            bootstrap_nodes: Vec::new(),
+            announce_addrs: Vec::new(),
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+impl NodeLaunchOptions {
+    /// Check every option, naming the first invalid one
+    pub fn validate(&self) -> Result<()> {
+        self.nat_arg()?;
+        self.listen_ip()?;
+
+        for spr in &self.bootstrap_nodes {
+            let spr = spr.trim();
+            if !spr.starts_with("spr:") || spr.len() <= 4 || spr.contains(char::is_whitespace) {
+                return Err(ArchivistError::ConfigError(format!(
+                    "Invalid bootstrap node (expected an SPR starting with \"spr:\"): {}",
+                    spr
+                )));
+            }
+        }
+
+        for addr in &self.announce_addrs {
+            let addr = addr.trim();
+            if !addr.starts_with('/') || addr.len() < 2 || addr.contains(char::is_whitespace) {
+                return Err(ArchivistError::ConfigError(format!(
+                    "Invalid announce address (expected a multiaddr): {}",
+                    addr
+                )));
+            }
+        }
+
+        for arg in &self.extra_args {
+            validate_extra_arg(arg)?;
+        }
+
+        Ok(())
+    }
+
+    /// Multiaddr the node listens on for P2P connections
+    pub fn listen_addr(&self, port: u16) -> Result<String> {
+        Ok(match self.listen_ip()? {
+            IpAddr::V4(ip) => format!("/ip4/{}/tcp/{}", ip, port),
+            IpAddr::V6(ip) => format!("/ip6/{}/tcp/{}", ip, port),
+        })
+    }
+
+    /// Arguments for the options above, after the ones the app manages
+    pub fn args(&self) -> Result<Vec<String>> {
+        self.validate()?;
+
+        let mut args = vec![format!("--nat={}", self.nat_arg()?)];
+        args.extend(
+            self.bootstrap_nodes
+                .iter()
+                .map(|spr| format!("--bootstrap-node={}", spr.trim())),
+        );
+        args.extend(
+            self.announce_addrs
+                .iter()
+                .map(|addr| format!("--announce-addrs={}", addr.trim())),
+        );
+        args.extend(self.extra_args.iter().map(|arg| arg.trim().to_string()));
+        Ok(args)
+    }
+
+    fn listen_ip(&self) -> Result<IpAddr> {
+        self.listen_ip.trim().parse().map_err(|_| {
+            ArchivistError::ConfigError(format!("Invalid listen IP: {}", self.listen_ip))
+        })
+    }
+
+    fn nat_arg(&self) -> Result<String> {
+        Ok(match self.nat {
+            NatStrategy::None => "none".to_string(),
+            NatStrategy::Upnp => "upnp".to_string(),
+            NatStrategy::Pmp => "pmp".to_string(),
+            NatStrategy::Extip => {
+                let ip = self.nat_external_ip.as_deref().unwrap_or("").trim();
+                let ip: IpAddr = ip.parse().map_err(|_| {
+                    ArchivistError::ConfigError(format!(
+                        "NAT strategy extip needs a valid external IP, got {:?}",
+                        ip
+                    ))
+                })?;
+                format!("extip:{}", ip)
+            }
+        })
+    }
+}
+
+/// Extra args must be single `--flag` / `--flag=value` arguments that don't
+/// touch a flag the app manages
+fn validate_extra_arg(arg: &str) -> Result<()> {
+    let arg = arg.trim();
+    let invalid = |reason: &str| {
+        ArchivistError::ConfigError(format!("Invalid extra node argument {:?}: {}", arg, reason))
+    };
+
+    let flag = arg
+        .strip_prefix("--")
+        .ok_or_else(|| invalid("must start with --"))?;
+    let name = flag.split('=').next().unwrap_or_default();
+
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(invalid("not a valid flag name"));
+    }
+    if arg.chars().any(char::is_control) {
+        return Err(invalid("contains control characters"));
+    }
+    if MANAGED_FLAGS.contains(&name) {
+        return Err(invalid("this flag is set from the node settings"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_args() {
+        let options = NodeLaunchOptions::default();
+        assert_eq!(options.args().unwrap(), vec!["--nat=upnp"]);
+        assert_eq!(options.listen_addr(8070).unwrap(), "/ip4/0.0.0.0/tcp/8070");
+    }
+
+    #[test]
+    fn test_private_network_args() {
+        let options = NodeLaunchOptions {
+            nat: NatStrategy::Extip,
+            nat_external_ip: Some("203.0.113.7".to_string()),
+            listen_ip: "::".to_string(),
+            bootstrap_nodes: vec!["spr:CiUIAhIhA".to_string()],
+            announce_addrs: vec!["/ip4/203.0.113.7/tcp/18070".to_string()],
+            extra_args: vec!["--max-peers=50".to_string()],
+        };
+        assert_eq!(
+            options.args().unwrap(),
+            vec![
+                "--nat=extip:203.0.113.7",
+                "--bootstrap-node=spr:CiUIAhIhA",
+                "--announce-addrs=/ip4/203.0.113.7/tcp/18070",
+                "--max-peers=50",
+            ]
+        );
+        assert_eq!(options.listen_addr(8070).unwrap(), "/ip6/::/tcp/8070");
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_options() {
+        let base = NodeLaunchOptions::default();
+
+        let extip_without_ip = NodeLaunchOptions {
+            nat: NatStrategy::Extip,
+            ..base.clone()
+        };
+        assert!(extip_without_ip.validate().is_err());
+
+        let bad_listen_ip = NodeLaunchOptions {
+            listen_ip: "localhost".to_string(),
+            ..base.clone()
+        };
+        assert!(bad_listen_ip.validate().is_err());
+
+        let bad_bootstrap = NodeLaunchOptions {
+            bootstrap_nodes: vec!["/ip4/1.2.3.4/tcp/8070".to_string()],
+            ..base.clone()
+        };
+        assert!(bad_bootstrap.validate().is_err());
+
+        let bad_announce = NodeLaunchOptions {
+            announce_addrs: vec!["1.2.3.4:8070".to_string()],
+            ..base.clone()
+        };
+        assert!(bad_announce.validate().is_err());
+
+        for arg in ["max-peers=50", "--data-dir=/tmp", "--nat", "--a b", "--"] {
+            let options = NodeLaunchOptions {
+                extra_args: vec![arg.to_string()],
+                ..base.clone()
+            };
+            assert!(options.validate().is_err(), "{} should be rejected", arg);
+        }
+    }
+}
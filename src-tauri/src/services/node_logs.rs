@@ -0,0 +1,895 @@
+//! archivist-node log files
+//!
+//! The sidecar's stdout/stderr is written to `node.log` in the node's data
+//! directory. Once the file reaches the configured size it is rotated to
+//! `node.log.1` (older files shift to `.2`, `.3`, ...) and files beyond the
+//! retention count are deleted.
+//!
+//! Lines are parsed from the chronicles text format the node logs in:
+//!
+//! ```text
+//! INF 2025-01-15 10:23:45.123+00:00 Started node    topics="archivist node" tid=1234
+//! ```
+//!
+//! into level, timestamp, topic, message and the remaining `key=value`
+//! fields. Queries read the files backwards from the newest line, so a page
+//! of recent entries never loads a whole file into memory. While a logs view
+//! is subscribed, new lines are also forwarded to the frontend as events.
+
+use crate::error::{ArchivistError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc;
+
+/// Name of the current log file inside the node's data directory
+pub const NODE_LOG_FILE: &str = "node.log";
+
+/// Entries returned per query page unless asked otherwise
+const DEFAULT_PAGE_SIZE: usize = 200;
+
+/// Bytes read per step when reading a log file backwards
+const REVERSE_READ_CHUNK: usize = 64 * 1024;
+
+/// Leading bytes of a log file that identify it in a query cursor
+const FINGERPRINT_BYTES: u64 = 4096;
+
+/// Tauri event carrying a batch of new log entries, oldest first
+pub const NODE_LOG_EVENT: &str = "node-log-lines";
+
+/// Minimum time between two streamed batches
+const LOG_STREAM_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Lines buffered for streaming; more are dropped until the frontend catches up
+const LOG_STREAM_BUFFER: usize = 2000;
+
+/// Path of the current log file for a node data directory
+pub fn node_log_path(data_dir: &str) -> PathBuf {
+    Path::new(data_dir).join(NODE_LOG_FILE)
+}
+
+/// Path of the `index`-th log file: 0 is the current file, 1 the most recently
+/// rotated one, and so on
+fn rotated_log_path(path: &Path, index: u32) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+/// Open a log file for reading while the node may still be writing to it
+fn open_shared(path: &Path) -> std::io::Result<File> {
+    // On Windows, sharing must be allowed explicitly to read (and rotate) a
+    // file that is open elsewhere
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        std::fs::OpenOptions::new()
+            .read(true)
+            .share_mode(0x00000001 | 0x00000002 | 0x00000004) // FILE_SHARE_READ | WRITE | DELETE
+            .open(path)
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    File::open(path)
+}
+
+/// Appends lines to `node.log`, rotating it once it reaches `max_bytes`
+pub struct RotatingLogWriter {
+    path: PathBuf,
+    max_bytes: u64,
+    /// Rotated files kept besides the current one
+    max_files: u32,
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingLogWriter {
+    /// Open (or create) the log file, appending to what is already there
+    pub fn open(path: PathBuf, max_bytes: u64, max_files: u32) -> std::io::Result<Self> {
+        let mut writer = Self {
+            path,
+            max_bytes,
+            max_files,
+            file: None,
+            size: 0,
+        };
+        writer.reopen()?;
+        Ok(writer)
+    }
+
+    fn reopen(&mut self) -> std::io::Result<()> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = file.metadata()?.len();
+        self.file = Some(file);
+        Ok(())
+    }
+
+    /// Write one line, rotating first if it would push the file past the limit
+    pub fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.max_bytes > 0 && self.size > 0 && self.size + len > self.max_bytes {
+            if let Err(e) = self.rotate() {
+                log::warn!("Failed to rotate {}: {}", self.path.display(), e);
+            }
+        }
+        if self.file.is_none() {
+            self.reopen()?;
+        }
+
+        let file = self.file.as_mut().expect("log file was just opened");
+        writeln!(file, "{}", line)?;
+        file.flush()?;
+        self.size += len;
+        Ok(())
+    }
+
+    /// Shift `node.log.N` to `node.log.N+1` (dropping the oldest) and start a
+    /// new `node.log`
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file = None;
+
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+            return self.reopen();
+        }
+
+        let oldest = rotated_log_path(&self.path, self.max_files);
+        if oldest.exists() {
+            std::fs::remove_file(&oldest)?;
+        }
+        for index in (0..self.max_files).rev() {
+            let from = rotated_log_path(&self.path, index);
+            if from.exists() {
+                std::fs::rename(&from, rotated_log_path(&self.path, index + 1))?;
+            }
+        }
+        log::info!("Rotated {}", self.path.display());
+        self.reopen()
+    }
+}
+
+/// Severity of a chronicles log line, least to most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeLogLevel {
+    Trace,
+    Debug,
+    Info,
+    Notice,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl NodeLogLevel {
+    /// Level from the three-letter prefix chronicles writes
+    pub fn from_abbreviation(abbreviation: &str) -> Option<Self> {
+        match abbreviation {
+            "TRC" => Some(Self::Trace),
+            "DBG" => Some(Self::Debug),
+            "INF" => Some(Self::Info),
+            "NTC" | "NOT" => Some(Self::Notice),
+            "WRN" => Some(Self::Warn),
+            "ERR" => Some(Self::Error),
+            "FAT" => Some(Self::Fatal),
+            _ => None,
+        }
+    }
+
+    /// Name the chronicles `loglevel` endpoint expects
+    pub fn as_chronicles(&self) -> &'static str {
+        match self {
+            Self::Trace => "TRACE",
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Notice => "NOTICE",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+            Self::Fatal => "FATAL",
+        }
+    }
+}
+
+/// Chronicles log level setting: a default level followed by per-topic
+/// overrides, e.g. `DEBUG;TRACE:libp2p,dht;WARN:discv5`
+pub fn log_level_spec(default: &str, topics: &BTreeMap<String, NodeLogLevel>) -> Result<String> {
+    let mut by_level: BTreeMap<NodeLogLevel, Vec<&str>> = BTreeMap::new();
+    for (topic, level) in topics {
+        let topic = topic.trim();
+        if topic.is_empty()
+            || topic
+                .chars()
+                .any(|c| c == ';' || c == ':' || c == ',' || c.is_whitespace())
+        {
+            return Err(ArchivistError::ConfigError(format!(
+                "Invalid log topic: {:?}",
+                topic
+            )));
+        }
+        by_level.entry(*level).or_default().push(topic);
+    }
+
+    let mut spec = default.trim().to_string();
+    for (level, topics) in by_level {
+        spec.push_str(&format!(";{}:{}", level.as_chronicles(), topics.join(",")));
+    }
+    Ok(spec)
+}
+
+/// One line of the node's log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeLogEntry {
+    /// None for lines not in the chronicles format (e.g. crash output)
+    pub level: Option<NodeLogLevel>,
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Chronicles `topics` of the line
+    pub topic: Option<String>,
+    pub message: String,
+    /// Remaining `key=value` properties, unquoted
+    pub fields: BTreeMap<String, String>,
+    pub raw: String,
+}
+
+/// Parse one log line; lines that aren't chronicles output are kept as a
+/// message without level or timestamp
+pub fn parse_log_line(line: &str) -> NodeLogEntry {
+    let raw = line.trim_end().to_string();
+    parse_chronicles_line(&raw).unwrap_or_else(|| NodeLogEntry {
+        level: None,
+        timestamp: None,
+        topic: None,
+        message: raw.clone(),
+        fields: BTreeMap::new(),
+        raw,
+    })
+}
+
+fn parse_chronicles_line(line: &str) -> Option<NodeLogEntry> {
+    let mut parts = line.splitn(4, ' ');
+    let level = NodeLogLevel::from_abbreviation(parts.next()?)?;
+    let date = parts.next()?;
+    let time = parts.next()?;
+    let rest = parts.next().unwrap_or("");
+
+    let timestamp =
+        DateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M:%S%.f%:z")
+            .ok()?
+            .with_timezone(&Utc);
+
+    // Chronicles pads the message with spaces before the properties, so
+    // prefer a padded gap; long messages are followed by a single space
+    let padded = rest.match_indices("  ").map(|(index, _)| index);
+    let single = rest.match_indices(' ').map(|(index, _)| index);
+    let split = padded
+        .chain(single)
+        .find_map(|index| Some((index, parse_fields(&rest[index + 1..])?)))
+        .unwrap_or((rest.len(), BTreeMap::new()));
+    let (message_end, mut fields) = split;
+    let topic = fields.remove("topics");
+
+    Some(NodeLogEntry {
+        level: Some(level),
+        timestamp: Some(timestamp),
+        topic,
+        message: rest[..message_end].trim().to_string(),
+        fields,
+        raw: line.to_string(),
+    })
+}
+
+/// Parse `key=value key="quoted value" ...` covering all of `input`
+fn parse_fields(input: &str) -> Option<BTreeMap<String, String>> {
+    let input = input.trim_start();
+    if input.is_empty() {
+        return None;
+    }
+
+    let mut fields = BTreeMap::new();
+    let mut rest = input;
+    while !rest.is_empty() {
+        let key_len = rest.find('=')?;
+        let key = &rest[..key_len];
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
+        {
+            return None;
+        }
+        rest = &rest[key_len + 1..];
+
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = None;
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some((_, escaped)) = chars.next() {
+                            value.push(escaped);
+                        }
+                    }
+                    '"' => {
+                        end = Some(i);
+                        break;
+                    }
+                    _ => value.push(c),
+                }
+            }
+            rest = &quoted[end? + 1..];
+            if !rest.is_empty() && !rest.starts_with(' ') {
+                return None;
+            }
+            value
+        } else {
+            let end = rest.find(' ').unwrap_or(rest.len());
+            let value = rest[..end].to_string();
+            rest = &rest[end..];
+            value
+        };
+
+        fields.insert(key.to_string(), value);
+        rest = rest.trim_start();
+    }
+    Some(fields)
+}
+
+/// Reads a file's lines from a byte offset backwards, newest first
+struct ReverseLineReader {
+    file: File,
+    /// Start of the bytes held in `buf`
+    pos: u64,
+    /// Bytes between `pos` and the start of the last line returned
+    buf: Vec<u8>,
+    chunk_size: usize,
+}
+
+impl ReverseLineReader {
+    fn new(file: File, end: u64, chunk_size: usize) -> Self {
+        Self {
+            file,
+            pos: end,
+            buf: Vec::new(),
+            chunk_size,
+        }
+    }
+
+    /// The previous line and the byte offset it starts at
+    fn next_line(&mut self) -> std::io::Result<Option<(u64, String)>> {
+        loop {
+            if self.buf.is_empty() && self.pos == 0 {
+                return Ok(None);
+            }
+            let content_end = if self.buf.last() == Some(&b'\n') {
+                self.buf.len() - 1
+            } else {
+                self.buf.len()
+            };
+            if let Some(i) = self.buf[..content_end].iter().rposition(|&b| b == b'\n') {
+                let line = String::from_utf8_lossy(&self.buf[i + 1..content_end]).into_owned();
+                let start = self.pos + i as u64 + 1;
+                self.buf.truncate(i + 1);
+                return Ok(Some((start, line)));
+            }
+            if self.pos == 0 {
+                let line = String::from_utf8_lossy(&self.buf[..content_end]).into_owned();
+                self.buf.clear();
+                return Ok(Some((0, line)));
+            }
+
+            let chunk = (self.chunk_size as u64).min(self.pos);
+            self.pos -= chunk;
+            let mut bytes = vec![0u8; chunk as usize];
+            self.file.seek(SeekFrom::Start(self.pos))?;
+            self.file.read_exact(&mut bytes)?;
+            bytes.extend_from_slice(&self.buf);
+            self.buf = bytes;
+        }
+    }
+}
+
+/// Filters and paging for a log query
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeLogQuery {
+    /// Only entries at least this severe (lines without a level are dropped)
+    pub min_level: Option<NodeLogLevel>,
+    /// Case-insensitive text the line must contain
+    pub text: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Continue from the `next_cursor` of a previous page
+    pub cursor: Option<String>,
+    /// Entries per page (default: 200)
+    pub limit: Option<usize>,
+}
+
+/// One page of log entries, oldest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeLogPage {
+    pub entries: Vec<NodeLogEntry>,
+    /// Cursor for the page of older entries; None once the logs are exhausted
+    pub next_cursor: Option<String>,
+}
+
+/// Position to read backwards from: a log file and a byte offset in it. The
+/// file is identified by its fingerprint, since rotation renames it; its index
+/// when the cursor was made is where the search for it starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LogCursor {
+    file_index: u32,
+    fingerprint: String,
+    offset: u64,
+}
+
+impl LogCursor {
+    fn parse(cursor: &str) -> Result<Self> {
+        let invalid = || ArchivistError::ConfigError(format!("Invalid log cursor: {}", cursor));
+        let mut parts = cursor.splitn(3, ':');
+        let (Some(file_index), Some(fingerprint), Some(offset)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        Ok(Self {
+            file_index: file_index.parse().map_err(|_| invalid())?,
+            fingerprint: fingerprint.to_string(),
+            offset: offset.parse().map_err(|_| invalid())?,
+        })
+    }
+
+    fn encode(&self) -> String {
+        format!("{}:{}:{}", self.file_index, self.fingerprint, self.offset)
+    }
+}
+
+/// Hash of a log file's first line (up to `FINGERPRINT_BYTES`). Lines start
+/// with a timestamp and files are only appended to, so it stays the same while
+/// the file is rotated.
+fn file_fingerprint(file: &mut File) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut head = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    Read::by_ref(file)
+        .take(FINGERPRINT_BYTES)
+        .read_to_end(&mut head)?;
+    let first_line = head.split(|b| *b == b'\n').next().unwrap_or_default();
+    Ok(hex::encode(&Sha256::digest(first_line)[..8]))
+}
+
+impl NodeLogQuery {
+    fn matches(&self, entry: &NodeLogEntry, text: Option<&str>) -> bool {
+        if let Some(min) = self.min_level {
+            if entry.level.map_or(true, |level| level < min) {
+                return false;
+            }
+        }
+        if self.from.is_some() || self.to.is_some() {
+            let Some(timestamp) = entry.timestamp else {
+                return false;
+            };
+            if self.from.is_some_and(|from| timestamp < from)
+                || self.to.is_some_and(|to| timestamp > to)
+            {
+                return false;
+            }
+        }
+        text.map_or(true, |text| entry.raw.to_lowercase().contains(text))
+    }
+}
+
+/// Run a query over `node.log` and its rotated files, newest lines first,
+/// returning up to `limit` matching entries. A page continues where the
+/// previous one stopped even if the files were rotated in between; if that
+/// file has since been deleted, the logs are exhausted.
+pub fn query_logs(path: &Path, query: &NodeLogQuery) -> Result<NodeLogPage> {
+    query_logs_with_chunk(path, query, REVERSE_READ_CHUNK)
+}
+
+fn query_logs_with_chunk(
+    path: &Path,
+    query: &NodeLogQuery,
+    chunk_size: usize,
+) -> Result<NodeLogPage> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).max(1);
+    let text = query.text.as_deref().map(str::to_lowercase);
+    let mut cursor = match &query.cursor {
+        Some(cursor) => Some(LogCursor::parse(cursor)?),
+        None => None,
+    };
+    let mut file_index = cursor.as_ref().map_or(0, |c| c.file_index);
+
+    let mut entries = Vec::new();
+    loop {
+        let file_path = rotated_log_path(path, file_index);
+        let mut file = match open_shared(&file_path) {
+            Ok(file) => file,
+            // No older files left
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+            Err(e) => return Err(e.into()),
+        };
+        let len = file.metadata()?.len();
+        let fingerprint = file_fingerprint(&mut file)?;
+        let end = match cursor.take() {
+            // Rotation only moves files to higher indexes, so look further back
+            Some(c) if c.fingerprint != fingerprint => {
+                cursor = Some(c);
+                file_index += 1;
+                continue;
+            }
+            Some(c) => c.offset.min(len),
+            None => len,
+        };
+
+        let mut reader = ReverseLineReader::new(file, end, chunk_size);
+        while let Some((start, line)) = reader.next_line()? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = parse_log_line(&line);
+
+            // Logs are chronological, so nothing older can match
+            if let (Some(from), Some(timestamp)) = (query.from, entry.timestamp) {
+                if timestamp < from {
+                    entries.reverse();
+                    return Ok(NodeLogPage {
+                        entries,
+                        next_cursor: None,
+                    });
+                }
+            }
+
+            if query.matches(&entry, text.as_deref()) {
+                entries.push(entry);
+                if entries.len() == limit {
+                    entries.reverse();
+                    return Ok(NodeLogPage {
+                        entries,
+                        next_cursor: Some(
+                            LogCursor {
+                                file_index,
+                                fingerprint,
+                                offset: start,
+                            }
+                            .encode(),
+                        ),
+                    });
+                }
+            }
+        }
+        file_index += 1;
+    }
+
+    entries.reverse();
+    Ok(NodeLogPage {
+        entries,
+        next_cursor: None,
+    })
+}
+
+/// The last `count` raw lines across the log files, oldest first
+pub fn tail_lines(path: &Path, count: usize) -> Result<Vec<String>> {
+    let page = query_logs(
+        path,
+        &NodeLogQuery {
+            limit: Some(count),
+            ..Default::default()
+        },
+    )?;
+    Ok(page.entries.into_iter().map(|e| e.raw).collect())
+}
+
+/// Switch for forwarding new log lines to the frontend, shared across node
+/// restarts
+#[derive(Debug, Clone, Default)]
+pub struct NodeLogStream {
+    enabled: Arc<AtomicBool>,
+}
+
+impl NodeLogStream {
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Start forwarding for one node run; forwarding stops once the returned
+    /// sender is dropped
+    pub fn start(&self, app_handle: AppHandle) -> NodeLogSender {
+        let (tx, mut rx) = mpsc::channel::<NodeLogEntry>(LOG_STREAM_BUFFER);
+
+        tokio::spawn(async move {
+            let mut batch = Vec::new();
+            while rx.recv_many(&mut batch, LOG_STREAM_BUFFER).await > 0 {
+                if let Err(e) = app_handle.emit(NODE_LOG_EVENT, &batch) {
+                    log::warn!("Failed to emit node log lines: {}", e);
+                }
+                batch.clear();
+                tokio::time::sleep(LOG_STREAM_INTERVAL).await;
+            }
+        });
+
+        NodeLogSender {
+            stream: self.clone(),
+            tx,
+        }
+    }
+}
+
+/// Hands lines written by the node to the stream's forwarding task
+pub struct NodeLogSender {
+    stream: NodeLogStream,
+    tx: mpsc::Sender<NodeLogEntry>,
+}
+
+impl NodeLogSender {
+    /// Queue a line for the frontend if a logs view is subscribed
+    pub fn send(&self, line: &str) {
+        if self.stream.is_enabled() {
+            // Never block the node's output on a slow frontend
+            let _ = self.tx.try_send(parse_log_line(line));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(level: &str, minute: u32, message: &str) -> String {
+        format!(
+            "{} 2025-01-15 10:{:02}:00.000+00:00 {:<30} topics=\"archivist node\" tid=42",
+            level, minute, message
+        )
+    }
+
+    fn write_log(path: &Path, lines: &[String]) {
+        std::fs::write(path, lines.join("\n") + "\n").unwrap();
+    }
+
+    #[test]
+    fn test_parse_chronicles_line() {
+        let entry = parse_log_line(
+            r#"WRN 2025-01-15 10:23:45.123+01:00 Peer dropped, retry=later     topics="libp2p dht" peer=16U*abc reason="timed out \"hard\"" count=3"#,
+        );
+        assert_eq!(entry.level, Some(NodeLogLevel::Warn));
+        assert_eq!(
+            entry.timestamp.unwrap().to_rfc3339(),
+            "2025-01-15T09:23:45.123+00:00"
+        );
+        assert_eq!(entry.topic.as_deref(), Some("libp2p dht"));
+        assert_eq!(entry.message, "Peer dropped, retry=later");
+        assert_eq!(entry.fields["peer"], "16U*abc");
+        assert_eq!(entry.fields["reason"], "timed out \"hard\"");
+        assert_eq!(entry.fields["count"], "3");
+        assert!(!entry.fields.contains_key("topics"));
+    }
+
+    #[test]
+    fn test_parse_plain_line() {
+        let entry = parse_log_line("Traceback (most recent call last):");
+        assert_eq!(entry.level, None);
+        assert_eq!(entry.timestamp, None);
+        assert_eq!(entry.message, "Traceback (most recent call last):");
+
+        let entry = parse_log_line("INF 2025-01-15 10:23:45.123+00:00 Started");
+        assert_eq!(entry.level, Some(NodeLogLevel::Info));
+        assert_eq!(entry.message, "Started");
+        assert!(entry.fields.is_empty());
+    }
+
+    #[test]
+    fn test_log_level_spec() {
+        let mut topics = BTreeMap::new();
+        assert_eq!(log_level_spec("INFO", &topics).unwrap(), "INFO");
+
+        topics.insert("libp2p".to_string(), NodeLogLevel::Trace);
+        topics.insert("dht".to_string(), NodeLogLevel::Trace);
+        topics.insert("discv5".to_string(), NodeLogLevel::Warn);
+        assert_eq!(
+            log_level_spec("DEBUG", &topics).unwrap(),
+            "DEBUG;TRACE:dht,libp2p;WARN:discv5"
+        );
+
+        topics.insert("bad;topic".to_string(), NodeLogLevel::Info);
+        assert!(log_level_spec("DEBUG", &topics).is_err());
+    }
+
+    #[test]
+    fn test_rotating_writer_keeps_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(NODE_LOG_FILE);
+        let mut writer = RotatingLogWriter::open(path.clone(), 25, 2).unwrap();
+
+        for i in 0..8 {
+            writer.write_line(&format!("line number {}", i)).unwrap();
+        }
+
+        // 15 bytes per line: each file holds one line, two rotated files kept
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "line number 7\n");
+        assert_eq!(
+            std::fs::read_to_string(rotated_log_path(&path, 1)).unwrap(),
+            "line number 6\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(rotated_log_path(&path, 2)).unwrap(),
+            "line number 5\n"
+        );
+        assert!(!rotated_log_path(&path, 3).exists());
+    }
+
+    #[test]
+    fn test_reverse_reader_across_chunks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lines.txt");
+        std::fs::write(&path, "first\nsecond line\n\nthird\nlast without newline").unwrap();
+
+        let file = File::open(&path).unwrap();
+        let len = file.metadata().unwrap().len();
+        let mut reader = ReverseLineReader::new(file, len, 4);
+        let mut lines = Vec::new();
+        while let Some((start, line)) = reader.next_line().unwrap() {
+            lines.push((start, line));
+        }
+        assert_eq!(
+            lines,
+            vec![
+                (25, "last without newline".to_string()),
+                (19, "third".to_string()),
+                (18, String::new()),
+                (6, "second line".to_string()),
+                (0, "first".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_pages_across_rotated_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(NODE_LOG_FILE);
+        write_log(
+            &rotated_log_path(&path, 1),
+            &[
+                line("INF", 1, "old info"),
+                line("ERR", 2, "old error"),
+                line("DBG", 3, "old debug"),
+            ],
+        );
+        write_log(
+            &path,
+            &[
+                line("WRN", 4, "new warning"),
+                "plain crash output".to_string(),
+                line("ERR", 5, "new error"),
+            ],
+        );
+
+        let query = NodeLogQuery {
+            min_level: Some(NodeLogLevel::Warn),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let first = query_logs_with_chunk(&path, &query, 16).unwrap();
+        let messages: Vec<&str> = first.entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["new warning", "new error"]);
+
+        let second = query_logs_with_chunk(
+            &path,
+            &NodeLogQuery {
+                cursor: first.next_cursor.clone(),
+                ..query.clone()
+            },
+            16,
+        )
+        .unwrap();
+        let messages: Vec<&str> = second.entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["old error"]);
+        assert!(second.next_cursor.is_none());
+
+        // Text and time range filters
+        let query = NodeLogQuery {
+            text: Some("OLD".to_string()),
+            from: Some("2025-01-15T10:02:00Z".parse().unwrap()),
+            ..Default::default()
+        };
+        let page = query_logs_with_chunk(&path, &query, 16).unwrap();
+        let messages: Vec<&str> = page.entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["old error", "old debug"]);
+
+        assert_eq!(
+            tail_lines(&path, 2).unwrap(),
+            vec![
+                "plain crash output".to_string(),
+                line("ERR", 5, "new error")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_cursor_survives_rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(NODE_LOG_FILE);
+        write_log(
+            &rotated_log_path(&path, 1),
+            &[line("INF", 1, "one"), line("INF", 2, "two")],
+        );
+        write_log(
+            &path,
+            &[
+                line("INF", 3, "three"),
+                line("INF", 4, "four"),
+                line("INF", 5, "five"),
+            ],
+        );
+
+        let query = NodeLogQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let first = query_logs_with_chunk(&path, &query, 16).unwrap();
+        let messages: Vec<&str> = first.entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["four", "five"]);
+
+        // The node rotates and keeps logging before the next page is read
+        let mut writer = RotatingLogWriter::open(path.clone(), 0, 5).unwrap();
+        writer.rotate().unwrap();
+        writer.write_line(&line("INF", 6, "six")).unwrap();
+
+        let second = query_logs_with_chunk(
+            &path,
+            &NodeLogQuery {
+                cursor: first.next_cursor.clone(),
+                ..query.clone()
+            },
+            16,
+        )
+        .unwrap();
+        let messages: Vec<&str> = second.entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["two", "three"]);
+
+        let third = query_logs_with_chunk(
+            &path,
+            &NodeLogQuery {
+                cursor: second.next_cursor.clone(),
+                ..query.clone()
+            },
+            16,
+        )
+        .unwrap();
+        let messages: Vec<&str> = third.entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["one"]);
+        assert!(third.next_cursor.is_none());
+
+        // Once the file a cursor points into is deleted, the logs are exhausted
+        std::fs::remove_file(rotated_log_path(&path, 2)).unwrap();
+        let page = query_logs_with_chunk(
+            &path,
+            &NodeLogQuery {
+                cursor: second.next_cursor.clone(),
+                ..query
+            },
+            16,
+        )
+        .unwrap();
+        assert!(page.entries.is_empty());
+        assert!(page.next_cursor.is_none());
+    }
+}
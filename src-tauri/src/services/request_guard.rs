@@ -0,0 +1,573 @@
+//! Request Guard
+//!
+//! Shared middleware for the embedded HTTP servers (manifest server, backup
+//! trigger server and media streaming server):
+//! - Per-IP token bucket rate limiting (over-limit requests get HTTP 429)
+//! - A bounded audit log of who fetched manifests, triggered polls or streamed media
+//!
+//! The audit log is appended to `request-audit.jsonl` one entry per line and
+//! compacted back down to the most recent entries once the file has doubled.
+//! Writes happen on a background thread, off the request path.
+
+use crate::error::{ArchivistError, Result};
+use crate::services::config::RateLimitSettings;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::Filter;
+
+/// Default number of audit entries kept in memory and on disk
+const DEFAULT_MAX_AUDIT_ENTRIES: usize = 2000;
+
+/// Prune idle buckets once this many client IPs are being tracked
+const MAX_TRACKED_IPS: usize = 1024;
+
+/// Which embedded server handled a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerKind {
+    ManifestServer,
+    TriggerServer,
+    MediaStreaming,
+}
+
+/// How a request was answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Allowed,
+    RateLimited,
+    Denied,
+    Failed,
+}
+
+impl AuditOutcome {
+    fn from_status(status: u16) -> Self {
+        match status {
+            429 => AuditOutcome::RateLimited,
+            401 | 403 => AuditOutcome::Denied,
+            s if s >= 400 => AuditOutcome::Failed,
+            _ => AuditOutcome::Allowed,
+        }
+    }
+}
+
+/// A single audited request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub server: ServerKind,
+    pub remote_ip: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub outcome: AuditOutcome,
+    pub duration_ms: u64,
+}
+
+/// Filter for viewing the audit log
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLogQuery {
+    pub server: Option<ServerKind>,
+    pub remote_ip: Option<String>,
+    pub outcome: Option<AuditOutcome>,
+    /// Maximum number of entries to return (newest first)
+    pub limit: Option<usize>,
+}
+
+/// Disk writes queued by `RequestAuditLog`, applied in order by its writer
+/// thread so request handlers never wait on file I/O
+enum AuditWrite {
+    Append(AuditEntry),
+    Clear,
+}
+
+/// Owns the on-disk audit file; runs on its own thread
+struct AuditWriter {
+    path: PathBuf,
+    max_entries: usize,
+    /// Newest entries, used to rewrite the file on compaction
+    entries: VecDeque<AuditEntry>,
+    /// Lines currently in the file (may exceed `entries` until compaction)
+    lines_on_disk: usize,
+}
+
+impl AuditWriter {
+    fn run(mut self, rx: mpsc::Receiver<AuditWrite>) {
+        for write in rx {
+            let result = match write {
+                AuditWrite::Append(entry) => self.append(entry),
+                AuditWrite::Clear => {
+                    self.entries.clear();
+                    self.rewrite()
+                }
+            };
+            if let Err(e) = result {
+                log::warn!("Failed to persist request audit log: {}", e);
+            }
+        }
+    }
+
+    /// Append an entry to disk, rewriting the file when it has grown too large
+    fn append(&mut self, entry: AuditEntry) -> Result<()> {
+        if self.entries.len() == self.max_entries {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+
+        if self.lines_on_disk >= self.max_entries * 2 {
+            return self.rewrite();
+        }
+
+        self.create_parent_dir()?;
+        let entry = self.entries.back().expect("entry was just pushed");
+        let line = serde_json::to_string(entry).map_err(ArchivistError::SerializationError)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| {
+                ArchivistError::FileOperationFailed(format!("Failed to open audit log: {}", e))
+            })?;
+        writeln!(file, "{}", line).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!("Failed to write audit log: {}", e))
+        })?;
+        self.lines_on_disk += 1;
+
+        Ok(())
+    }
+
+    /// Replace the on-disk file with the retained entries
+    fn rewrite(&mut self) -> Result<()> {
+        let mut contents = String::new();
+        for entry in &self.entries {
+            let line = serde_json::to_string(entry).map_err(ArchivistError::SerializationError)?;
+            contents.push_str(&line);
+            contents.push('\n');
+        }
+
+        self.create_parent_dir()?;
+        std::fs::write(&self.path, contents).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!("Failed to write audit log: {}", e))
+        })?;
+        self.lines_on_disk = self.entries.len();
+
+        Ok(())
+    }
+
+    fn create_parent_dir(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ArchivistError::FileOperationFailed(format!(
+                    "Failed to create audit log directory: {}",
+                    e
+                ))
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Bounded, persistent log of requests to the embedded HTTP servers
+pub struct RequestAuditLog {
+    entries: Mutex<VecDeque<AuditEntry>>,
+    max_entries: usize,
+    /// Queue to the writer thread and its handle (None = in-memory only)
+    writer: Option<(mpsc::Sender<AuditWrite>, std::thread::JoinHandle<()>)>,
+}
+
+impl RequestAuditLog {
+    /// Create an audit log that is never written to disk
+    pub fn in_memory(max_entries: usize) -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::new()),
+            max_entries: max_entries.max(1),
+            writer: None,
+        }
+    }
+
+    /// Create an audit log backed by the default file, loading previous entries
+    pub fn load_default() -> Self {
+        let path = dirs::data_dir()
+            .map(|p| p.join("archivist").join("request-audit.jsonl"))
+            .unwrap_or_else(|| PathBuf::from("request-audit.jsonl"));
+        Self::with_file(path, DEFAULT_MAX_AUDIT_ENTRIES)
+    }
+
+    /// Create an audit log backed by the given file
+    pub fn with_file(path: PathBuf, max_entries: usize) -> Self {
+        let max_entries = max_entries.max(1);
+        let (entries, lines_on_disk) = match Self::load_entries(&path, max_entries) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                log::warn!("Failed to load request audit log, starting empty: {}", e);
+                (VecDeque::new(), 0)
+            }
+        };
+
+        let writer = AuditWriter {
+            path,
+            max_entries,
+            entries: entries.clone(),
+            lines_on_disk,
+        };
+        let (tx, rx) = mpsc::channel();
+        let writer = std::thread::Builder::new()
+            .name("request-audit-writer".to_string())
+            .spawn(move || writer.run(rx))
+            .map(|handle| (tx, handle))
+            .map_err(|e| log::warn!("Failed to start request audit writer: {}", e))
+            .ok();
+
+        Self {
+            entries: Mutex::new(entries),
+            max_entries,
+            writer,
+        }
+    }
+
+    /// Read the newest `max_entries` entries from disk, skipping corrupt lines
+    fn load_entries(path: &Path, max_entries: usize) -> Result<(VecDeque<AuditEntry>, usize)> {
+        if !path.exists() {
+            return Ok((VecDeque::new(), 0));
+        }
+
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!("Failed to read request audit log: {}", e))
+        })?;
+
+        let mut entries = VecDeque::new();
+        let mut lines = 0;
+        for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+            lines += 1;
+            if let Ok(entry) = serde_json::from_str::<AuditEntry>(line) {
+                if entries.len() == max_entries {
+                    entries.pop_front();
+                }
+                entries.push_back(entry);
+            }
+        }
+
+        Ok((entries, lines))
+    }
+
+    /// Record a request, evicting the oldest entry once the log is full. The
+    /// entry is written to disk in the background.
+    pub fn record(&self, entry: AuditEntry) {
+        {
+            let Ok(mut entries) = self.entries.lock() else {
+                return;
+            };
+            if entries.len() == self.max_entries {
+                entries.pop_front();
+            }
+            entries.push_back(entry.clone());
+        }
+
+        self.queue_write(AuditWrite::Append(entry));
+    }
+
+    fn queue_write(&self, write: AuditWrite) {
+        if let Some((tx, _)) = &self.writer {
+            if tx.send(write).is_err() {
+                log::warn!("Request audit writer has stopped, entry not persisted");
+            }
+        }
+    }
+
+    /// Get matching entries, newest first
+    pub fn query(&self, query: &AuditLogQuery) -> Vec<AuditEntry> {
+        let Ok(entries) = self.entries.lock() else {
+            return Vec::new();
+        };
+
+        entries
+            .iter()
+            .rev()
+            .filter(|e| query.server.map_or(true, |s| e.server == s))
+            .filter(|e| query.outcome.map_or(true, |o| e.outcome == o))
+            .filter(|e| {
+                query
+                    .remote_ip
+                    .as_ref()
+                    .map_or(true, |ip| e.remote_ip.as_ref() == Some(ip))
+            })
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    /// Remove all entries from memory and disk
+    pub fn clear(&self) -> Result<()> {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+        self.queue_write(AuditWrite::Clear);
+        Ok(())
+    }
+}
+
+impl Drop for RequestAuditLog {
+    /// Let the writer thread finish queued writes
+    fn drop(&mut self) {
+        if let Some((tx, handle)) = self.writer.take() {
+            drop(tx);
+            let _ = handle.join();
+        }
+    }
+}
+
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Per-IP token bucket rate limiter
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    buckets: HashMap<IpAddr, TokenBucket>,
+}
+
+impl RateLimiter {
+    pub fn new(settings: &RateLimitSettings) -> Self {
+        Self {
+            capacity: settings.burst.max(1) as f64,
+            refill_per_sec: settings.requests_per_minute as f64 / 60.0,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Take a token for this IP, returning false if it is over the limit
+    pub fn check(&mut self, ip: IpAddr) -> bool {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&mut self, ip: IpAddr, now: Instant) -> bool {
+        // A sustained rate of zero means the limiter is disabled
+        if self.refill_per_sec <= 0.0 {
+            return true;
+        }
+
+        if self.buckets.len() >= MAX_TRACKED_IPS && !self.buckets.contains_key(&ip) {
+            self.prune(now);
+        }
+
+        let capacity = self.capacity;
+        let refill_per_sec = self.refill_per_sec;
+        let bucket = self.buckets.entry(ip).or_insert(TokenBucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * refill_per_sec).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Drop buckets that have refilled completely (their IPs are idle)
+    fn prune(&mut self, now: Instant) {
+        let full_after = Duration::from_secs_f64(self.capacity / self.refill_per_sec);
+        self.buckets
+            .retain(|_, b| now.saturating_duration_since(b.last_refill) < full_after);
+    }
+}
+
+/// Rejection returned when a client exceeds its rate limit
+#[derive(Debug)]
+pub struct RateLimitedError;
+impl warp::reject::Reject for RateLimitedError {}
+
+/// Rate limiting and auditing for one embedded server
+#[derive(Clone)]
+pub struct RequestGuard {
+    server: ServerKind,
+    rate_limit: RateLimitSettings,
+    limiter: Arc<Mutex<RateLimiter>>,
+    audit_log: Arc<RequestAuditLog>,
+}
+
+impl RequestGuard {
+    pub fn new(
+        server: ServerKind,
+        rate_limit: &RateLimitSettings,
+        audit_log: Arc<RequestAuditLog>,
+    ) -> Self {
+        Self {
+            server,
+            rate_limit: rate_limit.clone(),
+            limiter: Arc::new(Mutex::new(RateLimiter::new(rate_limit))),
+            audit_log,
+        }
+    }
+
+    /// The rate limit this guard enforces
+    pub fn rate_limit_settings(&self) -> &RateLimitSettings {
+        &self.rate_limit
+    }
+
+    /// A guard for the same server and audit log with a different rate limit
+    pub fn with_rate_limit(&self, rate_limit: &RateLimitSettings) -> Self {
+        Self::new(self.server, rate_limit, self.audit_log.clone())
+    }
+
+    /// Filter that rejects with `RateLimitedError` when the client IP is over its limit
+    pub fn rate_limit(&self) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+        let limiter = self.limiter.clone();
+        let server = self.server;
+
+        warp::addr::remote()
+            .and_then(move |addr: Option<SocketAddr>| {
+                let limiter = limiter.clone();
+                async move {
+                    let ip = addr.map(|a| a.ip()).unwrap_or(IpAddr::from([0, 0, 0, 0]));
+                    let allowed = limiter
+                        .lock()
+                        .map(|mut limiter| limiter.check(ip))
+                        .unwrap_or(true);
+
+                    if allowed {
+                        Ok(())
+                    } else {
+                        log::warn!("{:?} request from {} rejected: rate limited", server, ip);
+                        Err(warp::reject::custom(RateLimitedError))
+                    }
+                }
+            })
+            .untuple_one()
+    }
+
+    /// Wrapper that records every answered request in the audit log
+    /// (health checks and CORS preflights are skipped)
+    pub fn audit(&self) -> warp::log::Log<impl Fn(warp::log::Info<'_>) + Clone + Send + Sync> {
+        let audit_log = self.audit_log.clone();
+        let server = self.server;
+
+        warp::log::custom(move |info| {
+            if info.path() == "/health" || info.method() == warp::http::Method::OPTIONS {
+                return;
+            }
+
+            let status = info.status().as_u16();
+            audit_log.record(AuditEntry {
+                timestamp: Utc::now(),
+                server,
+                remote_ip: info.remote_addr().map(|a| a.ip().to_string()),
+                method: info.method().to_string(),
+                path: info.path().to_string(),
+                status,
+                outcome: AuditOutcome::from_status(status),
+                duration_ms: info.elapsed().as_millis() as u64,
+            });
+        })
+    }
+}
+
+/// JSON reply for requests rejected by the rate limiter
+pub fn rate_limited_reply() -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({
+            "error": "Too Many Requests",
+            "message": "Rate limit exceeded, slow down and retry later"
+        })),
+        warp::http::StatusCode::TOO_MANY_REQUESTS,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(ip: &str, status: u16) -> AuditEntry {
+        AuditEntry {
+            timestamp: Utc::now(),
+            server: ServerKind::ManifestServer,
+            remote_ip: Some(ip.to_string()),
+            method: "GET".to_string(),
+            path: "/manifests".to_string(),
+            status,
+            outcome: AuditOutcome::from_status(status),
+            duration_ms: 1,
+        }
+    }
+
+    #[test]
+    fn test_rate_limiter_burst_and_refill() {
+        let mut limiter = RateLimiter::new(&RateLimitSettings {
+            requests_per_minute: 60,
+            burst: 2,
+        });
+        let ip: IpAddr = "192.168.1.10".parse().unwrap();
+        let other: IpAddr = "192.168.1.11".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.check_at(ip, start));
+        assert!(limiter.check_at(ip, start));
+        assert!(!limiter.check_at(ip, start));
+
+        // Other clients have their own bucket
+        assert!(limiter.check_at(other, start));
+
+        // One request per second refills
+        assert!(limiter.check_at(ip, start + Duration::from_secs(1)));
+        assert!(!limiter.check_at(ip, start + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_rate_limiter_zero_rate_is_unlimited() {
+        let mut limiter = RateLimiter::new(&RateLimitSettings {
+            requests_per_minute: 0,
+            burst: 1,
+        });
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        for _ in 0..100 {
+            assert!(limiter.check(ip));
+        }
+    }
+
+    #[test]
+    fn test_audit_log_bounded_and_persisted() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("request-audit.jsonl");
+
+        let log = RequestAuditLog::with_file(path.clone(), 3);
+        for i in 0..10 {
+            log.record(entry(&format!("10.0.0.{}", i), 200));
+        }
+        log.record(entry("10.0.0.99", 429));
+
+        let all = log.query(&AuditLogQuery::default());
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].remote_ip.as_deref(), Some("10.0.0.99"));
+        assert_eq!(all[0].outcome, AuditOutcome::RateLimited);
+
+        // Dropping the log waits for queued writes; compaction keeps the
+        // file from growing past twice the bound
+        drop(log);
+        let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert!(lines <= 6);
+
+        let reloaded = RequestAuditLog::with_file(path, 3);
+        let limited = reloaded.query(&AuditLogQuery {
+            outcome: Some(AuditOutcome::RateLimited),
+            ..Default::default()
+        });
+        assert_eq!(limited.len(), 1);
+        assert_eq!(reloaded.query(&AuditLogQuery::default()).len(), 3);
+    }
+}
@@ -0,0 +1,776 @@
+//! Folder Restore
+//!
+//! Rebuilds a backed-up folder on disk from one of its manifests. Each file
+//! entry is streamed out of the local node with `download_file_to_path`,
+//! fetching it from the network first if the node no longer holds it.
+//!
+//! Any retained snapshot (processed manifest sequence) of a folder can be
+//! restored, either whole or limited to selected files and directories.
+//!
+//! Jobs are persisted to `restore-jobs.json` so a restore interrupted by a
+//! restart or a cancel can be resumed. Files already restored, or already on
+//! disk with the size recorded in the manifest, are skipped.
+
+use crate::error::{ArchivistError, Result};
+use crate::node_api::NodeApiClient;
+use crate::services::backup_daemon::{fetch_manifest, ManifestFile, ManifestFileEntry};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::RwLock;
+
+/// Persist job progress after this many files (and always when a job ends)
+const SAVE_EVERY_FILES: u32 = 20;
+
+/// Suffix for files still being written; renamed into place once complete
+const PARTIAL_SUFFIX: &str = ".archivist-partial";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreStatus {
+    Running,
+    Completed,
+    CompletedWithErrors,
+    Cancelled,
+    /// The app stopped while the job was running; it can be resumed
+    Interrupted,
+}
+
+/// What to restore and where
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreRequest {
+    pub source_peer_id: String,
+    pub folder_id: String,
+    /// Manifest to restore from (None = latest processed manifest for the folder)
+    pub manifest_cid: Option<String>,
+    /// Directory the folder tree is rebuilt under
+    pub target_path: String,
+    /// Replace files that already exist at the target instead of skipping them
+    #[serde(default)]
+    pub overwrite_existing: bool,
+    /// Manifest paths (files or directories) to restore; None restores everything
+    #[serde(default)]
+    pub paths: Option<Vec<String>>,
+}
+
+/// A file as recorded in a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotFile {
+    pub path: String,
+    pub cid: String,
+    pub size_bytes: u64,
+    pub mime_type: Option<String>,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreFileFailure {
+    pub path: String,
+    pub cid: String,
+    pub error: String,
+}
+
+/// A restore operation and its progress
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreJob {
+    pub id: String,
+    pub manifest_cid: String,
+    pub source_peer_id: String,
+    pub folder_id: String,
+    pub sequence_number: u64,
+    pub target_path: String,
+    pub overwrite_existing: bool,
+    /// Paths selected for restore (None = whole folder)
+    #[serde(default)]
+    pub selected_paths: Option<Vec<String>>,
+    pub status: RestoreStatus,
+    pub total_files: u32,
+    pub total_bytes: u64,
+    pub files_restored: u32,
+    pub files_skipped: u32,
+    pub files_failed: u32,
+    pub bytes_restored: u64,
+    pub current_file: Option<String>,
+    /// Manifest paths that are done (restored or skipped), used when resuming
+    #[serde(default)]
+    pub completed_paths: HashSet<String>,
+    #[serde(default)]
+    pub failures: Vec<RestoreFileFailure>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Progress event payload emitted as `restore-progress`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreProgress {
+    pub job_id: String,
+    pub status: RestoreStatus,
+    pub total_files: u32,
+    pub files_done: u32,
+    pub files_failed: u32,
+    pub total_bytes: u64,
+    pub bytes_restored: u64,
+    pub current_file: Option<String>,
+}
+
+impl RestoreJob {
+    fn progress(&self) -> RestoreProgress {
+        RestoreProgress {
+            job_id: self.id.clone(),
+            status: self.status,
+            total_files: self.total_files,
+            files_done: self.files_restored + self.files_skipped,
+            files_failed: self.files_failed,
+            total_bytes: self.total_bytes,
+            bytes_restored: self.bytes_restored,
+            current_file: self.current_file.clone(),
+        }
+    }
+
+    fn is_resumable(&self) -> bool {
+        matches!(
+            self.status,
+            RestoreStatus::Interrupted
+                | RestoreStatus::Cancelled
+                | RestoreStatus::CompletedWithErrors
+        )
+    }
+}
+
+/// Restores backed-up folders from manifests to disk
+pub struct RestoreService {
+    api_client: NodeApiClient,
+    jobs: Arc<RwLock<HashMap<String, RestoreJob>>>,
+    cancel_flags: Arc<RwLock<HashMap<String, Arc<AtomicBool>>>>,
+    state_file_path: PathBuf,
+}
+
+impl RestoreService {
+    /// Create a restore service backed by the default state file
+    pub fn new(api_client: NodeApiClient) -> Self {
+        let path = dirs::data_dir()
+            .map(|p| p.join("archivist").join("restore-jobs.json"))
+            .unwrap_or_else(|| PathBuf::from("restore-jobs.json"));
+        Self::with_state_file(api_client, path)
+    }
+
+    /// Create a restore service backed by the given state file.
+    /// Jobs that were running when the app stopped are marked interrupted.
+    pub fn with_state_file(api_client: NodeApiClient, path: PathBuf) -> Self {
+        let mut jobs = Self::load_state(&path).unwrap_or_else(|e| {
+            log::warn!("Failed to load restore jobs, starting empty: {}", e);
+            HashMap::new()
+        });
+
+        for job in jobs.values_mut() {
+            if job.status == RestoreStatus::Running {
+                job.status = RestoreStatus::Interrupted;
+                job.current_file = None;
+            }
+        }
+
+        Self {
+            api_client,
+            jobs: Arc::new(RwLock::new(jobs)),
+            cancel_flags: Arc::new(RwLock::new(HashMap::new())),
+            state_file_path: path,
+        }
+    }
+
+    /// Load restore jobs from disk
+    fn load_state(path: &Path) -> Result<HashMap<String, RestoreJob>> {
+        if !path.exists() {
+            return Ok(HashMap::new());
+        }
+
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!("Failed to read restore jobs: {}", e))
+        })?;
+
+        let jobs: HashMap<String, RestoreJob> =
+            serde_json::from_str(&contents).map_err(ArchivistError::SerializationError)?;
+
+        log::info!("Loaded {} restore jobs", jobs.len());
+        Ok(jobs)
+    }
+
+    /// Save restore jobs to disk
+    async fn save_state(&self) -> Result<()> {
+        let jobs = self.jobs.read().await;
+
+        if let Some(parent) = self.state_file_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ArchivistError::FileOperationFailed(format!(
+                    "Failed to create state directory: {}",
+                    e
+                ))
+            })?;
+        }
+
+        let json =
+            serde_json::to_string_pretty(&*jobs).map_err(ArchivistError::SerializationError)?;
+
+        std::fs::write(&self.state_file_path, json).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!("Failed to write restore jobs: {}", e))
+        })?;
+
+        Ok(())
+    }
+
+    /// List the files in a snapshot, sorted by path
+    pub async fn snapshot_files(&self, manifest_cid: &str) -> Result<Vec<SnapshotFile>> {
+        let manifest = fetch_manifest(&self.api_client, manifest_cid).await?;
+        let mut files: Vec<SnapshotFile> = manifest
+            .files
+            .into_iter()
+            .map(|f| SnapshotFile {
+                path: f.path,
+                cid: f.cid,
+                size_bytes: f.size_bytes,
+                mime_type: f.mime_type,
+                uploaded_at: f.uploaded_at,
+            })
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(files)
+    }
+
+    /// Get all restore jobs, newest first
+    pub async fn get_jobs(&self) -> Vec<RestoreJob> {
+        let jobs = self.jobs.read().await;
+        let mut list: Vec<RestoreJob> = jobs.values().cloned().collect();
+        list.sort_by_key(|j| std::cmp::Reverse(j.started_at));
+        list
+    }
+
+    /// Start restoring a folder from the given manifest (runs in background)
+    pub async fn start_restore(
+        self: &Arc<Self>,
+        app_handle: AppHandle,
+        request: RestoreRequest,
+        manifest_cid: &str,
+    ) -> Result<RestoreJob> {
+        let target = PathBuf::from(&request.target_path);
+        if request.target_path.trim().is_empty() {
+            return Err(ArchivistError::RestoreError(
+                "No target path given".to_string(),
+            ));
+        }
+
+        {
+            let jobs = self.jobs.read().await;
+            if jobs
+                .values()
+                .any(|j| j.status == RestoreStatus::Running && Path::new(&j.target_path) == target)
+            {
+                return Err(ArchivistError::RestoreError(format!(
+                    "A restore into {} is already running",
+                    request.target_path
+                )));
+            }
+        }
+
+        let manifest = fetch_manifest(&self.api_client, manifest_cid).await?;
+        if manifest.folder_id != request.folder_id {
+            return Err(ArchivistError::RestoreError(format!(
+                "Manifest {} belongs to folder {}, not {}",
+                manifest_cid, manifest.folder_id, request.folder_id
+            )));
+        }
+
+        let selection = request.paths.as_deref();
+        let selected: Vec<&ManifestFileEntry> = manifest
+            .files
+            .iter()
+            .filter(|f| path_selected(&f.path, selection))
+            .collect();
+        if selected.is_empty() {
+            return Err(ArchivistError::RestoreError(
+                "None of the selected paths are in this snapshot".to_string(),
+            ));
+        }
+
+        std::fs::create_dir_all(&target).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!(
+                "Failed to create restore directory: {}",
+                e
+            ))
+        })?;
+
+        let job = RestoreJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            manifest_cid: manifest_cid.to_string(),
+            source_peer_id: request.source_peer_id,
+            folder_id: request.folder_id,
+            sequence_number: manifest.sequence_number,
+            target_path: request.target_path,
+            overwrite_existing: request.overwrite_existing,
+            selected_paths: request.paths.clone(),
+            status: RestoreStatus::Running,
+            total_files: selected.len() as u32,
+            total_bytes: selected.iter().map(|f| f.size_bytes).sum(),
+            files_restored: 0,
+            files_skipped: 0,
+            files_failed: 0,
+            bytes_restored: 0,
+            current_file: None,
+            completed_paths: HashSet::new(),
+            failures: Vec::new(),
+            started_at: Utc::now(),
+            finished_at: None,
+        };
+
+        log::info!(
+            "Starting restore {} of folder {} (manifest {}, seq {}, {} files) into {}",
+            job.id,
+            job.folder_id,
+            job.manifest_cid,
+            job.sequence_number,
+            job.total_files,
+            job.target_path
+        );
+
+        self.jobs.write().await.insert(job.id.clone(), job.clone());
+        self.save_state().await?;
+        self.spawn_job(app_handle, job.id.clone(), manifest).await;
+
+        Ok(job)
+    }
+
+    /// Resume an interrupted, cancelled or partially failed restore.
+    /// Failed files are retried; completed ones are skipped.
+    pub async fn resume_restore(
+        self: &Arc<Self>,
+        app_handle: AppHandle,
+        job_id: &str,
+    ) -> Result<RestoreJob> {
+        let manifest_cid = {
+            let jobs = self.jobs.read().await;
+            let job = jobs.get(job_id).ok_or_else(|| {
+                ArchivistError::RestoreError(format!("Restore job not found: {}", job_id))
+            })?;
+            if !job.is_resumable() {
+                return Err(ArchivistError::RestoreError(format!(
+                    "Restore job {} cannot be resumed ({:?})",
+                    job_id, job.status
+                )));
+            }
+            job.manifest_cid.clone()
+        };
+
+        let manifest = fetch_manifest(&self.api_client, &manifest_cid).await?;
+
+        let job = {
+            let mut jobs = self.jobs.write().await;
+            let job = jobs.get_mut(job_id).ok_or_else(|| {
+                ArchivistError::RestoreError(format!("Restore job not found: {}", job_id))
+            })?;
+            job.status = RestoreStatus::Running;
+            job.files_failed = 0;
+            job.failures.clear();
+            job.finished_at = None;
+            job.clone()
+        };
+
+        log::info!(
+            "Resuming restore {} ({} of {} files done)",
+            job.id,
+            job.completed_paths.len(),
+            job.total_files
+        );
+
+        self.save_state().await?;
+        self.spawn_job(app_handle, job.id.clone(), manifest).await;
+
+        Ok(job)
+    }
+
+    /// Ask a running restore to stop after the current file
+    pub async fn cancel_restore(&self, job_id: &str) -> Result<()> {
+        let flags = self.cancel_flags.read().await;
+        match flags.get(job_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                log::info!("Cancelling restore {}", job_id);
+                Ok(())
+            }
+            None => Err(ArchivistError::RestoreError(format!(
+                "Restore job {} is not running",
+                job_id
+            ))),
+        }
+    }
+
+    async fn spawn_job(
+        self: &Arc<Self>,
+        app_handle: AppHandle,
+        job_id: String,
+        manifest: ManifestFile,
+    ) {
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.cancel_flags
+            .write()
+            .await
+            .insert(job_id.clone(), cancel.clone());
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            service
+                .run_job(&app_handle, &job_id, &manifest, &cancel)
+                .await;
+            service.cancel_flags.write().await.remove(&job_id);
+        });
+    }
+
+    /// Restore every file in the manifest that is not already done
+    async fn run_job(
+        &self,
+        app_handle: &AppHandle,
+        job_id: &str,
+        manifest: &ManifestFile,
+        cancel: &AtomicBool,
+    ) {
+        let Some((target, overwrite, completed, selection)) =
+            self.jobs.read().await.get(job_id).map(|j| {
+                (
+                    PathBuf::from(&j.target_path),
+                    j.overwrite_existing,
+                    j.completed_paths.clone(),
+                    j.selected_paths.clone(),
+                )
+            })
+        else {
+            return;
+        };
+
+        let mut since_save = 0;
+
+        for entry in manifest
+            .files
+            .iter()
+            .filter(|f| path_selected(&f.path, selection.as_deref()))
+        {
+            if cancel.load(Ordering::Relaxed) {
+                self.finish_job(app_handle, job_id, RestoreStatus::Cancelled)
+                    .await;
+                return;
+            }
+
+            if completed.contains(&entry.path) {
+                continue;
+            }
+
+            self.update_job(app_handle, job_id, |job| {
+                job.current_file = Some(entry.path.clone());
+            })
+            .await;
+
+            let outcome = match resolve_restore_path(&target, &entry.path) {
+                Ok(dest) => {
+                    if !overwrite && file_matches(&dest, entry.size_bytes) {
+                        Ok(false)
+                    } else {
+                        restore_file(&self.api_client, entry, &dest)
+                            .await
+                            .map(|_| true)
+                    }
+                }
+                Err(e) => Err(e),
+            };
+
+            self.update_job(app_handle, job_id, |job| match &outcome {
+                Ok(restored) => {
+                    if *restored {
+                        job.files_restored += 1;
+                        job.bytes_restored += entry.size_bytes;
+                    } else {
+                        job.files_skipped += 1;
+                    }
+                    job.completed_paths.insert(entry.path.clone());
+                }
+                Err(e) => {
+                    log::error!("Failed to restore {} ({}): {}", entry.path, entry.cid, e);
+                    job.files_failed += 1;
+                    job.failures.push(RestoreFileFailure {
+                        path: entry.path.clone(),
+                        cid: entry.cid.clone(),
+                        error: e.to_string(),
+                    });
+                }
+            })
+            .await;
+
+            since_save += 1;
+            if since_save >= SAVE_EVERY_FILES {
+                since_save = 0;
+                if let Err(e) = self.save_state().await {
+                    log::warn!("Failed to save restore progress: {}", e);
+                }
+            }
+        }
+
+        let failed = self
+            .jobs
+            .read()
+            .await
+            .get(job_id)
+            .map(|j| j.files_failed)
+            .unwrap_or(0);
+        let status = if failed > 0 {
+            RestoreStatus::CompletedWithErrors
+        } else {
+            RestoreStatus::Completed
+        };
+        self.finish_job(app_handle, job_id, status).await;
+    }
+
+    /// Apply a change to a job and emit its progress
+    async fn update_job(
+        &self,
+        app_handle: &AppHandle,
+        job_id: &str,
+        update: impl FnOnce(&mut RestoreJob),
+    ) {
+        let progress = {
+            let mut jobs = self.jobs.write().await;
+            let Some(job) = jobs.get_mut(job_id) else {
+                return;
+            };
+            update(job);
+            job.progress()
+        };
+        let _ = app_handle.emit("restore-progress", progress);
+    }
+
+    async fn finish_job(&self, app_handle: &AppHandle, job_id: &str, status: RestoreStatus) {
+        self.update_job(app_handle, job_id, |job| {
+            job.status = status;
+            job.current_file = None;
+            job.finished_at = Some(Utc::now());
+            log::info!(
+                "Restore {} finished ({:?}): {} restored, {} skipped, {} failed",
+                job.id,
+                status,
+                job.files_restored,
+                job.files_skipped,
+                job.files_failed
+            );
+        })
+        .await;
+
+        if let Err(e) = self.save_state().await {
+            log::warn!("Failed to save restore jobs: {}", e);
+        }
+    }
+}
+
+/// Map a manifest path onto the restore target, refusing anything that would
+/// escape it (manifests come from another machine)
+fn resolve_restore_path(target: &Path, manifest_path: &str) -> Result<PathBuf> {
+    let mut dest = target.to_path_buf();
+    let mut depth = 0;
+
+    // Manifests may come from Windows or Unix peers, so accept either separator
+    for part in manifest_path.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => {
+                return Err(ArchivistError::RestoreError(format!(
+                    "Refusing to restore path outside target: {}",
+                    manifest_path
+                )))
+            }
+            _ => {
+                let mut components = Path::new(part).components();
+                if !matches!(
+                    (components.next(), components.next()),
+                    (Some(Component::Normal(_)), None)
+                ) || (depth == 0 && part.ends_with(':'))
+                {
+                    return Err(ArchivistError::RestoreError(format!(
+                        "Invalid path in manifest: {}",
+                        manifest_path
+                    )));
+                }
+                dest.push(part);
+                depth += 1;
+            }
+        }
+    }
+
+    if depth == 0 {
+        return Err(ArchivistError::RestoreError(format!(
+            "Empty path in manifest: {:?}",
+            manifest_path
+        )));
+    }
+
+    Ok(dest)
+}
+
+/// Whether a manifest path is covered by the selection (an exact file path or
+/// a directory prefix). Separators are normalized so either style matches.
+fn path_selected(path: &str, selection: Option<&[String]>) -> bool {
+    let Some(selection) = selection else {
+        return true;
+    };
+
+    let path = path.replace('\\', "/");
+    let path = path.trim_start_matches('/');
+    selection.iter().any(|selected| {
+        let selected = selected.replace('\\', "/");
+        let selected = selected.trim_matches('/');
+        selected.is_empty()
+            || path == selected
+            || (path.starts_with(selected) && path[selected.len()..].starts_with('/'))
+    })
+}
+
+/// Whether a file already exists at `path` with the expected size
+fn file_matches(path: &Path, size_bytes: u64) -> bool {
+    std::fs::metadata(path)
+        .map(|m| m.is_file() && m.len() == size_bytes)
+        .unwrap_or(false)
+}
+
+/// Stream one file out of the node into place, fetching it from the network
+/// if the node does not hold it
+async fn restore_file(
+    api_client: &NodeApiClient,
+    entry: &ManifestFileEntry,
+    dest: &Path,
+) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!("Failed to create directory: {}", e))
+        })?;
+    }
+
+    let mut partial = dest.as_os_str().to_owned();
+    partial.push(PARTIAL_SUFFIX);
+    let partial = PathBuf::from(partial);
+
+    if let Err(e) = api_client.download_file_to_path(&entry.cid, &partial).await {
+        log::info!(
+            "{} not available locally ({}), fetching from network",
+            entry.cid,
+            e
+        );
+        api_client.request_network_download(&entry.cid).await?;
+        api_client
+            .download_file_to_path(&entry.cid, &partial)
+            .await?;
+    }
+
+    let written = std::fs::metadata(&partial).map(|m| m.len()).unwrap_or(0);
+    if written != entry.size_bytes {
+        let _ = std::fs::remove_file(&partial);
+        return Err(ArchivistError::RestoreError(format!(
+            "Size mismatch for {}: expected {} bytes, got {}",
+            entry.path, entry.size_bytes, written
+        )));
+    }
+
+    // rename() does not replace an existing file on Windows
+    if dest.exists() {
+        std::fs::remove_file(dest).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!("Failed to replace file: {}", e))
+        })?;
+    }
+    std::fs::rename(&partial, dest).map_err(|e| {
+        ArchivistError::FileOperationFailed(format!("Failed to move restored file: {}", e))
+    })?;
+
+    log::debug!("Restored {} ({})", entry.path, entry.cid);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_resolve_restore_path() {
+        let target = Path::new("/restore");
+
+        assert_eq!(
+            resolve_restore_path(target, "photos/2024/a.jpg").unwrap(),
+            target.join("photos").join("2024").join("a.jpg")
+        );
+        assert_eq!(
+            resolve_restore_path(target, "docs\\report.pdf").unwrap(),
+            target.join("docs").join("report.pdf")
+        );
+        assert_eq!(
+            resolve_restore_path(target, "/leading/slash.txt").unwrap(),
+            target.join("leading").join("slash.txt")
+        );
+
+        assert!(resolve_restore_path(target, "../escape.txt").is_err());
+        assert!(resolve_restore_path(target, "a/../../escape.txt").is_err());
+        assert!(resolve_restore_path(target, "C:\\Windows\\evil.dll").is_err());
+        assert!(resolve_restore_path(target, "").is_err());
+    }
+
+    #[test]
+    fn test_path_selected() {
+        let selection = vec!["photos/2024".to_string(), "notes.txt".to_string()];
+        let selection = Some(selection.as_slice());
+
+        assert!(path_selected("photos/2024/a.jpg", selection));
+        assert!(path_selected("photos\\2024\\b.jpg", selection));
+        assert!(path_selected("notes.txt", selection));
+        assert!(!path_selected("photos/2024-old/a.jpg", selection));
+        assert!(!path_selected("docs/notes.txt", selection));
+        assert!(path_selected("anything", None));
+    }
+
+    #[test]
+    fn test_running_jobs_marked_interrupted_on_load() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("restore-jobs.json");
+
+        let job = RestoreJob {
+            id: "job-1".to_string(),
+            manifest_cid: "zManifest".to_string(),
+            source_peer_id: "peer".to_string(),
+            folder_id: "folder".to_string(),
+            sequence_number: 3,
+            target_path: temp_dir.path().to_string_lossy().to_string(),
+            overwrite_existing: false,
+            selected_paths: None,
+            status: RestoreStatus::Running,
+            total_files: 10,
+            total_bytes: 1000,
+            files_restored: 4,
+            files_skipped: 0,
+            files_failed: 0,
+            bytes_restored: 400,
+            current_file: Some("a.txt".to_string()),
+            completed_paths: ["x.txt".to_string()].into_iter().collect(),
+            failures: Vec::new(),
+            started_at: Utc::now(),
+            finished_at: None,
+        };
+        let mut jobs = HashMap::new();
+        jobs.insert(job.id.clone(), job);
+        std::fs::write(&path, serde_json::to_string(&jobs).unwrap()).unwrap();
+
+        let service = RestoreService::with_state_file(NodeApiClient::new(8080), path);
+        let jobs = tokio_test::block_on(service.get_jobs());
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].status, RestoreStatus::Interrupted);
+        assert!(jobs[0].is_resumable());
+        assert!(jobs[0].completed_paths.contains("x.txt"));
+    }
+}
@@ -0,0 +1,509 @@
+//! Integrity Scrubber
+//!
+//! Periodically walks the latest retained snapshot of every backed-up folder
+//! and checks that each CID is still held by the local node and reads back in
+//! full with the size recorded in the manifest. Missing or unreadable CIDs are
+//! fetched again from the network, after connecting to the source peer.
+//!
+//! The result is a per-folder health report, persisted to
+//! `integrity-report.json` so the last report survives a restart.
+
+use crate::error::{ArchivistError, Result};
+use crate::node_api::NodeApiClient;
+use crate::services::backup_daemon::{
+    fetch_manifest, filter_manifest_paths, BackupDaemon, ProcessedManifest,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tokio::time::{Duration, Instant};
+
+/// Delay before the first scheduled scrub, so it doesn't compete with startup
+const FIRST_SCRUB_DELAY: Duration = Duration::from_secs(10 * 60);
+
+/// How often the schedule checks whether a scrub is due
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FolderHealth {
+    /// Every file was present and readable
+    Healthy,
+    /// Some files were missing or unreadable but have been fetched again
+    Repaired,
+    /// Some files (or the manifest itself) could not be recovered
+    Damaged,
+}
+
+/// A file that failed its check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrubFileIssue {
+    pub path: String,
+    pub cid: String,
+    pub problem: String,
+    /// Whether refetching from the source fixed it
+    pub repaired: bool,
+}
+
+/// Scrub result for the latest snapshot of one folder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderHealthReport {
+    pub source_peer_id: String,
+    pub folder_id: String,
+    pub manifest_cid: String,
+    pub sequence_number: u64,
+    pub health: FolderHealth,
+    pub total_files: u32,
+    pub files_ok: u32,
+    pub files_repaired: u32,
+    pub files_damaged: u32,
+    pub issues: Vec<ScrubFileIssue>,
+    /// Set when the folder could not be checked at all
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Latest integrity report across all backed-up folders
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    pub running: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub folders: Vec<FolderHealthReport>,
+}
+
+impl IntegrityReport {
+    /// Replace the entry for the report's folder, or add it
+    fn upsert_folder(&mut self, report: FolderHealthReport) {
+        match self
+            .folders
+            .iter_mut()
+            .find(|f| f.source_peer_id == report.source_peer_id && f.folder_id == report.folder_id)
+        {
+            Some(existing) => *existing = report,
+            None => self.folders.push(report),
+        }
+    }
+}
+
+/// Checks backed-up content in local storage and repairs what it can
+pub struct IntegrityScrubber {
+    api_client: NodeApiClient,
+    daemon: Arc<BackupDaemon>,
+    report: RwLock<IntegrityReport>,
+    running: AtomicBool,
+    report_file_path: PathBuf,
+}
+
+impl IntegrityScrubber {
+    /// Create a scrubber backed by the default report file
+    pub fn new(api_client: NodeApiClient, daemon: Arc<BackupDaemon>) -> Self {
+        let path = dirs::data_dir()
+            .map(|p| p.join("archivist").join("integrity-report.json"))
+            .unwrap_or_else(|| PathBuf::from("integrity-report.json"));
+
+        let mut report = Self::load_report(&path).unwrap_or_else(|e| {
+            log::warn!("Failed to load integrity report, starting empty: {}", e);
+            IntegrityReport::default()
+        });
+        report.running = false;
+
+        Self {
+            api_client,
+            daemon,
+            report: RwLock::new(report),
+            running: AtomicBool::new(false),
+            report_file_path: path,
+        }
+    }
+
+    /// Load the last report from disk
+    fn load_report(path: &Path) -> Result<IntegrityReport> {
+        if !path.exists() {
+            return Ok(IntegrityReport::default());
+        }
+
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!("Failed to read integrity report: {}", e))
+        })?;
+
+        serde_json::from_str(&contents).map_err(ArchivistError::SerializationError)
+    }
+
+    /// Save the report to disk
+    async fn save_report(&self) -> Result<()> {
+        let report = self.report.read().await;
+
+        if let Some(parent) = self.report_file_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ArchivistError::FileOperationFailed(format!(
+                    "Failed to create state directory: {}",
+                    e
+                ))
+            })?;
+        }
+
+        let json =
+            serde_json::to_string_pretty(&*report).map_err(ArchivistError::SerializationError)?;
+
+        std::fs::write(&self.report_file_path, json).map_err(|e| {
+            ArchivistError::FileOperationFailed(format!("Failed to write integrity report: {}", e))
+        })?;
+
+        Ok(())
+    }
+
+    /// Get the latest report (partial while a scrub is running)
+    pub async fn get_report(&self) -> IntegrityReport {
+        self.report.read().await.clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Scrub every few hours while the backup daemon is enabled. The
+    /// interval is read from the daemon's settings before each check, so a
+    /// changed interval (0 disables scheduled scrubbing) applies without a
+    /// restart.
+    pub async fn run_scheduled(self: Arc<Self>) {
+        tokio::time::sleep(FIRST_SCRUB_DELAY).await;
+
+        let mut last_scrub: Option<Instant> = None;
+        let mut logged_interval = None;
+        loop {
+            let interval_hours = self.daemon.scrub_interval_hours();
+            if logged_interval != Some(interval_hours) {
+                if interval_hours == 0 {
+                    log::info!("Scheduled integrity scrubbing is disabled");
+                } else {
+                    log::info!("Integrity scrub scheduled every {}h", interval_hours);
+                }
+                logged_interval = Some(interval_hours);
+            }
+
+            let now = Instant::now();
+            if scrub_due(interval_hours, last_scrub, now) {
+                last_scrub = Some(now);
+                if self.daemon.is_enabled() {
+                    if let Err(e) = self.run().await {
+                        log::warn!("Integrity scrub failed: {}", e);
+                    }
+                }
+            }
+            tokio::time::sleep(SCHEDULE_CHECK_INTERVAL).await;
+        }
+    }
+
+    /// Check the latest snapshot of every backed-up folder
+    pub async fn run(&self) -> Result<IntegrityReport> {
+        if self
+            .running
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Err(ArchivistError::SyncError(
+                "An integrity scrub is already running".to_string(),
+            ));
+        }
+
+        let result = self.scrub_all().await;
+
+        {
+            let mut report = self.report.write().await;
+            report.running = false;
+            report.finished_at = Some(Utc::now());
+        }
+        self.running.store(false, Ordering::SeqCst);
+        self.save_report().await?;
+
+        result?;
+        Ok(self.get_report().await)
+    }
+
+    async fn scrub_all(&self) -> Result<()> {
+        let snapshots = self.daemon.latest_snapshots().await;
+        log::info!("Starting integrity scrub of {} folders", snapshots.len());
+
+        {
+            let mut report = self.report.write().await;
+            report.running = true;
+            report.started_at = Some(Utc::now());
+            report.finished_at = None;
+            // Drop folders that are no longer backed up
+            report.folders.retain(|f| {
+                snapshots
+                    .iter()
+                    .any(|s| s.source_peer_id == f.source_peer_id && s.folder_id == f.folder_id)
+            });
+        }
+
+        let local: HashSet<String> = self
+            .api_client
+            .list_data()
+            .await?
+            .content
+            .into_iter()
+            .map(|item| item.cid)
+            .collect();
+
+        for snapshot in &snapshots {
+            let folder = self.scrub_folder(snapshot, &local).await;
+            match folder.health {
+                FolderHealth::Healthy => log::info!(
+                    "Folder {} is healthy ({} files)",
+                    folder.folder_id,
+                    folder.total_files
+                ),
+                FolderHealth::Repaired => log::warn!(
+                    "Folder {}: repaired {} of {} files",
+                    folder.folder_id,
+                    folder.files_repaired,
+                    folder.total_files
+                ),
+                FolderHealth::Damaged => log::error!(
+                    "Folder {} is damaged: {} files unrecoverable{}",
+                    folder.folder_id,
+                    folder.files_damaged,
+                    folder
+                        .error
+                        .as_deref()
+                        .map(|e| format!(" ({})", e))
+                        .unwrap_or_default()
+                ),
+            }
+
+            self.report.write().await.upsert_folder(folder);
+            if let Err(e) = self.save_report().await {
+                log::warn!("Failed to save integrity report: {}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check (and repair) every file of one snapshot
+    async fn scrub_folder(
+        &self,
+        snapshot: &ProcessedManifest,
+        local: &HashSet<String>,
+    ) -> FolderHealthReport {
+        let mut folder = FolderHealthReport {
+            source_peer_id: snapshot.source_peer_id.clone(),
+            folder_id: snapshot.folder_id.clone(),
+            manifest_cid: snapshot.manifest_cid.clone(),
+            sequence_number: snapshot.sequence_number,
+            health: FolderHealth::Healthy,
+            total_files: 0,
+            files_ok: 0,
+            files_repaired: 0,
+            files_damaged: 0,
+            issues: Vec::new(),
+            error: None,
+            checked_at: Utc::now(),
+        };
+
+        // Only the paths the snapshot was mirrored with are expected locally
+        let manifest = match fetch_manifest(&self.api_client, &snapshot.manifest_cid).await {
+            Ok(mut manifest) => {
+                filter_manifest_paths(&mut manifest, &snapshot.path_prefixes);
+                manifest
+            }
+            Err(e) => {
+                folder.error = Some(format!("Manifest unavailable: {}", e));
+                folder.health = folder_health(&folder);
+                return folder;
+            }
+        };
+
+        let mut seen = HashSet::new();
+        let mut connected = false;
+        for file in &manifest.files {
+            if !seen.insert(file.cid.as_str()) {
+                continue;
+            }
+            folder.total_files += 1;
+
+            let problem = match self.check_file(&file.cid, file.size_bytes, local).await {
+                Ok(()) => {
+                    folder.files_ok += 1;
+                    continue;
+                }
+                Err(problem) => problem,
+            };
+
+            log::warn!("Scrub: {} ({}) {}", file.path, file.cid, problem);
+
+            if !connected {
+                self.connect_source(&snapshot.source_peer_id).await;
+                connected = true;
+            }
+
+            let repaired = match self.api_client.request_network_download(&file.cid).await {
+                Ok(()) => self
+                    .check_file(
+                        &file.cid,
+                        file.size_bytes,
+                        &HashSet::from([file.cid.clone()]),
+                    )
+                    .await
+                    .is_ok(),
+                Err(e) => {
+                    log::warn!("Scrub: refetch of {} failed: {}", file.cid, e);
+                    false
+                }
+            };
+
+            if repaired {
+                folder.files_repaired += 1;
+            } else {
+                folder.files_damaged += 1;
+            }
+            folder.issues.push(ScrubFileIssue {
+                path: file.path.clone(),
+                cid: file.cid.clone(),
+                problem,
+                repaired,
+            });
+        }
+
+        folder.checked_at = Utc::now();
+        folder.health = folder_health(&folder);
+        folder
+    }
+
+    /// Confirm a CID is held locally and reads back with the expected size
+    async fn check_file(
+        &self,
+        cid: &str,
+        size_bytes: u64,
+        local: &HashSet<String>,
+    ) -> std::result::Result<(), String> {
+        if !local.contains(cid) {
+            return Err("missing from local storage".to_string());
+        }
+        match self.api_client.read_file_len(cid).await {
+            Ok(len) if len == size_bytes => Ok(()),
+            Ok(len) => Err(format!("read {} bytes, expected {}", len, size_bytes)),
+            Err(e) => Err(format!("unreadable: {}", e)),
+        }
+    }
+
+    /// Connect to the folder's source peer so missing CIDs can be fetched
+    async fn connect_source(&self, source_peer_id: &str) {
+        let Some(multiaddr) = self
+            .daemon
+            .source_peer_config(source_peer_id)
+            .await
+            .and_then(|p| p.multiaddr)
+        else {
+            return;
+        };
+
+        if let Err(e) = self
+            .api_client
+            .connect_peer(source_peer_id, &multiaddr)
+            .await
+        {
+            log::warn!(
+                "Scrub: failed to connect to source peer {}: {} (will try refetch anyway)",
+                source_peer_id,
+                e
+            );
+        }
+    }
+}
+
+/// Overall health of a checked folder
+fn folder_health(folder: &FolderHealthReport) -> FolderHealth {
+    if folder.error.is_some() || folder.files_damaged > 0 {
+        FolderHealth::Damaged
+    } else if folder.files_repaired > 0 {
+        FolderHealth::Repaired
+    } else {
+        FolderHealth::Healthy
+    }
+}
+
+/// Whether a scheduled scrub should run now: never when the interval is 0,
+/// right away if none ran yet, otherwise once the interval has passed
+fn scrub_due(interval_hours: u32, last_scrub: Option<Instant>, now: Instant) -> bool {
+    if interval_hours == 0 {
+        return false;
+    }
+    last_scrub.map_or(true, |last| {
+        now.saturating_duration_since(last) >= Duration::from_secs(interval_hours as u64 * 3600)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folder(id: &str) -> FolderHealthReport {
+        FolderHealthReport {
+            source_peer_id: "peer".to_string(),
+            folder_id: id.to_string(),
+            manifest_cid: "zManifest".to_string(),
+            sequence_number: 1,
+            health: FolderHealth::Healthy,
+            total_files: 3,
+            files_ok: 3,
+            files_repaired: 0,
+            files_damaged: 0,
+            issues: Vec::new(),
+            error: None,
+            checked_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_scrub_due() {
+        let now = Instant::now();
+        assert!(!scrub_due(0, None, now));
+        assert!(scrub_due(24, None, now));
+
+        let last = Some(now);
+        assert!(!scrub_due(24, last, now + Duration::from_secs(3600)));
+        // Shortening the interval takes effect without waiting out the old one
+        assert!(scrub_due(1, last, now + Duration::from_secs(3600)));
+        assert!(!scrub_due(0, last, now + Duration::from_secs(100 * 3600)));
+    }
+
+    #[test]
+    fn test_folder_health() {
+        let mut f = folder("docs");
+        assert_eq!(folder_health(&f), FolderHealth::Healthy);
+
+        f.files_repaired = 1;
+        assert_eq!(folder_health(&f), FolderHealth::Repaired);
+
+        f.files_damaged = 1;
+        assert_eq!(folder_health(&f), FolderHealth::Damaged);
+
+        let mut missing_manifest = folder("photos");
+        missing_manifest.error = Some("Manifest unavailable".to_string());
+        assert_eq!(folder_health(&missing_manifest), FolderHealth::Damaged);
+    }
+
+    #[test]
+    fn test_upsert_folder_replaces_same_folder() {
+        let mut report = IntegrityReport::default();
+        report.upsert_folder(folder("docs"));
+        report.upsert_folder(folder("photos"));
+
+        let mut rescanned = folder("docs");
+        rescanned.files_damaged = 2;
+        report.upsert_folder(rescanned);
+
+        assert_eq!(report.folders.len(), 2);
+        assert_eq!(report.folders[0].files_damaged, 2);
+    }
+}
@@ -263,7 +263,17 @@ impl SyncService {
             ));
         }
 
-        let id = Uuid::new_v4().to_string();
+        // Reuse the folder ID and manifest sequence if this path was advertised
+        // before, so backup peers see a continuous sequence after a restart
+        let previous_manifest = match &self.manifest_registry {
+            Some(registry) => registry.read().await.find_by_path(path),
+            None => None,
+        };
+
+        let id = previous_manifest
+            .as_ref()
+            .map(|m| m.folder_id.clone())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
 
         // Count files in folder
         let (file_count, total_size) = self.scan_folder_stats(path_buf)?;
@@ -276,14 +286,28 @@ impl SyncService {
             total_size_bytes: total_size,
             last_synced: None,
             status: FolderStatus::Idle,
-            manifest_cid: None,
-            manifest_sequence: 0,
-            manifest_updated_at: None,
+            manifest_cid: previous_manifest.as_ref().map(|m| m.manifest_cid.clone()),
+            manifest_sequence: previous_manifest
+                .as_ref()
+                .map(|m| m.sequence_number)
+                .unwrap_or(0),
+            manifest_updated_at: previous_manifest
+                .as_ref()
+                .and_then(|m| DateTime::parse_from_rfc3339(&m.updated_at).ok())
+                .map(|dt| dt.with_timezone(&Utc)),
             backup_synced_at: None,
             backup_ack_received: false,
             pending_retry: false,
         };
 
+        if let Some(previous) = &previous_manifest {
+            log::info!(
+                "Restored manifest state for {} from registry (seq {})",
+                path,
+                previous.sequence_number
+            );
+        }
+
         // Add to watcher if available
         if let Some(ref mut watcher) = self.watcher {
             watcher
@@ -322,6 +346,11 @@ impl SyncService {
         // Remove from synced files
         self.synced_files.retain(|p| !p.starts_with(&folder.path));
 
+        // Stop advertising the folder to backup peers
+        if let Some(registry) = &self.manifest_registry {
+            registry.write().await.unregister(folder_id);
+        }
+
         log::info!("Removed watched folder: {}", folder.path);
         Ok(())
     }
@@ -825,10 +854,14 @@ impl SyncService {
         Ok(cid)
     }
 
-    /// Mark manifest as acknowledged by backup server
-    #[allow(dead_code)]
-    pub fn acknowledge_manifest(&mut self, folder_id: &str) -> Result<()> {
-        if let Some(folder) = self.folders.get_mut(folder_id) {
+    /// Mark manifest as acknowledged by backup server. Acks for a manifest
+    /// other than the folder's current one are ignored.
+    pub fn acknowledge_manifest(&mut self, folder_id: &str, manifest_cid: &str) -> Result<()> {
+        if let Some(folder) = self
+            .folders
+            .get_mut(folder_id)
+            .filter(|f| f.manifest_cid.as_deref() == Some(manifest_cid))
+        {
             folder.backup_ack_received = true;
             folder.pending_retry = false;
             folder.backup_synced_at = Some(Utc::now());
@@ -879,10 +912,44 @@ impl SyncManager {
     }
 
     /// Mark manifest as successfully notified (clear pending_retry)
-    #[allow(dead_code)]
-    pub async fn mark_manifest_notified(&self, folder_id: &str) -> Result<()> {
+    pub async fn mark_manifest_notified(&self, folder_id: &str, manifest_cid: &str) -> Result<()> {
         let mut sync = self.sync_service.write().await;
-        sync.acknowledge_manifest(folder_id)
+        sync.acknowledge_manifest(folder_id, manifest_cid)
+    }
+
+    /// Mark folders whose current manifest a backup peer has acknowledged
+    /// (recorded by the manifest server) with a verified signature and every
+    /// file stored
+    async fn apply_backup_acks(&self) {
+        let acked: Vec<(String, String)> = {
+            let sync = self.sync_service.read().await;
+            let Some(registry) = &sync.manifest_registry else {
+                return;
+            };
+            let registry = registry.read().await;
+            sync.folders
+                .values()
+                .filter(|f| !f.backup_ack_received)
+                .filter_map(|f| {
+                    let manifest_cid = f.manifest_cid.as_ref()?;
+                    registry
+                        .get_acks(&f.id)
+                        .iter()
+                        .any(|r| {
+                            r.verified
+                                && &r.ack.manifest_cid == manifest_cid
+                                && r.ack.files_failed == 0
+                        })
+                        .then(|| (f.id.clone(), manifest_cid.clone()))
+                })
+                .collect()
+        };
+
+        for (folder_id, manifest_cid) in acked {
+            if let Err(e) = self.mark_manifest_notified(&folder_id, &manifest_cid).await {
+                log::warn!("Failed to record backup ack for {}: {}", folder_id, e);
+            }
+        }
     }
 
     /// Start background sync processing
@@ -918,6 +985,8 @@ impl SyncManager {
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
 
+            self.apply_backup_acks().await;
+
             let mut sync = self.sync_service.write().await;
             match sync.process_queue().await {
                 Ok(count) => {
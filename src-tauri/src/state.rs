@@ -5,9 +5,10 @@ use tokio::sync::RwLock;
 use crate::node_api::NodeApiClient;
 use crate::services::node::NodeConfig;
 use crate::services::{
-    BackupDaemon, BackupService, ConfigService, FileService, ManifestRegistry, ManifestServer,
-    ManifestServerConfig, MediaDownloadService, MediaStreamingConfig, MediaStreamingServer,
-    NodeService, PeerService, SyncService,
+    BackupDaemon, BackupService, ConfigService, DiscoveryService, FileService, IntegrityScrubber,
+    ManifestRegistry, ManifestServer, ManifestServerConfig, MediaDownloadService,
+    MediaStreamingConfig, MediaStreamingServer, NodeService, PeerService, RequestAuditLog,
+    RestoreService, SyncService,
 };
 
 /// Global application state managed by Tauri
@@ -23,6 +24,10 @@ pub struct AppState {
     pub manifest_server: Arc<RwLock<ManifestServer>>,
     pub media: Arc<RwLock<MediaDownloadService>>,
     pub media_streaming: Arc<RwLock<MediaStreamingServer>>,
+    pub discovery: Arc<RwLock<DiscoveryService>>,
+    pub request_audit: Arc<RequestAuditLog>,
+    pub restore: Arc<RestoreService>,
+    pub scrubber: Arc<IntegrityScrubber>,
 }
 
 impl AppState {
@@ -51,9 +56,12 @@ impl AppState {
         // Create backup service with API client and peer service
         let backup_service = BackupService::new(api_client.clone(), peers.clone());
 
+        // Create restore service (rebuilds backed-up folders on disk)
+        let restore = Arc::new(RestoreService::new(api_client.clone()));
+
         // Create backup daemon with API client and config
         let backup_daemon = Arc::new(BackupDaemon::new(
-            api_client,
+            api_client.clone(),
             app_config.backup_server.enabled,
             app_config.backup_server.poll_interval_secs,
             app_config.backup_server.max_concurrent_downloads,
@@ -64,12 +72,22 @@ impl AppState {
 
         // Source peers will be configured when backup daemon starts (in lib.rs setup)
 
-        // Create manifest registry (shared between sync service and manifest server)
-        let manifest_registry = Arc::new(RwLock::new(ManifestRegistry::new()));
+        // Create integrity scrubber (checks backed-up content in local storage)
+        let scrubber = Arc::new(IntegrityScrubber::new(
+            api_client.clone(),
+            backup_daemon.clone(),
+        ));
+
+        // Create manifest registry (shared between sync service and manifest server),
+        // restoring previously advertised manifests from disk
+        let manifest_registry = Arc::new(RwLock::new(ManifestRegistry::load_default()));
 
         // Create sync service with manifest registry for auto-registration
         let sync_service = SyncService::with_manifest_registry(manifest_registry.clone());
 
+        // Shared audit log for requests to the embedded HTTP servers
+        let request_audit = Arc::new(RequestAuditLog::load_default());
+
         // Create manifest server with config from settings
         let mut allowed_ips = std::collections::HashSet::new();
         for ip_str in &app_config.manifest_server.allowed_ips {
@@ -87,10 +105,18 @@ impl AppState {
             port: app_config.manifest_server.port,
             enabled: app_config.manifest_server.enabled,
             allowed_ips,
+            rate_limit: app_config.manifest_server.rate_limit.clone(),
+            content_rate_limit: app_config.manifest_server.content_rate_limit.clone(),
+            ack_secret: app_config.manifest_server.ack_secret.clone(),
+            http_fallback_enabled: app_config.manifest_server.http_fallback_enabled,
         };
 
-        let manifest_server =
-            ManifestServer::with_config(manifest_registry.clone(), manifest_server_config);
+        let manifest_server = ManifestServer::with_config(
+            manifest_registry.clone(),
+            manifest_server_config,
+            request_audit.clone(),
+            api_client.clone(),
+        );
         let manifest_server = Arc::new(RwLock::new(manifest_server));
 
         // Create media download service
@@ -101,10 +127,12 @@ impl AppState {
         // Create media streaming server (shares media download service for library)
         let streaming_config = MediaStreamingConfig {
             port: app_config.media_streaming.port,
+            rate_limit: app_config.media_streaming.rate_limit.clone(),
         };
         let media_streaming = Arc::new(RwLock::new(MediaStreamingServer::new(
             streaming_config,
             media.clone(),
+            request_audit.clone(),
         )));
 
         Self {
@@ -119,6 +147,10 @@ impl AppState {
             manifest_server,
             media,
             media_streaming,
+            discovery: Arc::new(RwLock::new(DiscoveryService::new())),
+            request_audit,
+            restore,
+            scrubber,
         }
     }
 }